@@ -0,0 +1,193 @@
+//! # gltf
+//!
+//! Writer for [glTF 2.0](https://registry.khronos.org/glTF/specs/2.0/glTF-2.0.html),
+//! either as a binary GLB or as a .gltf JSON file with the buffer embedded.
+
+use base64::Engine;
+use serde_json::json;
+use serde_json::Value;
+
+use std::fs::File;
+use std::io::Write;
+
+const ARRAY_BUFFER: u32 = 34962;
+const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+const COMPONENT_FLOAT: u32 = 5126;
+const COMPONENT_UNSIGNED_INT: u32 = 5125;
+const MODE_TRIANGLES: u32 = 4;
+
+const GLB_MAGIC: u32 = 0x4654_6C67;
+const GLB_CHUNK_JSON: u32 = 0x4E4F_534A;
+const GLB_CHUNK_BIN: u32 = 0x004E_4942;
+
+/// A triangle mesh ready to be written, its coordinates are relative
+/// to `translation` (and z-up, the y-up rotation is added in the nodes).
+pub struct Mesh {
+    pub positions: Vec<[f32; 3]>,
+    pub normals: Option<Vec<[f32; 3]>>,
+    pub colours: Option<Vec<[f32; 3]>>,
+    pub indices: Vec<[u32; 3]>,
+    pub translation: [f64; 3],
+}
+
+/// Hypsometric colour ramp (green-yellow-brown-white), `t` in [0, 1].
+pub fn colour_ramp(t: f64) -> [f32; 3] {
+    let stops: [(f64, [f64; 3]); 5] = [
+        (0.00, [0.05, 0.40, 0.20]),
+        (0.25, [0.45, 0.70, 0.30]),
+        (0.50, [0.90, 0.85, 0.45]),
+        (0.75, [0.60, 0.40, 0.25]),
+        (1.00, [1.00, 1.00, 1.00]),
+    ];
+    let t = if t.is_nan() { 0.0 } else { t.clamp(0.0, 1.0) };
+    for w in stops.windows(2) {
+        let (t0, c0) = w[0];
+        let (t1, c1) = w[1];
+        if t <= t1 {
+            let f = (t - t0) / (t1 - t0);
+            return [
+                (c0[0] + f * (c1[0] - c0[0])) as f32,
+                (c0[1] + f * (c1[1] - c0[1])) as f32,
+                (c0[2] + f * (c1[2] - c0[2])) as f32,
+            ];
+        }
+    }
+    [1.0, 1.0, 1.0]
+}
+
+/// Append a VEC3 float attribute to the buffer, returns the index of its accessor.
+fn add_vec3(
+    data: &[[f32; 3]],
+    minmax: bool,
+    buffer: &mut Vec<u8>,
+    views: &mut Vec<Value>,
+    accessors: &mut Vec<Value>,
+) -> usize {
+    let offset = buffer.len();
+    let mut min = [f32::MAX; 3];
+    let mut max = [f32::MIN; 3];
+    for p in data {
+        for i in 0..3 {
+            buffer.extend_from_slice(&p[i].to_le_bytes());
+            min[i] = min[i].min(p[i]);
+            max[i] = max[i].max(p[i]);
+        }
+    }
+    views.push(json!({
+        "buffer": 0,
+        "byteOffset": offset,
+        "byteLength": buffer.len() - offset,
+        "target": ARRAY_BUFFER,
+    }));
+    let mut a = json!({
+        "bufferView": views.len() - 1,
+        "componentType": COMPONENT_FLOAT,
+        "count": data.len(),
+        "type": "VEC3",
+    });
+    if minmax {
+        a["min"] = json!(min);
+        a["max"] = json!(max);
+    }
+    accessors.push(a);
+    accessors.len() - 1
+}
+
+/// Write the mesh to `path`, as GLB if `binary` is true or otherwise
+/// as a .gltf file with the buffer embedded as a base64 data URI.
+pub fn write(path: &str, mesh: &Mesh, binary: bool) -> std::io::Result<()> {
+    let mut buffer: Vec<u8> = Vec::new();
+    let mut views: Vec<Value> = Vec::new();
+    let mut accessors: Vec<Value> = Vec::new();
+    let mut attributes = serde_json::Map::new();
+    let a = add_vec3(
+        &mesh.positions,
+        true,
+        &mut buffer,
+        &mut views,
+        &mut accessors,
+    );
+    attributes.insert("POSITION".to_string(), json!(a));
+    if let Some(ns) = &mesh.normals {
+        let a = add_vec3(ns, false, &mut buffer, &mut views, &mut accessors);
+        attributes.insert("NORMAL".to_string(), json!(a));
+    }
+    if let Some(cs) = &mesh.colours {
+        let a = add_vec3(cs, false, &mut buffer, &mut views, &mut accessors);
+        attributes.insert("COLOR_0".to_string(), json!(a));
+    }
+    //-- indices
+    let offset = buffer.len();
+    let mut imax: u32 = 0;
+    for tr in &mesh.indices {
+        for i in tr {
+            buffer.extend_from_slice(&i.to_le_bytes());
+            imax = imax.max(*i);
+        }
+    }
+    views.push(json!({
+        "buffer": 0,
+        "byteOffset": offset,
+        "byteLength": buffer.len() - offset,
+        "target": ELEMENT_ARRAY_BUFFER,
+    }));
+    accessors.push(json!({
+        "bufferView": views.len() - 1,
+        "componentType": COMPONENT_UNSIGNED_INT,
+        "count": mesh.indices.len() * 3,
+        "type": "SCALAR",
+        "min": [0],
+        "max": [imax],
+    }));
+    let indices = accessors.len() - 1;
+    //-- the buffer itself
+    let mut jbuffer = json!({ "byteLength": buffer.len() });
+    if !binary {
+        jbuffer["uri"] = json!(format!(
+            "data:application/octet-stream;base64,{}",
+            base64::engine::general_purpose::STANDARD.encode(&buffer)
+        ));
+    }
+    //-- glTF is y-up: the root node rotates -90deg around the x-axis, and
+    //-- the child node has the offset to the local origin of the mesh
+    let h = std::f64::consts::FRAC_1_SQRT_2;
+    let j = json!({
+        "asset": { "version": "2.0", "generator": "startinpy" },
+        "scene": 0,
+        "scenes": [ { "nodes": [0] } ],
+        "nodes": [
+            { "children": [1], "rotation": [-h, 0.0, 0.0, h] },
+            { "mesh": 0, "translation": mesh.translation },
+        ],
+        "meshes": [ {
+            "primitives": [ {
+                "attributes": attributes,
+                "indices": indices,
+                "mode": MODE_TRIANGLES,
+            } ]
+        } ],
+        "buffers": [ jbuffer ],
+        "bufferViews": views,
+        "accessors": accessors,
+    });
+    let mut fo = File::create(path)?;
+    if !binary {
+        write!(fo, "{}", j)?;
+        return Ok(());
+    }
+    //-- GLB: header + JSON chunk (padded with spaces) + BIN chunk (padded with 0s)
+    let mut sj = j.to_string().into_bytes();
+    sj.resize(sj.len().div_ceil(4) * 4, b' ');
+    buffer.resize(buffer.len().div_ceil(4) * 4, 0);
+    let total = 12 + 8 + sj.len() + 8 + buffer.len();
+    fo.write_all(&GLB_MAGIC.to_le_bytes())?;
+    fo.write_all(&2_u32.to_le_bytes())?;
+    fo.write_all(&(total as u32).to_le_bytes())?;
+    fo.write_all(&(sj.len() as u32).to_le_bytes())?;
+    fo.write_all(&GLB_CHUNK_JSON.to_le_bytes())?;
+    fo.write_all(&sj)?;
+    fo.write_all(&(buffer.len() as u32).to_le_bytes())?;
+    fo.write_all(&GLB_CHUNK_BIN.to_le_bytes())?;
+    fo.write_all(&buffer)?;
+    Ok(())
+}
@@ -0,0 +1,532 @@
+//! # interpolation
+//!
+//! The weights of the vertices contributing to the interpolation at a location, for
+//! the same methods as startin (NN, TIN, Laplace, NNI, IDW). The estimation is
+//! the weighted sum of the values at the vertices, which can be the z-values or
+//! the extra attributes.
+//!
+//! Kriging is also a weighted sum, the weights are obtained by solving the ordinary
+//! kriging system for the neighbours of the location (see [`crate::kriging`]).
+//!
+//! The smooth (C1) interpolants SibsonC1 and CloughTocher are not a weighted sum of the
+//! values, they use also the gradients at the vertices (estimated from their neighbours).
+
+use startin::geom;
+use startin::StartinError;
+
+use crate::kriging;
+use crate::search;
+
+/// The interpolation methods, with their parameters (named as in startin).
+#[allow(clippy::upper_case_acronyms)]
+pub enum Method {
+    NN,
+    TIN,
+    Laplace,
+    NNI {
+        precompute: bool,
+    },
+    IDW(Idw),
+    SibsonC1,
+    CloughTocher,
+    Kriging {
+        variogram: kriging::Variogram,
+        neighbours: Neighbourhood,
+    },
+}
+
+/// The parameters of IDW: the neighbours are searched in a circle (or an ellipse if
+/// anisotropic), and they can be balanced in sectors around the location.
+pub struct Idw {
+    pub power: f64,
+    /// The search radius (the major radius of the ellipse), None for no limit.
+    pub radius: Option<f64>,
+    /// The minor radius of the ellipse and the angle (in radians, CCW from the x-axis)
+    /// of its major axis.
+    pub anisotropy: Option<(f64, f64)>,
+    /// If fewer neighbours are found, the radius is doubled until enough are found.
+    pub min_points: usize,
+    /// The maximum number of neighbours (the closest ones).
+    pub max_points: Option<usize>,
+    /// The number of sectors (1, 4 or 8), each has at most max_points/sectors neighbours.
+    pub sectors: usize,
+}
+
+/// The neighbours of a location used for kriging.
+pub enum Neighbourhood {
+    /// The natural neighbours of the location.
+    Natural,
+    /// The vertices at most k edges away from the triangle containing the location.
+    Rings(usize),
+}
+
+impl Method {
+    /// Whether the estimation is a weighted sum of the values at the vertices.
+    pub fn has_weights(&self) -> bool {
+        !matches!(self, Method::SibsonC1 | Method::CloughTocher)
+    }
+}
+
+/// The area of the Voronoi cell of each vertex (0 for the removed and the infinite vertex),
+/// to precompute the NNI weights.
+pub fn voronoi_areas(t: &startin::Triangulation) -> Vec<f64> {
+    let n = t.all_vertices().len();
+    let mut areas: Vec<f64> = vec![0.0; n];
+    for (vi, a) in areas.iter_mut().enumerate().skip(1) {
+        if t.is_vertex_removed(vi) == Ok(false) {
+            *a = t.voronoi_cell_area(vi, true).unwrap_or(0.0);
+        }
+    }
+    areas
+}
+
+/// The contributing vertices and their weights (their sum is 1.0) for the
+/// interpolation at p.
+/// `vorareas` are the precomputed Voronoi areas for NNI (see [`voronoi_areas`]).
+pub fn weights(
+    t: &mut startin::Triangulation,
+    method: &Method,
+    p: [f64; 2],
+    vorareas: Option<&[f64]>,
+) -> Result<Vec<(usize, f64)>, StartinError> {
+    if t.convex_hull().is_empty() {
+        return Err(StartinError::EmptyTriangulation);
+    }
+    match method {
+        Method::SibsonC1 => weights(t, &Method::NNI { precompute: false }, p, vorareas),
+        Method::CloughTocher => weights(t, &Method::TIN, p, vorareas),
+        Method::NN => {
+            let vi = t.closest_point(p[0], p[1])?;
+            Ok(vec![(vi, 1.0)])
+        }
+        Method::TIN => {
+            let tr = t.locate(p[0], p[1])?;
+            let q = [p[0], p[1], 0.0];
+            let pts: Vec<Vec<f64>> = tr.v.iter().map(|v| t.get_point(*v).unwrap()).collect();
+            let a = [
+                geom::area2d_triangle(&q, &pts[1], &pts[2]),
+                geom::area2d_triangle(&q, &pts[2], &pts[0]),
+                geom::area2d_triangle(&q, &pts[0], &pts[1]),
+            ];
+            let total: f64 = a.iter().sum();
+            Ok((0..3).map(|i| (tr.v[i], a[i] / total)).collect())
+        }
+        Method::Laplace => {
+            let (nns, ws) = match natural_neighbours(t, p, laplace_weights)? {
+                Natural::Vertex(vi) => return Ok(vec![(vi, 1.0)]),
+                Natural::Neighbours(nns, ws) => (nns, ws),
+            };
+            normalise(nns.into_iter().zip(ws).collect())
+        }
+        Method::NNI { .. } => {
+            //-- the areas of the Voronoi cells with the location inserted, the last one is its own
+            let (nns, ws) = match natural_neighbours(t, p, |t, pi, nns| {
+                let mut areas: Vec<f64> = nns
+                    .iter()
+                    .map(|nn| t.voronoi_cell_area(*nn, true).unwrap())
+                    .collect();
+                areas.push(t.voronoi_cell_area(pi, true).unwrap());
+                areas
+            })? {
+                Natural::Vertex(vi) => return Ok(vec![(vi, 1.0)]),
+                Natural::Neighbours(nns, ws) => (nns, ws),
+            };
+            //-- the stolen areas, now that the location is removed
+            let newarea = ws[nns.len()];
+            Ok(nns
+                .iter()
+                .zip(ws.iter())
+                .map(|(nn, a)| {
+                    let before = match vorareas {
+                        Some(v) => v[*nn],
+                        None => t.voronoi_cell_area(*nn, true).unwrap(),
+                    };
+                    (*nn, (before - a) / newarea)
+                })
+                .collect())
+        }
+        Method::Kriging {
+            variogram,
+            neighbours,
+        } => Ok(kriging_weights(t, variogram, neighbours, p)?.0),
+        Method::IDW(idw) => {
+            //-- beyond that radius all the vertices are in the search ellipse
+            let b = t.get_bbox();
+            let reach = [[b[0], b[1]], [b[0], b[3]], [b[2], b[1]], [b[2], b[3]]]
+                .iter()
+                .map(|c| ((c[0] - p[0]).powi(2) + (c[1] - p[1]).powi(2)).sqrt())
+                .fold(0.0, f64::max)
+                * idw
+                    .anisotropy
+                    .map_or(1.0, |(minor, _)| idw.radius.unwrap() / minor);
+            let mut radius = idw.radius;
+            let mut ns = idw_neighbours(t, idw, p, radius);
+            while ns.len() < idw.min_points {
+                radius = match radius {
+                    Some(r) if r < reach => Some(2.0 * r),
+                    Some(_) => None,
+                    None => break,
+                };
+                ns = idw_neighbours(t, idw, p, radius);
+            }
+            if ns.is_empty() {
+                return Err(StartinError::SearchCircleEmpty);
+            }
+            let snaptol = t.get_snap_tolerance();
+            let mut ws: Vec<(usize, f64)> = Vec::with_capacity(ns.len());
+            for (vi, d) in ns {
+                if geom::distance2d(&p, &t.get_point(vi).unwrap()) <= snaptol {
+                    return Ok(vec![(vi, 1.0)]);
+                }
+                ws.push((vi, d.powf(-idw.power)));
+            }
+            normalise(ws)
+        }
+    }
+}
+
+/// The neighbours for IDW at p (with their distance, anisotropic if an ellipse is used),
+/// by increasing distance. The radius can be larger than the one of `idw` when it grows.
+fn idw_neighbours(
+    t: &mut startin::Triangulation,
+    idw: &Idw,
+    p: [f64; 2],
+    radius: Option<f64>,
+) -> Vec<(usize, f64)> {
+    let r = radius.unwrap_or(f64::INFINITY);
+    let (cos, sin, ratio) = match idw.anisotropy {
+        Some((minor, angle)) => (angle.cos(), angle.sin(), idw.radius.unwrap() / minor),
+        None => (1.0, 0.0, 1.0),
+    };
+    let max = idw.max_points.unwrap_or(usize::MAX);
+    let cap = if idw.sectors > 1 {
+        max.div_ceil(idw.sectors)
+    } else {
+        usize::MAX
+    };
+    let mut k = max.saturating_mul(2);
+    loop {
+        //-- the vertices by increasing euclidean distance, which is <= the anisotropic one:
+        //-- all the vertices closer (anisotropically) than the last one (euclidean) are known
+        let (ns, known) = match idw.max_points {
+            None => (search::in_radius(t, p, r), f64::INFINITY),
+            Some(_) => {
+                let ns = search::nearest_k(t, p, k);
+                let known = match ns.last() {
+                    Some(vi) if ns.len() == k => geom::distance2d(&p, &t.get_point(*vi).unwrap()),
+                    _ => f64::INFINITY,
+                };
+                (ns, known)
+            }
+        };
+        let mut cands: Vec<(usize, f64, usize)> = ns
+            .into_iter()
+            .filter_map(|vi| {
+                let q = t.get_point(vi).unwrap();
+                let (dx, dy) = (q[0] - p[0], q[1] - p[1]);
+                let u = dx * cos + dy * sin;
+                let v = (dy * cos - dx * sin) * ratio;
+                let d = (u * u + v * v).sqrt();
+                let a = v.atan2(u).rem_euclid(2.0 * std::f64::consts::PI);
+                let sector = ((a / (2.0 * std::f64::consts::PI) * idw.sectors as f64) as usize)
+                    .min(idw.sectors - 1);
+                (d <= r).then_some((vi, d, sector))
+            })
+            .collect();
+        cands.sort_by(|a, b| a.1.total_cmp(&b.1));
+        let mut re: Vec<(usize, f64)> = Vec::new();
+        let mut counts: Vec<usize> = vec![0; idw.sectors];
+        let mut complete = false;
+        for (vi, d, sector) in cands {
+            if d > known {
+                break;
+            }
+            if counts[sector] < cap {
+                re.push((vi, d));
+                counts[sector] += 1;
+                if re.len() == max || counts.iter().all(|c| *c == cap) {
+                    complete = true;
+                    break;
+                }
+            }
+        }
+        //-- with sectors some can remain empty (eg near the convex hull), the search
+        //-- is then limited to the 64*max_points closest vertices
+        if complete || known.is_infinite() || known > r || k >= max.saturating_mul(64) {
+            return re;
+        }
+        k = k.saturating_mul(2);
+    }
+}
+
+/// The ordinary kriging weights for the interpolation at p, and the kriging variance.
+pub fn kriging_weights(
+    t: &mut startin::Triangulation,
+    variogram: &kriging::Variogram,
+    neighbours: &Neighbourhood,
+    p: [f64; 2],
+) -> Result<(Vec<(usize, f64)>, f64), StartinError> {
+    if t.convex_hull().is_empty() {
+        return Err(StartinError::EmptyTriangulation);
+    }
+    let vis: Vec<usize> = match neighbours {
+        Neighbourhood::Natural => {
+            match natural_neighbours(t, p, |_, _, nns| vec![0.0; nns.len()])? {
+                Natural::Vertex(vi) => return Ok((vec![(vi, 1.0)], 0.0)),
+                Natural::Neighbours(nns, _) => nns,
+            }
+        }
+        Neighbourhood::Rings(k) => {
+            let tr = t.locate(p[0], p[1])?;
+            let mut vis: Vec<usize> = tr.v.to_vec();
+            let mut ring: Vec<usize> = tr.v.to_vec();
+            for _ in 0..*k {
+                let mut next: Vec<usize> = Vec::new();
+                for vi in ring {
+                    for w in t.adjacent_vertices_to_vertex(vi).unwrap() {
+                        if w != 0 && !vis.contains(&w) {
+                            vis.push(w);
+                            next.push(w);
+                        }
+                    }
+                }
+                ring = next;
+            }
+            vis
+        }
+    };
+    let pts: Vec<[f64; 2]> = vis
+        .iter()
+        .map(|vi| {
+            let q = t.get_point(*vi).unwrap();
+            [q[0], q[1]]
+        })
+        .collect();
+    let (ws, variance) = kriging::solve(variogram, &pts, p);
+    Ok((vis.into_iter().zip(ws).collect(), variance))
+}
+
+/// The estimation at p of the values of the vertices (`value(t, vi)`), with any method.
+pub fn estimate<F>(
+    t: &mut startin::Triangulation,
+    method: &Method,
+    p: [f64; 2],
+    vorareas: Option<&[f64]>,
+    value: F,
+) -> Result<f64, StartinError>
+where
+    F: Fn(&startin::Triangulation, usize) -> f64,
+{
+    let ws = weights(t, method, p, vorareas)?;
+    match method {
+        Method::SibsonC1 if ws.len() > 1 => Ok(sibson_c1(t, &ws, p, &value)),
+        Method::CloughTocher if ws.iter().all(|(_, w)| *w < 1.0) => {
+            let tr: Vec<usize> = ws.iter().map(|(vi, _)| *vi).collect();
+            Ok(clough_tocher(t, [tr[0], tr[1], tr[2]], p, &value))
+        }
+        _ => Ok(ws.iter().map(|(vi, w)| w * value(t, *vi)).sum()),
+    }
+}
+
+/// The gradient of the values at the vertex vi, the least-squares fit of a plane
+/// through the vertex and its neighbours (weighted by the inverse squared distance).
+pub fn gradient<F>(t: &startin::Triangulation, vi: usize, value: &F) -> [f64; 2]
+where
+    F: Fn(&startin::Triangulation, usize) -> f64,
+{
+    let p = t.get_point(vi).unwrap();
+    let f = value(t, vi);
+    let (mut sxx, mut sxy, mut syy, mut sxf, mut syf) = (0.0, 0.0, 0.0, 0.0, 0.0);
+    for n in t.adjacent_vertices_to_vertex(vi).unwrap() {
+        if n == 0 {
+            continue;
+        }
+        let q = t.get_point(n).unwrap();
+        let (dx, dy) = (q[0] - p[0], q[1] - p[1]);
+        let w = 1.0 / (dx * dx + dy * dy);
+        let df = value(t, n) - f;
+        sxx += w * dx * dx;
+        sxy += w * dx * dy;
+        syy += w * dy * dy;
+        sxf += w * dx * df;
+        syf += w * dy * df;
+    }
+    let det = sxx * syy - sxy * sxy;
+    if det.abs() < 1e-12 {
+        return [0.0, 0.0];
+    }
+    [(syy * sxf - sxy * syf) / det, (sxx * syf - sxy * sxf) / det]
+}
+
+/// Sibson's C1 interpolant (Sibson, 1981): a blend of the NNI (with the Sibson
+/// coordinates `ws`) and of the linear functions at the natural neighbours
+/// (defined by their gradients) weighted by the Sibson coordinates over the distance.
+fn sibson_c1<F>(t: &startin::Triangulation, ws: &[(usize, f64)], p: [f64; 2], value: &F) -> f64
+where
+    F: Fn(&startin::Triangulation, usize) -> f64,
+{
+    let (mut z0, mut xi, mut sxi, mut a1, mut a2, mut beta) = (0.0, 0.0, 0.0, 0.0, 0.0, 0.0);
+    for (vi, l) in ws {
+        let q = t.get_point(*vi).unwrap();
+        let f = value(t, *vi);
+        let g = gradient(t, *vi, value);
+        let r = geom::distance2d(&p, &q);
+        z0 += l * f;
+        xi += l / r * (f + g[0] * (p[0] - q[0]) + g[1] * (p[1] - q[1]));
+        sxi += l / r;
+        a1 += l * r;
+        beta += l * r * r;
+        a2 += l / r;
+    }
+    xi /= sxi;
+    let alpha = a1 / a2;
+    (alpha * z0 + beta * xi) / (alpha + beta)
+}
+
+/// The Clough-Tocher interpolant: the triangle is split in 3 at its centroid, and
+/// a cubic Bézier triangle is built for each, with C1 continuity between them and
+/// with the neighbouring triangles (the cross-boundary derivative is linear along the edges).
+fn clough_tocher<F>(t: &startin::Triangulation, tr: [usize; 3], p: [f64; 2], value: &F) -> f64
+where
+    F: Fn(&startin::Triangulation, usize) -> f64,
+{
+    let pts: Vec<[f64; 2]> = tr
+        .iter()
+        .map(|vi| {
+            let q = t.get_point(*vi).unwrap();
+            [q[0], q[1]]
+        })
+        .collect();
+    let f: Vec<f64> = tr.iter().map(|vi| value(t, *vi)).collect();
+    let g: Vec<[f64; 2]> = tr.iter().map(|vi| gradient(t, *vi, value)).collect();
+    let c = [
+        (pts[0][0] + pts[1][0] + pts[2][0]) / 3.0,
+        (pts[0][1] + pts[1][1] + pts[2][1]) / 3.0,
+    ];
+    let dot = |a: [f64; 2], b: [f64; 2]| a[0] * b[0] + a[1] * b[1];
+    let sub = |a: [f64; 2], b: [f64; 2]| [a[0] - b[0], a[1] - b[1]];
+    //-- b[i][j]: the control point on the edge i-j next to vertex i (j == 3 is the centroid)
+    let mut b = [[0.0; 4]; 3];
+    for i in 0..3 {
+        for j in 0..3 {
+            if i != j {
+                b[i][j] = f[i] + dot(g[i], sub(pts[j], pts[i])) / 3.0;
+            }
+        }
+        b[i][3] = f[i] + dot(g[i], sub(c, pts[i])) / 3.0;
+    }
+    //-- e[i]: the interior control point of the sub-triangle (i, i+1, centroid)
+    let mut e = [0.0; 3];
+    for i in 0..3 {
+        let j = (i + 1) % 3;
+        let edge = sub(pts[j], pts[i]);
+        let l = dot(edge, edge).sqrt();
+        let tg = [edge[0] / l, edge[1] / l];
+        let n = [-tg[1], tg[0]];
+        let v = sub(
+            c,
+            [(pts[i][0] + pts[j][0]) / 2.0, (pts[i][1] + pts[j][1]) / 2.0],
+        );
+        let dn = (dot(g[i], n) + dot(g[j], n)) / 2.0;
+        let dt = 3.0 * (b[j][i] - b[i][j]) / l;
+        e[i] = (b[i][j] + b[j][i]) / 2.0 + (dot(v, n) * dn + dot(v, tg) * dt) / 3.0;
+    }
+    //-- q[i]: the control point on the edge vertex i-centroid next to the centroid
+    let mut q = [0.0; 3];
+    for i in 0..3 {
+        q[i] = (b[i][3] + e[i] + e[(i + 2) % 3]) / 3.0;
+    }
+    let s = (q[0] + q[1] + q[2]) / 3.0;
+    //-- the sub-triangle containing p, and the barycentric coordinates in it
+    for i in 0..3 {
+        let j = (i + 1) % 3;
+        let area = |a: [f64; 2], b: [f64; 2], d: [f64; 2]| {
+            (b[0] - a[0]) * (d[1] - a[1]) - (d[0] - a[0]) * (b[1] - a[1])
+        };
+        let total = area(pts[i], pts[j], c);
+        let u = area(p, pts[j], c) / total;
+        let w = area(pts[i], p, c) / total;
+        let z = 1.0 - u - w;
+        if u >= -1e-12 && w >= -1e-12 && z >= -1e-12 {
+            return f[i] * u.powi(3)
+                + f[j] * w.powi(3)
+                + s * z.powi(3)
+                + 3.0 * b[i][j] * u * u * w
+                + 3.0 * b[j][i] * u * w * w
+                + 3.0 * b[i][3] * u * u * z
+                + 3.0 * b[j][3] * w * w * z
+                + 3.0 * q[i] * u * z * z
+                + 3.0 * q[j] * w * z * z
+                + 6.0 * e[i] * u * w * z;
+        }
+    }
+    f64::NAN
+}
+
+/// The natural neighbours of a location, or the vertex at that location.
+enum Natural {
+    Vertex(usize),
+    Neighbours(Vec<usize>, Vec<f64>),
+}
+
+/// Inserts temporarily p in the DT, and `f` returns the raw weights of its natural
+/// neighbours (in CCW order) before p is removed. If p is a vertex (based on the snap
+/// tolerance) then it is the only contributing vertex.
+fn natural_neighbours<F>(
+    t: &mut startin::Triangulation,
+    p: [f64; 2],
+    f: F,
+) -> Result<Natural, StartinError>
+where
+    F: Fn(&startin::Triangulation, usize, &[usize]) -> Vec<f64>,
+{
+    t.locate(p[0], p[1])?;
+    //-- checked first so that the duplicates handling doesn't modify the z of the vertex
+    let vi = t.closest_point(p[0], p[1])?;
+    let snaptol = t.get_snap_tolerance();
+    if geom::distance2d_squared(&p, &t.get_point(vi).unwrap()) <= snaptol * snaptol {
+        return Ok(Natural::Vertex(vi));
+    }
+    let pi = match t.insert_one_pt(p[0], p[1], 0.0) {
+        Ok(pi) => pi,
+        Err((vi, _)) => return Ok(Natural::Vertex(vi)),
+    };
+    //-- on the boundary of the convex hull the Voronoi cell is unbounded
+    if t.is_vertex_convex_hull(pi) {
+        let _ = t.remove(pi);
+        return Err(StartinError::OutsideConvexHull);
+    }
+    let nns = t.adjacent_vertices_to_vertex(pi).unwrap();
+    let ws = f(t, pi, &nns);
+    let _ = t.remove(pi);
+    Ok(Natural::Neighbours(nns, ws))
+}
+
+/// The Laplace weights: the length of the Voronoi edge divided by the distance.
+fn laplace_weights(t: &startin::Triangulation, pi: usize, nns: &[usize]) -> Vec<f64> {
+    let pp = t.get_point(pi).unwrap();
+    let n = nns.len();
+    let centres: Vec<Vec<f64>> = (0..n)
+        .map(|i| {
+            geom::circle_centre(
+                &pp,
+                &t.get_point(nns[i]).unwrap(),
+                &t.get_point(nns[(i + 1) % n]).unwrap(),
+            )
+        })
+        .collect();
+    (0..n)
+        .map(|i| {
+            let e = geom::distance2d(&centres[i], &centres[(i + n - 1) % n]);
+            e / geom::distance2d(&pp, &t.get_point(nns[i]).unwrap())
+        })
+        .collect()
+}
+
+fn normalise(mut ws: Vec<(usize, f64)>) -> Result<Vec<(usize, f64)>, StartinError> {
+    let total: f64 = ws.iter().map(|(_, w)| w).sum();
+    for w in ws.iter_mut() {
+        w.1 /= total;
+    }
+    Ok(ws)
+}
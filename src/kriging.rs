@@ -0,0 +1,266 @@
+//! # kriging
+//!
+//! Ordinary kriging: the variogram models, the experimental variogram of the z-values
+//! of the vertices (and the fitting of a model to it), and the kriging system for a
+//! set of neighbours, which gives the weights of the neighbours and the kriging variance.
+
+use crate::search;
+
+/// The variogram models.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Model {
+    Spherical,
+    Exponential,
+    Gaussian,
+}
+
+impl Model {
+    pub fn from_name(name: &str) -> Option<Model> {
+        match name {
+            "spherical" => Some(Model::Spherical),
+            "exponential" => Some(Model::Exponential),
+            "gaussian" => Some(Model::Gaussian),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Model::Spherical => "spherical",
+            Model::Exponential => "exponential",
+            Model::Gaussian => "gaussian",
+        }
+    }
+
+    /// The model with a unit sill, h is the distance divided by the range.
+    /// The range of the exponential and gaussian models is the practical range (95% of the sill).
+    fn shape(&self, h: f64) -> f64 {
+        match self {
+            Model::Spherical => {
+                if h >= 1.0 {
+                    1.0
+                } else {
+                    1.5 * h - 0.5 * h * h * h
+                }
+            }
+            Model::Exponential => 1.0 - (-3.0 * h).exp(),
+            Model::Gaussian => 1.0 - (-3.0 * h * h).exp(),
+        }
+    }
+}
+
+/// A variogram: the sill includes the nugget.
+#[derive(Clone, Copy, Debug)]
+pub struct Variogram {
+    pub model: Model,
+    pub sill: f64,
+    pub range: f64,
+    pub nugget: f64,
+}
+
+impl Variogram {
+    pub fn gamma(&self, d: f64) -> f64 {
+        if d == 0.0 {
+            return 0.0;
+        }
+        self.nugget + (self.sill - self.nugget) * self.model.shape(d / self.range)
+    }
+}
+
+/// The experimental variogram of the z-values, with the pairs of vertices at a distance
+/// <= max_distance grouped in `bins` bins of equal width.
+/// Returns for each bin the mean distance of its pairs (the centre of the bin if empty),
+/// the semivariance (NaN if empty), and the number of pairs.
+pub fn experimental(
+    t: &mut startin::Triangulation,
+    bins: usize,
+    max_distance: f64,
+) -> (Vec<f64>, Vec<f64>, Vec<usize>) {
+    let mut sumd: Vec<f64> = vec![0.0; bins];
+    let mut sumg: Vec<f64> = vec![0.0; bins];
+    let mut counts: Vec<usize> = vec![0; bins];
+    for vi in 1..t.all_vertices().len() {
+        if t.is_vertex_removed(vi) != Ok(false) {
+            continue;
+        }
+        let p = t.get_point(vi).unwrap();
+        for vj in search::in_radius(t, [p[0], p[1]], max_distance) {
+            if vj <= vi {
+                continue;
+            }
+            let q = t.get_point(vj).unwrap();
+            let d = ((p[0] - q[0]).powi(2) + (p[1] - q[1]).powi(2)).sqrt();
+            let b = ((d / max_distance * bins as f64) as usize).min(bins - 1);
+            sumd[b] += d;
+            sumg[b] += (p[2] - q[2]).powi(2);
+            counts[b] += 1;
+        }
+    }
+    let width = max_distance / bins as f64;
+    let mut lags: Vec<f64> = Vec::with_capacity(bins);
+    let mut gammas: Vec<f64> = Vec::with_capacity(bins);
+    for b in 0..bins {
+        if counts[b] == 0 {
+            lags.push((b as f64 + 0.5) * width);
+            gammas.push(f64::NAN);
+        } else {
+            lags.push(sumd[b] / counts[b] as f64);
+            gammas.push(sumg[b] / (2.0 * counts[b] as f64));
+        }
+    }
+    (lags, gammas, counts)
+}
+
+/// Fits a model to an experimental variogram, by least squares weighted by the number of
+/// pairs in each bin. For a given range the model is linear in the nugget and the sill, the
+/// range is found by searching (first coarsely, then finely around the best one).
+/// None if there are no pairs.
+pub fn fit(model: Model, lags: &[f64], gammas: &[f64], counts: &[usize]) -> Option<Variogram> {
+    let obs: Vec<(f64, f64, f64)> = lags
+        .iter()
+        .zip(gammas)
+        .zip(counts)
+        .filter(|((h, g), c)| **c > 0 && h.is_finite() && g.is_finite())
+        .map(|((h, g), c)| (*h, *g, *c as f64))
+        .collect();
+    let hmax = obs.iter().map(|o| o.0).fold(0.0, f64::max);
+    if obs.is_empty() || hmax <= 0.0 {
+        return None;
+    }
+    let mut best = fit_range(model, &obs, hmax);
+    let step = 2.0 * hmax / 200.0;
+    for i in 1..=200 {
+        let v = fit_range(model, &obs, i as f64 * step);
+        if v.1 < best.1 {
+            best = v;
+        }
+    }
+    let centre = best.0.range;
+    for i in 0..=100 {
+        let a = centre - step + i as f64 * step / 50.0;
+        if a > 0.0 {
+            let v = fit_range(model, &obs, a);
+            if v.1 < best.1 {
+                best = v;
+            }
+        }
+    }
+    Some(best.0)
+}
+
+/// The best nugget and sill for a given range, and the weighted sum of squared errors.
+fn fit_range(model: Model, obs: &[(f64, f64, f64)], range: f64) -> (Variogram, f64) {
+    let (mut s, mut sf, mut sff, mut sg, mut sfg) = (0.0, 0.0, 0.0, 0.0, 0.0);
+    for (h, g, w) in obs {
+        let f = model.shape(h / range);
+        s += w;
+        sf += w * f;
+        sff += w * f * f;
+        sg += w * g;
+        sfg += w * f * g;
+    }
+    let det = s * sff - sf * sf;
+    let (mut nugget, mut c) = if det.abs() > 1e-12 * s * sff {
+        let c = (s * sfg - sf * sg) / det;
+        ((sg - c * sf) / s, c)
+    } else {
+        (0.0, sfg / sff)
+    };
+    if nugget < 0.0 {
+        nugget = 0.0;
+        c = sfg / sff;
+    }
+    if c < 0.0 {
+        c = 0.0;
+        nugget = sg / s;
+    }
+    let sse: f64 = obs
+        .iter()
+        .map(|(h, g, w)| w * (g - nugget - c * model.shape(h / range)).powi(2))
+        .sum();
+    let v = Variogram {
+        model,
+        sill: nugget + c,
+        range,
+        nugget,
+    };
+    (v, sse)
+}
+
+/// The ordinary kriging weights of the points `pts` for the estimation at p, and the
+/// kriging variance.
+pub fn solve(variogram: &Variogram, pts: &[[f64; 2]], p: [f64; 2]) -> (Vec<f64>, f64) {
+    let n = pts.len();
+    let dist = |a: [f64; 2], b: [f64; 2]| ((a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2)).sqrt();
+    let mut a: Vec<Vec<f64>> = vec![vec![1.0; n + 1]; n + 1];
+    let mut b: Vec<f64> = vec![1.0; n + 1];
+    for i in 0..n {
+        for j in 0..n {
+            a[i][j] = variogram.gamma(dist(pts[i], pts[j]));
+        }
+        b[i] = variogram.gamma(dist(pts[i], p));
+    }
+    a[n][n] = 0.0;
+    //-- the gaussian model without a nugget gives ill-conditioned systems, a small nugget
+    //-- effect (on the diagonal only) is used to stabilise it
+    if variogram.model == Model::Gaussian {
+        let jitter = (1e-4 * variogram.sill - variogram.nugget).max(0.0);
+        for (i, row) in a.iter_mut().enumerate().take(n) {
+            row[i] -= jitter;
+        }
+    }
+    let x = match gauss(a.clone(), b.clone()) {
+        Some(x) => x,
+        None => {
+            //-- singular (eg gaussian model without nugget): a tiny nugget is added
+            let eps = 1e-9 * variogram.sill.abs().max(1.0);
+            for (i, row) in a.iter_mut().enumerate().take(n) {
+                row[i] -= eps;
+            }
+            match gauss(a, b.clone()) {
+                Some(x) => x,
+                None => {
+                    let mut x = vec![1.0 / n as f64; n];
+                    x.push(0.0);
+                    x
+                }
+            }
+        }
+    };
+    let variance: f64 = (0..n).map(|i| x[i] * b[i]).sum::<f64>() + x[n];
+    (x[..n].to_vec(), variance.max(0.0))
+}
+
+/// Solves ax = b with the Gaussian elimination (with partial pivoting), None if singular.
+pub fn gauss(mut a: Vec<Vec<f64>>, mut b: Vec<f64>) -> Option<Vec<f64>> {
+    let n = b.len();
+    let scale = a
+        .iter()
+        .flat_map(|row| row.iter())
+        .fold(0.0_f64, |m, v| m.max(v.abs()));
+    for k in 0..n {
+        let piv = (k..n).max_by(|i, j| a[*i][k].abs().total_cmp(&a[*j][k].abs()))?;
+        if a[piv][k].abs() <= 1e-14 * scale {
+            return None;
+        }
+        a.swap(k, piv);
+        b.swap(k, piv);
+        let (top, bottom) = a.split_at_mut(k + 1);
+        let pivot = &top[k];
+        for (i, row) in bottom.iter_mut().enumerate() {
+            let f = row[k] / pivot[k];
+            if f != 0.0 {
+                for (x, p) in row[k..].iter_mut().zip(&pivot[k..]) {
+                    *x -= f * p;
+                }
+                b[k + 1 + i] -= f * b[k];
+            }
+        }
+    }
+    let mut x = vec![0.0; n];
+    for k in (0..n).rev() {
+        let s: f64 = ((k + 1)..n).map(|j| a[k][j] * x[j]).sum();
+        x[k] = (b[k] - s) / a[k][k];
+    }
+    Some(x)
+}
@@ -0,0 +1,159 @@
+//! # landxml
+//!
+//! Reader and writer for the TIN surfaces of [LandXML 1.2](http://www.landxml.org),
+//! that is `<Surface><Definition surfType="TIN">` with its `<Pnts>` and `<Faces>`.
+//! Notice that in LandXML the points are stored as "northing easting elevation".
+
+use quick_xml::events::Event;
+use quick_xml::Reader;
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Write the surface, the triangles refer to `pts` (0-based), the `<P>` ids are 1-based.
+/// The user IDs (if not empty, one per point) are the names of the points, -1 is no name.
+pub fn write(
+    path: &str,
+    name: &str,
+    units: &str,
+    pts: &[[f64; 3]],
+    trs: &[[usize; 3]],
+    uids: &[i64],
+) -> std::io::Result<()> {
+    let now = chrono::Utc::now();
+    let mut fo = BufWriter::new(File::create(path)?);
+    writeln!(fo, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(
+        fo,
+        r#"<LandXML xmlns="http://www.landxml.org/schema/LandXML-1.2" version="1.2" date="{}" time="{}">"#,
+        now.format("%Y-%m-%d"),
+        now.format("%H:%M:%S")
+    )?;
+    writeln!(fo, "  <Units>")?;
+    match units {
+        "meter" => writeln!(
+            fo,
+            r#"    <Metric linearUnit="meter" areaUnit="squareMeter" volumeUnit="cubicMeter"/>"#
+        )?,
+        _ => writeln!(
+            fo,
+            r#"    <Imperial linearUnit="{}" areaUnit="squareFoot" volumeUnit="cubicYard"/>"#,
+            units
+        )?,
+    }
+    writeln!(fo, "  </Units>")?;
+    writeln!(
+        fo,
+        r#"  <Application name="startinpy" version="{}"/>"#,
+        env!("CARGO_PKG_VERSION")
+    )?;
+    writeln!(fo, "  <Surfaces>")?;
+    writeln!(fo, r#"    <Surface name="{}">"#, escape(name))?;
+    writeln!(fo, r#"      <Definition surfType="TIN">"#)?;
+    writeln!(fo, "        <Pnts>")?;
+    for (i, p) in pts.iter().enumerate() {
+        let pname = match uids.get(i) {
+            Some(u) if *u != -1 => format!(r#" name="{}""#, u),
+            _ => String::new(),
+        };
+        writeln!(
+            fo,
+            r#"          <P id="{}"{}>{} {} {}</P>"#,
+            i + 1,
+            pname,
+            p[1],
+            p[0],
+            p[2]
+        )?;
+    }
+    writeln!(fo, "        </Pnts>")?;
+    writeln!(fo, "        <Faces>")?;
+    for tr in trs {
+        writeln!(
+            fo,
+            "          <F>{} {} {}</F>",
+            tr[0] + 1,
+            tr[1] + 1,
+            tr[2] + 1
+        )?;
+    }
+    writeln!(fo, "        </Faces>")?;
+    writeln!(fo, "      </Definition>")?;
+    writeln!(fo, "    </Surface>")?;
+    writeln!(fo, "  </Surfaces>")?;
+    writeln!(fo, "</LandXML>")?;
+    fo.flush()
+}
+
+/// Read the points (as [x, y, z]) of the `<Pnts>` of a TIN surface, either the one
+/// with the given `name` or the first one in the file.
+pub fn read_points(path: &str, name: Option<&str>) -> Result<Vec<[f64; 3]>, String> {
+    let mut reader = Reader::from_file(path).map_err(|e| e.to_string())?;
+    reader.config_mut().trim_text(true);
+    let mut buf: Vec<u8> = Vec::new();
+    let mut pts: Vec<[f64; 3]> = Vec::new();
+    let mut in_surface = false;
+    let mut in_pnts = false;
+    let mut in_p = false;
+    let mut found = false;
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Err(e) => return Err(e.to_string()),
+            Ok(Event::Eof) => break,
+            Ok(Event::Start(e)) => match e.local_name().as_ref() {
+                b"Surface" => {
+                    let n = match e.try_get_attribute("name") {
+                        Ok(Some(a)) => a.unescape_value().map_err(|e| e.to_string())?.to_string(),
+                        _ => String::new(),
+                    };
+                    in_surface = match name {
+                        Some(s) => s == n,
+                        None => true,
+                    };
+                }
+                b"Pnts" if in_surface => in_pnts = true,
+                b"P" if in_pnts => in_p = true,
+                _ => (),
+            },
+            Ok(Event::Text(t)) if in_p => {
+                let s = t.unescape().map_err(|e| e.to_string())?;
+                let v: Vec<f64> = s
+                    .split_whitespace()
+                    .map(|x| x.parse::<f64>())
+                    .collect::<Result<Vec<f64>, _>>()
+                    .map_err(|_| format!("Invalid point: '{}'", s))?;
+                if v.len() < 3 {
+                    return Err(format!("Invalid point: '{}'", s));
+                }
+                pts.push([v[1], v[0], v[2]]);
+            }
+            Ok(Event::End(e)) => match e.local_name().as_ref() {
+                b"P" => in_p = false,
+                b"Pnts" if in_pnts => {
+                    in_pnts = false;
+                    found = true;
+                }
+                b"Surface" => {
+                    in_surface = false;
+                    if found {
+                        break;
+                    }
+                }
+                _ => (),
+            },
+            _ => (),
+        }
+        buf.clear();
+    }
+    if !found {
+        return Err("No surface with points found".to_string());
+    }
+    Ok(pts)
+}
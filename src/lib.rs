@@ -4,8 +4,12 @@ use pyo3::exceptions;
 use pyo3::prelude::*;
 use pyo3::types::{PyDict, PyList, PyTuple};
 
+use std::collections::HashMap;
 use std::fs::File;
 use std::io::Write;
+use std::ops::{Deref, DerefMut};
+use std::sync::{Mutex, MutexGuard, TryLockError};
+use std::thread::ThreadId;
 
 use geojson::{Feature, FeatureCollection, Geometry, Value as GeoValue};
 use serde::{Deserialize, Serialize};
@@ -13,6 +17,22 @@ use serde_json::json;
 use serde_json::Value;
 use serde_json::{to_value, Map};
 
+mod gltf;
+mod interpolation;
+mod kriging;
+mod landxml;
+mod mesh;
+mod pointcloud;
+mod quality;
+mod quantizedmesh;
+mod refine;
+mod search;
+mod sfc;
+mod streaming;
+mod terrain;
+mod validity;
+mod vtk;
+
 #[derive(Debug, Serialize, Deserialize)]
 struct Cityjson {
     r#type: String,
@@ -58,6 +78,196 @@ fn convert_json_value_to_pyobject(py: Python, value: &Value) -> PyResult<PyObjec
     }
 }
 
+/// The interpolation method (and its parameters) of a JSON/dict interpolant.
+fn interpolation_method(interpolant: &PyDict) -> PyResult<interpolation::Method> {
+    let m: String = match interpolant.get_item("method") {
+        None => return Err(exceptions::PyValueError::new_err("Wrong parameters")),
+        Some(m) => m.extract()?,
+    };
+    match m.as_str() {
+        "IDW" => {
+            let wrong = || Err(exceptions::PyValueError::new_err("Wrong parameters"));
+            let power: f64 = match interpolant.get_item("power") {
+                Some(p) => p.extract()?,
+                None => return wrong(),
+            };
+            let radius: Option<f64> = interpolant
+                .get_item("radius")
+                .map(|r| r.extract())
+                .transpose()?;
+            let k: Option<usize> = interpolant.get_item("k").map(|k| k.extract()).transpose()?;
+            let max_points: Option<usize> = match (
+                k,
+                interpolant
+                    .get_item("max_points")
+                    .map(|m| m.extract::<usize>())
+                    .transpose()?,
+            ) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            };
+            let min_points: usize = match interpolant.get_item("min_points") {
+                Some(m) => m.extract()?,
+                None => 1,
+            };
+            let sectors: usize = match interpolant.get_item("sectors") {
+                Some(s) => s.extract()?,
+                None => 1,
+            };
+            let minor: Option<f64> = interpolant
+                .get_item("minor_radius")
+                .map(|r| r.extract())
+                .transpose()?;
+            let angle: f64 = match interpolant.get_item("angle") {
+                Some(a) => a.extract()?,
+                None => 0.0,
+            };
+            if power.is_nan()
+                || power <= 0.0
+                || radius.is_some_and(|r| r.is_nan() || r <= 0.0)
+                || (radius.is_none() && max_points.is_none())
+                || max_points == Some(0)
+                || max_points.is_some_and(|m| min_points > m)
+                || ![1, 4, 8].contains(&sectors)
+                || (sectors > 1 && max_points.is_none())
+            {
+                return wrong();
+            }
+            let anisotropy = match minor {
+                Some(m) => match radius {
+                    Some(r) if m > 0.0 && m <= r => Some((m, angle.to_radians())),
+                    _ => return wrong(),
+                },
+                None => None,
+            };
+            Ok(interpolation::Method::IDW(interpolation::Idw {
+                power,
+                radius,
+                anisotropy,
+                min_points,
+                max_points,
+                sectors,
+            }))
+        }
+        "Laplace" => Ok(interpolation::Method::Laplace),
+        "NN" => Ok(interpolation::Method::NN),
+        "NNI" => {
+            let precompute: bool = match interpolant.get_item("precompute") {
+                Some(b) => b.extract()?,
+                None => false,
+            };
+            Ok(interpolation::Method::NNI { precompute })
+        }
+        "TIN" => Ok(interpolation::Method::TIN),
+        "SibsonC1" => Ok(interpolation::Method::SibsonC1),
+        "CloughTocher" => Ok(interpolation::Method::CloughTocher),
+        "Kriging" => {
+            let variogram = match interpolant.get_item("variogram") {
+                Some(v) => variogram_from_dict(v.downcast::<PyDict>()?)?,
+                None => return Err(exceptions::PyValueError::new_err("Wrong parameters")),
+            };
+            let neighbours = match interpolant.get_item("neighbours") {
+                None => interpolation::Neighbourhood::Natural,
+                Some(n) => match n.extract::<String>() {
+                    Ok(s) if s == "natural" => interpolation::Neighbourhood::Natural,
+                    Ok(_) => return Err(exceptions::PyValueError::new_err("Wrong parameters")),
+                    Err(_) => interpolation::Neighbourhood::Rings(n.extract::<usize>()?),
+                },
+            };
+            Ok(interpolation::Method::Kriging {
+                variogram,
+                neighbours,
+            })
+        }
+        _ => Err(exceptions::PyValueError::new_err(
+            "Unknown interpolation method",
+        )),
+    }
+}
+
+/// The variogram of a dict {"model": "spherical", "sill": 1.0, "range": 10.0, "nugget": 0.0}.
+fn variogram_from_dict(d: &PyDict) -> PyResult<kriging::Variogram> {
+    let model = match d.get_item("model") {
+        Some(m) => {
+            let m: String = m.extract()?;
+            match kriging::Model::from_name(&m) {
+                Some(model) => model,
+                None => return Err(exceptions::PyValueError::new_err("Unknown variogram model")),
+            }
+        }
+        None => return Err(exceptions::PyValueError::new_err("Wrong parameters")),
+    };
+    let (sill, range) = match (d.get_item("sill"), d.get_item("range")) {
+        (Some(s), Some(r)) => (s.extract::<f64>()?, r.extract::<f64>()?),
+        _ => return Err(exceptions::PyValueError::new_err("Wrong parameters")),
+    };
+    let nugget: f64 = match d.get_item("nugget") {
+        Some(n) => n.extract()?,
+        None => 0.0,
+    };
+    if range.is_nan()
+        || range <= 0.0
+        || nugget.is_nan()
+        || nugget < 0.0
+        || sill.is_nan()
+        || sill < nugget
+    {
+        return Err(exceptions::PyValueError::new_err("Wrong parameters"));
+    }
+    Ok(kriging::Variogram {
+        model,
+        sill,
+        range,
+        nugget,
+    })
+}
+
+/// The vertex indices and their weights.
+type WeightsArrays<'py> = (
+    &'py PyArray<usize, numpy::Ix1>,
+    &'py PyArray<f64, numpy::Ix1>,
+);
+
+/// A sparse matrix in the CSR format: (indptr, indices, data).
+type CsrArrays<'py> = (
+    &'py PyArray<usize, numpy::Ix1>,
+    &'py PyArray<usize, numpy::Ix1>,
+    &'py PyArray<f64, numpy::Ix1>,
+);
+
+/// The experimental variogram: (lags, semivariances, number of pairs).
+type VariogramArrays<'py> = (
+    &'py PyArray<f64, numpy::Ix1>,
+    &'py PyArray<f64, numpy::Ix1>,
+    &'py PyArray<usize, numpy::Ix1>,
+);
+
+/// A raster and its geotransform (as in GDAL).
+type RasterArrays<'py> = (&'py PyArray<u8, numpy::Ix2>, (f64, f64, f64, f64, f64, f64));
+
+/// The message of the exception when the interpolation at loc is impossible.
+fn interpolation_error(why: &startin::StartinError, loc: [f64; 2]) -> String {
+    match why {
+        startin::StartinError::SearchCircleEmpty => {
+            format!("({}, {}) no points in search radius", loc[0], loc[1])
+        }
+        _ => format!("({}, {}) is outside the convex hull", loc[0], loc[1]),
+    }
+}
+
+/// The value of the vertex vi: its z-value if `name` is None, otherwise its extra attribute
+/// (NaN if it doesn't have it).
+fn vertex_value(t: &startin::Triangulation, vi: usize, name: Option<&str>) -> f64 {
+    match name {
+        None => t.get_point(vi).unwrap()[2],
+        Some(name) => t
+            .get_vertex_attributes(vi)
+            .ok()
+            .and_then(|a| a.get(name).and_then(|x| x.as_f64()))
+            .unwrap_or(f64::NAN),
+    }
+}
+
 /// A Delaunay triangulator where the input are 2.5D points,
 /// the DT is computed in 2D but the elevation of the vertices are kept.
 /// This is used mostly for the modelling of terrains.
@@ -66,14 +276,26 @@ fn convert_json_value_to_pyobject(py: Python, value: &Value) -> PyResult<PyObjec
 #[pymodule]
 fn startinpy(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
     m.add_class::<DT>()?;
+    m.add_class::<streaming::StreamingDT>()?;
     Ok(())
 }
 
-#[pyclass(unsendable)]
+#[pyclass]
 /// A Delaunay triangulation (DT), containing vertices+triangles
 pub struct DT {
+    inner: Mutex<Inner>,
+    /// The thread holding the lock, to detect a re-entry (eg from the Python code run
+    /// while an argument is extracted) instead of waiting forever.
+    owner: Mutex<Option<ThreadId>>,
+}
+
+/// The state of a DT, behind its lock: a method has an exclusive access to it, thus
+/// the threads using the same DT wait for each other.
+struct Inner {
     t: startin::Triangulation,
     dtype: Vec<(String, String)>,
+    insert_stats: Value,
+    uids: Vec<i64>,
 }
 
 #[pymethods]
@@ -86,14 +308,19 @@ impl DT {
     fn new(attributes_schema: Option<&PyAny>) -> Self {
         let tmp = startin::Triangulation::new();
         let tmp2 = Vec::new();
-        let mut dt = DT {
+        let mut dt = Inner {
             t: tmp,
             dtype: tmp2,
+            insert_stats: json!({}),
+            uids: Vec::new(),
         };
         if attributes_schema.is_some() {
             let _ = dt.set_attributes_schema(&attributes_schema.unwrap());
         }
-        dt
+        DT {
+            inner: Mutex::new(dt),
+            owner: Mutex::new(None),
+        }
     }
 
     /// Get the points [x, y, z] of all vertices in the DT.
@@ -112,7 +339,8 @@ impl DT {
     /// array([inf, inf, inf])
     #[getter]
     fn points<'py>(&self, py: Python<'py>) -> PyResult<&'py PyArray<f64, numpy::Ix2>> {
-        let vs = self.t.all_vertices();
+        let dt = self.lock()?;
+        let vs = dt.t.all_vertices();
         Ok(PyArray::from_vec2(py, &vs).unwrap())
     }
 
@@ -127,8 +355,9 @@ impl DT {
     /// x-coordinate of first vertex: [25.98 35.12 4.78]
     #[getter]
     fn triangles<'py>(&self, py: Python<'py>) -> PyResult<&'py PyArray<usize, numpy::Ix2>> {
-        let mut trs: Vec<Vec<usize>> = Vec::with_capacity(self.t.number_of_triangles());
-        for each in self.t.all_finite_triangles() {
+        let dt = self.lock()?;
+        let mut trs: Vec<Vec<usize>> = Vec::with_capacity(dt.t.number_of_triangles());
+        for each in dt.t.all_finite_triangles() {
             let mut tr = Vec::with_capacity(3);
             tr.push(each.v[0]);
             tr.push(each.v[1]);
@@ -139,11 +368,13 @@ impl DT {
     }
 
     fn __str__(&self) -> PyResult<String> {
-        Ok(format!("{}", self.t))
+        let dt = self.lock()?;
+        Ok(format!("{}", dt.t))
     }
 
     fn __repr__(&self) -> PyResult<String> {
-        Ok(format!("{}", self.t))
+        let dt = self.lock()?;
+        Ok(format!("{}", dt.t))
     }
 
     /// Insert one new point in the DT.
@@ -166,20 +397,21 @@ impl DT {
     /// >>> dt.insert_one_pt([13.2, 44.1, 74.2], intensity=77.2)
     #[pyo3(signature = (p3, **py_kwargs))]
     fn insert_one_pt(
-        &mut self,
+        &self,
         p3: [f64; 3],
         py_kwargs: Option<&PyDict>,
     ) -> PyResult<(usize, bool, bool)> {
+        let dt = &mut *self.lock()?;
         // Result<usize, (usize, bool)>
-        let re = self.t.insert_one_pt(p3[0], p3[1], p3[2]);
+        let re = dt.inserting(|t| t.insert_one_pt(p3[0], p3[1], p3[2]));
         match re {
             Ok(x) => {
-                let _ = self.set_vertex_attributes(x, py_kwargs);
+                let _ = dt.set_vertex_attributes(x, py_kwargs);
                 return Ok((x, true, true));
             }
             Err((x, b)) => {
                 if b == true {
-                    let _ = self.set_vertex_attributes(x, py_kwargs);
+                    let _ = dt.set_vertex_attributes(x, py_kwargs);
                 }
                 return Ok((x, false, b));
             }
@@ -195,10 +427,14 @@ impl DT {
     /// >>>     t.remove(45)
     /// >>> except Exception as e:
     /// >>>     print(e)
-    fn remove(&mut self, vi: usize) -> PyResult<()> {
-        let re = self.t.remove(vi);
+    fn remove(&self, vi: usize) -> PyResult<()> {
+        let dt = &mut *self.lock()?;
+        let re = dt.t.remove(vi);
         match re {
-            Ok(_x) => return Ok(()),
+            Ok(_x) => {
+                dt.forget_user_id(vi);
+                return Ok(());
+            }
             Err(why) => match why {
                 startin::StartinError::VertexInfinite => {
                     return Err(exceptions::PyIndexError::new_err(
@@ -214,13 +450,113 @@ impl DT {
         };
     }
 
+    /// Remove/delete several vertices from the DT (in one call).
+    ///
+    /// :param indices: an array of vertex indices
+    /// :param ignore_invalid: (default=False) if False, an Exception is thrown (and nothing is
+    ///     removed) if one of the indices is invalid (infinite vertex, vertex doesn't exist or
+    ///     is already removed); if True the invalid indices are skipped
+    /// :param collect_garbage: (default=False) call :func:`collect_garbage` afterwards
+    /// :return: the number of vertices removed
+    ///
+    /// >>> n = dt.remove_many([4, 12, 67])
+    /// 3
+    #[pyo3(signature = (indices, ignore_invalid=false, collect_garbage=false))]
+    fn remove_many(
+        &self,
+        py: Python<'_>,
+        indices: Vec<usize>,
+        ignore_invalid: bool,
+        collect_garbage: bool,
+    ) -> PyResult<usize> {
+        let dt = &mut *self.lock()?;
+        if !ignore_invalid {
+            for vi in &indices {
+                if *vi == 0 {
+                    return Err(exceptions::PyIndexError::new_err(
+                        "Invalid vertex index: cannot remove infinite vertex",
+                    ));
+                }
+                if dt.t.is_vertex_removed(*vi).unwrap_or(true) {
+                    let s = format!("Invalid vertex index: vertex {} doesn't exist", vi);
+                    return Err(exceptions::PyIndexError::new_err(s));
+                }
+            }
+        }
+        Ok(py.allow_threads(|| dt.remove_vertices(indices, collect_garbage)))
+    }
+
+    /// Remove/delete all the vertices inside a polygon.
+    /// The polygon is a ring of [x, y] coordinates (the first and last can be the same),
+    /// holes are not supported.
+    ///
+    /// :param polygon: an array of [x, y] coordinates
+    /// :param collect_garbage: (default=False) call :func:`collect_garbage` afterwards
+    /// :return: the number of vertices removed
+    ///
+    /// >>> n = dt.remove_in_polygon([[30.0, 60.0], [40.0, 60.0], [35.0, 70.0]])
+    #[pyo3(signature = (polygon, collect_garbage=false))]
+    fn remove_in_polygon(
+        &self,
+        py: Python<'_>,
+        polygon: Vec<[f64; 2]>,
+        collect_garbage: bool,
+    ) -> PyResult<usize> {
+        let dt = &mut *self.lock()?;
+        if polygon.len() < 3 {
+            return Err(exceptions::PyValueError::new_err(
+                "polygon must have at least 3 vertices",
+            ));
+        }
+        Ok(py.allow_threads(|| {
+            let vis = search::in_polygon(&mut dt.t, &polygon);
+            dt.remove_vertices(vis, collect_garbage)
+        }))
+    }
+
+    /// Remove/delete the vertices for which the mask is True.
+    /// The mask has the same length as :func:`points`, the infinite vertex and the
+    /// vertices already removed are ignored.
+    ///
+    /// :param mask: an array of booleans
+    /// :param collect_garbage: (default=False) call :func:`collect_garbage` afterwards
+    /// :return: the number of vertices removed
+    ///
+    /// >>> #-- remove the outliers
+    /// >>> n = dt.remove_where(dt.points[:, 2] > 200.0)
+    #[pyo3(signature = (mask, collect_garbage=false))]
+    fn remove_where(
+        &self,
+        py: Python<'_>,
+        mask: Vec<bool>,
+        collect_garbage: bool,
+    ) -> PyResult<usize> {
+        let dt = &mut *self.lock()?;
+        if mask.len() != dt.t.all_vertices().len() {
+            return Err(exceptions::PyValueError::new_err(
+                "mask must have the same length as the points",
+            ));
+        }
+        Ok(py.allow_threads(|| {
+            let vis: Vec<usize> = (1..mask.len()).filter(|i| mask[*i]).collect();
+            dt.remove_vertices(vis, collect_garbage)
+        }))
+    }
+
     /// Insert each point in the array of points (a 2D array) by calling insert_one_pt() for each.
     /// Different insertion strategies can be used: "AsIs" (*default*: inserts points in the order
-    /// given) or "BBox" (inserts first the BBox of the points, which speeds up the construction,
-    /// works especially good for rasters).
+    /// given), "BBox" (inserts first the BBox of the points, which speeds up the construction,
+    /// works especially good for rasters), "Hilbert" (the points are first sorted along
+    /// a Hilbert curve), or "BRIO" (biased randomised insertion order: the points are shuffled
+    /// and inserted in rounds of increasing size, each sorted along a Hilbert curve).
+    /// "Hilbert" and "BRIO" are much faster than "AsIs" if the points have a poor spatial
+    /// coherence (eg if they are randomly shuffled), but since the order of insertion
+    /// is modified, the vertices kept for the xy-duplicates with "First" and "Last"
+    /// (see :func:`startinpy.DT.duplicates_handling`) can differ.
+    /// The statistics of the insertion can be obtained with :func:`insertion_statistics`.
     ///
     /// :param pts: an array of points (which is itself an array)
-    /// :param optional insertionstrategy:  "AsIs" (*default*) or "BBox"
+    /// :param optional insertionstrategy:  "AsIs" (*default*), "BBox", "Hilbert", or "BRIO"
     /// :return: (nothing)
     ///
     /// >>> pts = []
@@ -233,19 +569,129 @@ impl DT {
     /// OR
     /// >>> dt.insert(pts, insertionstrategy="BBox")
     #[pyo3(signature = (pts, insertionstrategy="AsIs"))]
-    fn insert(&mut self, pts: Vec<[f64; 3]>, insertionstrategy: &str) -> PyResult<()> {
-        match insertionstrategy {
-            "AsIs" => self.t.insert(&pts, startin::InsertionStrategy::AsIs),
-            "BBox" => self.t.insert(&pts, startin::InsertionStrategy::BBox),
-            _ => {
-                let s = format!(
-                    "'{}' is an unknown insertion strategy for insert()",
-                    insertionstrategy
-                );
-                return Err(exceptions::PyAttributeError::new_err(s));
+    fn insert(&self, py: Python<'_>, pts: Vec<[f64; 3]>, insertionstrategy: &str) -> PyResult<()> {
+        let dt = &mut *self.lock()?;
+        py.allow_threads(|| dt.insert_with_strategy(pts, insertionstrategy))
+    }
+
+    /// Returns the statistics of the last call to :func:`insert` (or :func:`read_landxml`):
+    /// the strategy used, the number of points, the number of vertices inserted, the number
+    /// of xy-duplicates, the number of rounds (for "BRIO", 1 otherwise), and the time
+    /// (in seconds) used to sort the points and to insert them.
+    ///
+    /// :return: a dictionary (empty if :func:`insert` was never called)
+    ///
+    /// >>> dt.insert(pts, insertionstrategy="BRIO")
+    /// >>> dt.insertion_statistics()
+    /// {'duplicates': 3, 'insert_time': 0.51, 'inserted': 99997, 'points': 100000, 'rounds': 11, 'sort_time': 0.02, 'strategy': 'BRIO'}
+    fn insertion_statistics(&self, py: Python<'_>) -> PyResult<PyObject> {
+        let dt = self.lock()?;
+        convert_json_value_to_pyobject(py, &dt.insert_stats)
+    }
+
+    /// Insert the points of a LAS/LAZ file, read directly (and streamed) from the file.
+    /// The points can be filtered by their classification and their return number,
+    /// and thinned (every nth point is kept).
+    /// Points are inserted as with :func:`insert_one_pt`, and LAS dimensions
+    /// (with the same names as in laspy, eg "intensity", "gps_time", or "red")
+    /// can be stored in the extra attributes of the vertices (the schema must have been set,
+    /// see :func:`set_attributes_schema`).
+    /// Throws an exception if the path is invalid or if the file cannot be read.
+    ///
+    /// :param path: full path (a string) on disk of the LAS/LAZ file
+    /// :param classes: (default=[2]) the classifications kept, None for all
+    /// :param returns: (default=None) the return numbers kept (-1 is the last return), None for all
+    /// :param thin: (default=1) keep every nth point of those that pass the filters
+    /// :param attributes: (default=None) a dictionary mapping the LAS dimensions to the names
+    ///    of the extra attributes in the schema
+    /// :return: the number of points inserted (including the xy-duplicates)
+    ///
+    /// >>> dt = startinpy.DT(np.dtype([("intensity", float)]))
+    /// >>> dt.insert_las("/home/elvis/myfile.laz", classes=[2, 9], attributes={"intensity": "intensity"})
+    /// 18399
+    #[pyo3(signature = (path, classes=vec![2], returns=None, thin=1, attributes=None))]
+    fn insert_las(
+        &self,
+        py: Python<'_>,
+        path: String,
+        classes: Option<Vec<u8>>,
+        returns: Option<Vec<i64>>,
+        thin: usize,
+        attributes: Option<HashMap<String, String>>,
+    ) -> PyResult<usize> {
+        let dt = &mut *self.lock()?;
+        py.allow_threads(|| {
+            if thin == 0 {
+                return Err(exceptions::PyValueError::new_err("thin must be >= 1"));
             }
-        }
-        Ok(())
+            let schema = dt.t.get_attributes_schema();
+            let mut dims: Vec<(String, String, String)> = Vec::new();
+            for (dim, name) in attributes.unwrap_or_default() {
+                if !pointcloud::DIMENSIONS.contains(&dim.as_str()) {
+                    let s = format!("'{}' is not a LAS dimension", dim);
+                    return Err(exceptions::PyValueError::new_err(s));
+                }
+                match schema.iter().find(|(n, _)| *n == name) {
+                    Some((_, dtype)) if dtype != "String" => dims.push((dim, name, dtype.clone())),
+                    _ => {
+                        let s = format!("'{}' is not a (numerical) attribute of the schema", name);
+                        return Err(exceptions::PyValueError::new_err(s));
+                    }
+                }
+            }
+            if !std::path::Path::new(&path).exists() {
+                return Err(exceptions::PyFileNotFoundError::new_err(
+                    "No such file or directory",
+                ));
+            }
+            let mut reader = match las::Reader::from_path(&path) {
+                Ok(r) => r,
+                Err(why) => return Err(exceptions::PyValueError::new_err(why.to_string())),
+            };
+            let format = *reader.header().point_format();
+            let mut nkept: usize = 0;
+            let mut ninserted: usize = 0;
+            for p in reader.points() {
+                let p = match p {
+                    Ok(p) => p,
+                    Err(why) => return Err(exceptions::PyValueError::new_err(why.to_string())),
+                };
+                if !pointcloud::keep(&p, &classes, &returns) {
+                    continue;
+                }
+                nkept += 1;
+                //-- not is_multiple_of(), which needs Rust 1.87
+                #[allow(clippy::manual_is_multiple_of)]
+                if (nkept - 1) % thin != 0 {
+                    continue;
+                }
+                ninserted += 1;
+                let vi = match dt.inserting(|t| t.insert_one_pt(p.x, p.y, p.z)) {
+                    Ok(vi) => vi,
+                    Err((vi, true)) => vi,
+                    Err((_, false)) => continue,
+                };
+                if dims.is_empty() {
+                    continue;
+                }
+                let mut m = Map::new();
+                for (dim, name, dtype) in &dims {
+                    let v = pointcloud::dimension(&p, &format, dim);
+                    if v.is_nan() {
+                        continue;
+                    }
+                    match dtype.as_ref() {
+                        "f64" => m.insert(name.to_string(), v.into()),
+                        "i64" => m.insert(name.to_string(), (v as i64).into()),
+                        "u64" => m.insert(name.to_string(), (v as u64).into()),
+                        "bool" => m.insert(name.to_string(), (v != 0.0).into()),
+                        _ => continue,
+                    };
+                }
+                let _ = dt.t.add_vertex_attributes(vi, Value::Object(m));
+            }
+            Ok(ninserted)
+        })
     }
 
     /// Get/set the snap tolerance used to merge vertices during insertion.
@@ -259,12 +705,15 @@ impl DT {
     /// The snap tolerance is: 0.05
     #[getter(snap_tolerance)]
     fn get_snap_tolerance(&self) -> PyResult<f64> {
-        Ok(self.t.get_snap_tolerance())
+        let dt = self.lock()?;
+        Ok(dt.t.get_snap_tolerance())
     }
 
     #[setter(snap_tolerance)]
-    fn set_snap_tolerance(&mut self, snaptol: f64) {
-        self.t.set_snap_tolerance(snaptol);
+    fn set_snap_tolerance(&self, snaptol: f64) -> PyResult<()> {
+        let dt = &mut *self.lock()?;
+        dt.t.set_snap_tolerance(snaptol);
+        Ok(())
     }
 
     /// Activate/deactivate the jump-and-walk for the point location.
@@ -281,12 +730,15 @@ impl DT {
     /// >>> dt.jump_and_walk = True
     #[getter(jump_and_walk)]
     fn get_jump_and_walk(&self) -> PyResult<bool> {
-        Ok(self.t.get_jump_and_walk())
+        let dt = self.lock()?;
+        Ok(dt.t.get_jump_and_walk())
     }
 
     #[setter(jump_and_walk)]
-    fn set_jump_and_walk(&mut self, b: bool) {
-        self.t.set_jump_and_walk(b);
+    fn set_jump_and_walk(&self, b: bool) -> PyResult<()> {
+        let dt = &mut *self.lock()?;
+        dt.t.set_jump_and_walk(b);
+        Ok(())
     }
 
     /// Specify the method to handle xy-duplicates.
@@ -304,24 +756,26 @@ impl DT {
     /// >>> dt.duplicates_handling = "Highest"
     #[getter(duplicates_handling)]
     fn get_duplicates_handling(&self) -> PyResult<String> {
-        Ok(self.t.get_duplicates_handling())
+        let dt = self.lock()?;
+        Ok(dt.t.get_duplicates_handling())
     }
 
     #[setter(duplicates_handling)]
-    fn set_duplicates_handling(&mut self, m: &str) -> PyResult<()> {
+    fn set_duplicates_handling(&self, m: &str) -> PyResult<()> {
+        let dt = &mut *self.lock()?;
         match m {
-            "First" => self
-                .t
-                .set_duplicates_handling(startin::DuplicateHandling::First),
-            "Last" => self
-                .t
-                .set_duplicates_handling(startin::DuplicateHandling::Last),
-            "Highest" => self
-                .t
-                .set_duplicates_handling(startin::DuplicateHandling::Highest),
-            "Lowest" => self
-                .t
-                .set_duplicates_handling(startin::DuplicateHandling::Lowest),
+            "First" => {
+                dt.t.set_duplicates_handling(startin::DuplicateHandling::First)
+            }
+            "Last" => {
+                dt.t.set_duplicates_handling(startin::DuplicateHandling::Last)
+            }
+            "Highest" => {
+                dt.t.set_duplicates_handling(startin::DuplicateHandling::Highest)
+            }
+            "Lowest" => {
+                dt.t.set_duplicates_handling(startin::DuplicateHandling::Lowest)
+            }
 
             _ => {
                 let s = format!(
@@ -355,60 +809,8 @@ impl DT {
     /// >>> dt.set_attributes_schema(myschema)
     /// >>> dt.insert_one_pt([85000.0, 444003.2, 2.2], classification=2, intensity=111.1)
     #[pyo3(signature = (dtype))]
-    fn set_attributes_schema(&mut self, dtype: &PyAny) -> PyResult<bool> {
-        let descr: &PyArrayDescr = dtype.extract()?;
-        let names: &PyTuple = descr.getattr("names")?.extract()?;
-        let mut v: Vec<(String, String)> = Vec::new();
-        self.dtype.clear();
-        for name in names.iter() {
-            let name: &str = name.extract()?;
-            let field = descr.getattr("fields")?.get_item(name)?;
-            let field_type = field.get_item(0)?;
-            // println!("{:?}", field_type);
-            match field_type.to_string().as_ref() {
-                "bool" => {
-                    v.push((name.to_string(), "bool".to_string()));
-                    self.dtype.push((name.to_string(), "?".to_string()));
-                }
-                "float32" => {
-                    v.push((name.to_string(), "f64".to_string()));
-                    self.dtype.push((name.to_string(), "<f4".to_string()));
-                }
-                "float64" => {
-                    v.push((name.to_string(), "f64".to_string()));
-                    self.dtype.push((name.to_string(), "<f8".to_string()));
-                }
-                "int32" => {
-                    v.push((name.to_string(), "i64".to_string()));
-                    self.dtype.push((name.to_string(), "<i4".to_string()));
-                }
-                "int64" => {
-                    v.push((name.to_string(), "i64".to_string()));
-                    self.dtype.push((name.to_string(), "<i8".to_string()));
-                }
-                "uint32" => {
-                    v.push((name.to_string(), "u64".to_string()));
-                    self.dtype.push((name.to_string(), "<u4".to_string()));
-                }
-                "uint64" => {
-                    v.push((name.to_string(), "u64".to_string()));
-                    self.dtype.push((name.to_string(), "<u8".to_string()));
-                }
-                other if other.starts_with("<U") => {
-                    v.push((name.to_string(), "String".to_string()));
-                    self.dtype.push((name.to_string(), other.to_string()));
-                }
-                _ => {
-                    return {
-                        Err(PyErr::new::<pyo3::exceptions::PyAttributeError, _>(
-                            format!("{} is not a valid dype for startinpy", field_type),
-                        ))
-                    };
-                }
-            };
-        }
-        let _ = self.t.set_attributes_schema(v);
-        Ok(true)
+    fn set_attributes_schema(&self, dtype: &PyAny) -> PyResult<bool> {
+        self.lock()?.set_attributes_schema(dtype)
     }
 
     /// Get the attribute schema that contains the data type definitions
@@ -421,8 +823,9 @@ impl DT {
     /// True
     /// >>> dt.get_attributes_schema()
     /// [('classification', '<f8'), ('name', '<U8')]
-    fn get_attributes_schema(&self) -> Vec<(String, String)> {
-        self.dtype.clone()
+    fn get_attributes_schema(&self) -> PyResult<Vec<(String, String)>> {
+        let dt = self.lock()?;
+        Ok(dt.dtype.clone())
     }
 
     /// Get all the values for a given extra attribute stored for the vertices.
@@ -441,9 +844,10 @@ impl DT {
     /// array([6, 2, 6, 6, ..., 6, 9])
     #[getter]
     fn attributes<'py>(&self, py: Python<'py>) -> PyResult<PyObject> {
+        let dt = self.lock()?;
         let np = py.import("numpy")?;
-        let dtype = np.call_method1("dtype", (self.dtype.clone(),))?;
-        let allt = self.t.all_attributes();
+        let dtype = np.call_method1("dtype", (dt.dtype.clone(),))?;
+        let allt = dt.t.all_attributes();
         if allt.is_none() {
             let arraydtype = np.call_method1("empty", (0, dtype))?;
             return Ok(arraydtype.into());
@@ -453,7 +857,7 @@ impl DT {
         for (i, each) in allt.iter().enumerate() {
             let item = arraydtype.get_item(i)?;
             let o = each.as_object().unwrap();
-            for (key, dtype) in &self.t.get_attributes_schema() {
+            for (key, dtype) in &dt.t.get_attributes_schema() {
                 match o.get(key) {
                     Some(x) => {
                         match dtype.as_ref() {
@@ -493,7 +897,8 @@ impl DT {
     /// {'intensity': 111.1, 'reflectance': 99.1}
     #[pyo3(signature = (vi))]
     fn get_vertex_attributes(&self, vi: usize) -> PyResult<PyObject> {
-        match self.t.get_vertex_attributes(vi) {
+        let dt = self.lock()?;
+        match dt.t.get_vertex_attributes(vi) {
             Ok(v) => {
                 // Convert serde_json::Value to Python object
                 Python::with_gil(|py| {
@@ -528,49 +933,8 @@ impl DT {
     /// >>> dt.get_vertex_attributes(17)
     /// {'intensity': 111.1, 'reflectance': 29.9, 'classification': 2, }'    
     #[pyo3(signature = (vi, **py_kwargs))]
-    fn set_vertex_attributes(&mut self, vi: usize, py_kwargs: Option<&PyDict>) -> PyResult<bool> {
-        let mut m = Map::new();
-        if py_kwargs.is_some() {
-            let tmp = py_kwargs.unwrap();
-            let keys = tmp.keys();
-            let am = self.t.get_attributes_schema();
-            for k in keys {
-                let b: &String = &k.extract()?;
-                let c = am.iter().position(|(first, _)| first == b);
-                if c.is_some() {
-                    match am[c.unwrap()].1.as_ref() {
-                        "f64" => {
-                            let t1: f64 = tmp.get_item(b).unwrap().extract()?;
-                            m.insert(b.to_string(), t1.into());
-                        }
-                        "i64" => {
-                            let t1: i64 = tmp.get_item(b).unwrap().extract()?;
-                            m.insert(b.to_string(), t1.into());
-                        }
-                        "u64" => {
-                            let t1: u64 = tmp.get_item(b).unwrap().extract()?;
-                            m.insert(b.to_string(), t1.into());
-                        }
-                        "bool" => {
-                            let t1: bool = tmp.get_item(b).unwrap().extract()?;
-                            m.insert(b.to_string(), t1.into());
-                        }
-                        "String" => {
-                            let t1: String = tmp.get_item(b).unwrap().extract()?;
-                            m.insert(b.to_string(), t1.into());
-                        }
-                        &_ => continue,
-                    }
-                }
-            }
-        }
-        match self
-            .t
-            .add_vertex_attributes(vi, serde_json::to_value(m).unwrap())
-        {
-            Ok(b) => return Ok(b),
-            Err(_) => return Ok(false),
-        }
+    fn set_vertex_attributes(&self, vi: usize, py_kwargs: Option<&PyDict>) -> PyResult<bool> {
+        self.lock()?.set_vertex_attributes(vi, py_kwargs)
     }
 
     /// Calculate the area in 2D of a given triangle (projection xy-plane).
@@ -583,10 +947,11 @@ impl DT {
     /// 22.1
     #[pyo3(signature = (t))]
     fn area2d_triangle(&self, t: Vec<usize>) -> PyResult<f64> {
+        let dt = self.lock()?;
         let tr = startin::Triangle {
             v: [t[0], t[1], t[2]],
         };
-        match self.t.area2d_triangle(&tr) {
+        match dt.t.area2d_triangle(&tr) {
             Ok(b) => return Ok(b),
             Err(_) => return Err(exceptions::PyIndexError::new_err("Invalid vertex index")),
         }
@@ -602,10 +967,11 @@ impl DT {
     /// 32.2
     #[pyo3(signature = (t))]
     fn area3d_triangle(&self, t: Vec<usize>) -> PyResult<f64> {
+        let dt = self.lock()?;
         let tr = startin::Triangle {
             v: [t[0], t[1], t[2]],
         };
-        match self.t.area3d_triangle(&tr) {
+        match dt.t.area3d_triangle(&tr) {
             Ok(b) => return Ok(b),
             Err(_) => return Err(exceptions::PyIndexError::new_err("Invalid vertex index")),
         }
@@ -622,10 +988,11 @@ impl DT {
     /// 32.2
     #[pyo3(signature = (t, zplane=0.0))]
     fn volume_triangle(&self, t: Vec<usize>, zplane: f64) -> PyResult<f64> {
+        let dt = self.lock()?;
         let tr = startin::Triangle {
             v: [t[0], t[1], t[2]],
         };
-        match self.t.volume_triangle(&tr, zplane) {
+        match dt.t.volume_triangle(&tr, zplane) {
             Ok(b) => return Ok(b),
             Err(_) => return Err(exceptions::PyIndexError::new_err("Invalid vertex index")),
         }
@@ -642,7 +1009,8 @@ impl DT {
     /// array([15.63303377, 26.9968598 ,  23.4])
     #[pyo3(signature = (vi))]
     fn normal_vertex(&self, vi: usize) -> PyResult<Vec<f64>> {
-        match self.t.normal_vertex(vi) {
+        let dt = self.lock()?;
+        match dt.t.normal_vertex(vi) {
             Ok(b) => return Ok(b),
             Err(_) => return Err(exceptions::PyIndexError::new_err("Invalid vertex index")),
         }
@@ -659,15 +1027,166 @@ impl DT {
     /// array([15.63303377, 26.9968598 ,  23.4])
     #[pyo3(signature = (t))]
     fn normal_triangle(&self, t: Vec<usize>) -> PyResult<Vec<f64>> {
+        let dt = self.lock()?;
         let tr = startin::Triangle {
             v: [t[0], t[1], t[2]],
         };
-        match self.t.normal_triangle(&tr) {
+        match dt.t.normal_triangle(&tr) {
             Ok(b) => return Ok(b),
             Err(_) => return Err(exceptions::PyIndexError::new_err("Invalid Triangle")),
         }
     }
 
+    /// Calculate the curvature at each vertex, from a quadric surface passing through the vertex
+    /// and fitted (least squares) to the vertices of its k-ring (the vertices at most k edges
+    /// away). At least 5 neighbours are needed, if the k-ring has fewer then the next ring is used.
+    ///
+    /// The kinds of curvature are:
+    ///
+    /// - "profile": in the direction of the slope
+    /// - "plan": in the direction of the contour line (the tangential curvature)
+    /// - "mean": the mean of the principal curvatures
+    ///
+    /// The curvatures are positive where the surface is concave upwards (eg a valley), and
+    /// negative where it is convex (eg a ridge); "profile" and "plan" are 0.0 on a horizontal plane.
+    ///
+    /// :param kind: "profile", "plan", or "mean"
+    /// :param rings: (default=1) the number of rings of neighbours used
+    /// :return: an array with the curvature of each vertex (same order as :func:`points`),
+    ///          numpy.nan for the infinite vertex and the removed vertices
+    ///
+    /// >>> c = dt.curvature("profile", rings=2)
+    /// >>> c[17]
+    /// -0.0213
+    #[pyo3(signature = (kind, rings=1))]
+    fn curvature<'py>(
+        &self,
+        py: Python<'py>,
+        kind: &str,
+        rings: usize,
+    ) -> PyResult<&'py PyArray<f64, numpy::Ix1>> {
+        let dt = self.lock()?;
+        let kind = match terrain::Curvature::from_name(kind) {
+            Some(k) => k,
+            None => {
+                let s = format!("'{}' is an unknown curvature (profile/plan/mean)", kind);
+                return Err(exceptions::PyValueError::new_err(s));
+            }
+        };
+        if rings == 0 {
+            return Err(exceptions::PyValueError::new_err("rings must be >= 1"));
+        }
+        let re = py.allow_threads(|| terrain::curvature(&dt.t, kind, rings));
+        Ok(PyArray::from_vec(py, re))
+    }
+
+    /// Calculate the roughness at each vertex: the root mean square of the elevation differences
+    /// between the vertex and the vertices of its k-ring (the vertices at most k edges away).
+    /// This is the terrain ruggedness index (TRI) of Riley et al. (1999), divided by the number
+    /// of neighbours so that it does not depend on the degree of the vertices.
+    ///
+    /// :param rings: (default=1) the number of rings of neighbours used
+    /// :return: an array with the roughness of each vertex (same order as :func:`points`),
+    ///          numpy.nan for the infinite vertex and the removed vertices
+    ///
+    /// >>> r = dt.roughness(rings=2)
+    #[pyo3(signature = (rings=1))]
+    fn roughness<'py>(
+        &self,
+        py: Python<'py>,
+        rings: usize,
+    ) -> PyResult<&'py PyArray<f64, numpy::Ix1>> {
+        let dt = self.lock()?;
+        if rings == 0 {
+            return Err(exceptions::PyValueError::new_err("rings must be >= 1"));
+        }
+        let re = py.allow_threads(|| terrain::roughness(&dt.t, rings));
+        Ok(PyArray::from_vec(py, re))
+    }
+
+    /// Calculate the topographic position index (TPI) at each vertex: its elevation minus the
+    /// mean elevation of the other vertices at a distance (in 2D) <= radius.
+    /// It is positive on ridges and peaks, and negative in valleys and pits.
+    ///
+    /// :param radius: the search radius
+    /// :return: an array with the TPI of each vertex (same order as :func:`points`),
+    ///          numpy.nan for the infinite vertex, the removed vertices, and the vertices
+    ///          without other vertices in the radius
+    ///
+    /// >>> tpi = dt.tpi(50.0)
+    /// >>> ridges = np.where(tpi > 2.0)[0]
+    #[pyo3(signature = (radius))]
+    fn tpi<'py>(&self, py: Python<'py>, radius: f64) -> PyResult<&'py PyArray<f64, numpy::Ix1>> {
+        let dt = &mut *self.lock()?;
+        if radius.is_nan() || radius <= 0.0 {
+            return Err(exceptions::PyValueError::new_err("radius must be > 0"));
+        }
+        let re = py.allow_threads(|| terrain::tpi(&mut dt.t, radius));
+        Ok(PyArray::from_vec(py, re))
+    }
+
+    /// Create a hillshade raster covering the bbox of the DT. The shading of a cell is computed
+    /// with the normal of the triangle containing its centre (and not with finite differences
+    /// on a raster), thus the breaklines of the terrain are crisp.
+    ///
+    /// The value of a cell is 1 + 254 * the illumination (the cosine of the angle between the
+    /// normal and the direction of the light, 0 if the surface faces away from the light), and
+    /// 0 (no data) if its centre is outside the convex hull.
+    /// The raster can be written with GDAL/rasterio with the geotransform.
+    ///
+    /// :param cellsize: the size of the cells
+    /// :param azimuth: (default=315.0) the direction of the light, in degrees clockwise from the north
+    /// :param altitude: (default=45.0) the angle of the light above the horizon, in degrees
+    /// :param z_factor: (default=1.0) the z-values are multiplied by this factor (the vertical exaggeration)
+    /// :param multidirectional: (default=False) combine the illuminations from the azimuths 225, 270, 315, and 360 (weighted with the aspect of the triangle, as in GDAL), the azimuth is then ignored
+    /// :return: a tuple with a NumPy array of uint8 (the rows from north to south), and the geotransform (minx, cellsize, 0.0, maxy, 0.0, -cellsize)
+    ///
+    /// >>> (raster, gt) = dt.hillshade(1.0, azimuth=270.0, z_factor=2.0)
+    /// >>> raster.shape
+    /// (250, 312)
+    /// >>> gt
+    /// (85000.0, 1.0, 0.0, 445250.0, 0.0, -1.0)
+    #[pyo3(signature = (cellsize, azimuth=315.0, altitude=45.0, z_factor=1.0, multidirectional=false))]
+    fn hillshade<'py>(
+        &self,
+        py: Python<'py>,
+        cellsize: f64,
+        azimuth: f64,
+        altitude: f64,
+        z_factor: f64,
+        multidirectional: bool,
+    ) -> PyResult<RasterArrays<'py>> {
+        let dt = &mut *self.lock()?;
+        if cellsize.is_nan() || cellsize <= 0.0 {
+            return Err(exceptions::PyValueError::new_err("cellsize must be > 0"));
+        }
+        if !(0.0..=90.0).contains(&altitude) {
+            return Err(exceptions::PyValueError::new_err(
+                "altitude must be in [0, 90]",
+            ));
+        }
+        if !azimuth.is_finite() || z_factor.is_nan() || z_factor <= 0.0 {
+            return Err(exceptions::PyValueError::new_err("Wrong parameters"));
+        }
+        if dt.t.number_of_triangles() == 0 {
+            return Err(exceptions::PyException::new_err("Empty triangulation"));
+        }
+        let (raster, gt) = py.allow_threads(|| {
+            terrain::hillshade(
+                &mut dt.t,
+                cellsize,
+                azimuth,
+                altitude,
+                z_factor,
+                multidirectional,
+            )
+        });
+        Ok((
+            PyArray::from_vec2(py, &raster).unwrap(),
+            (gt[0], gt[1], gt[2], gt[3], gt[4], gt[5]),
+        ))
+    }
+
     /// Update/set the z-value for a specific vertex.
     /// An exception is thrown if the vertex index is invalid.
     ///
@@ -679,38 +1198,296 @@ impl DT {
     /// >>> dt.points[17]
     /// array([15.63303377, 26.9968598 ,  23.4])
     #[pyo3(signature = (vi, z))]
-    fn update_vertex_z_value(&mut self, vi: usize, z: f64) -> PyResult<bool> {
-        match self.t.update_vertex_z_value(vi, z) {
+    fn update_vertex_z_value(&self, vi: usize, z: f64) -> PyResult<bool> {
+        let dt = &mut *self.lock()?;
+        match dt.t.update_vertex_z_value(vi, z) {
             Ok(b) => return Ok(b),
             Err(_) => return Ok(false),
         }
     }
 
+    /// Move a vertex to a new location [x, y, z], the DT is updated to stay Delaunay.
+    /// If only the z-value changes, it is updated; otherwise the vertex is removed and
+    /// inserted at its new location, and it keeps its index, its extra attributes and its
+    /// :func:`user_ids`.
+    /// This is also the case for small moves: startin can neither change the xy-coordinates
+    /// of a vertex nor flip edges, thus the DT cannot be repaired locally.
+    ///
+    /// If there is already another vertex at the new location (based on
+    /// :func:`startinpy.DT.snap_tolerance`), then the moved vertex is removed and
+    /// :func:`startinpy.DT.duplicates_handling` decides if the z-value (and the extra
+    /// attributes) of the moved vertex replace those of the other vertex.
+    /// An exception is thrown if the vertex index is invalid, or if the DT would have no
+    /// triangles without the vertex (eg if it has only 3 vertices): startin would then
+    /// renumber the vertices.
+    ///
+    /// :param vi: the index of the vertex
+    /// :param p3: array with the new [x, y, z]-coordinates of the vertex
+    /// :return: the index of the vertex at the new location (*vi*, or the index of the
+    ///          vertex it was merged with)
+    ///
+    /// >>> dt.move_vertex(17, [15.7, 26.8, 23.4])
+    /// 17
+    /// >>> dt.points[17]
+    /// array([15.7, 26.8, 23.4])
+    #[pyo3(signature = (vi, p3))]
+    fn move_vertex(&self, vi: usize, p3: [f64; 3]) -> PyResult<usize> {
+        let dt = &mut *self.lock()?;
+        if vi == 0 || dt.t.is_vertex_removed(vi) != Ok(false) {
+            return Err(exceptions::PyIndexError::new_err(
+                "Invalid vertex index: vertex doesn't exist",
+            ));
+        }
+        if dt.t.convex_hull().is_empty() {
+            return Err(exceptions::PyException::new_err(
+                "The DT has no triangles, the vertex cannot be moved",
+            ));
+        }
+        let p = dt.t.get_point(vi).unwrap();
+        if p[0] == p3[0] && p[1] == p3[1] {
+            let _ = dt.t.update_vertex_z_value(vi, p3[2]);
+            return Ok(vi);
+        }
+        if dt.is_needed_for_triangles(vi) {
+            return Err(exceptions::PyException::new_err(
+                "The DT would have no triangles without the vertex, it cannot be moved",
+            ));
+        }
+        let a = dt.t.get_vertex_attributes(vi).ok();
+        let uid = dt.user_id(vi);
+        if dt.t.remove(vi).is_err() {
+            return Err(exceptions::PyException::new_err(
+                "The vertex cannot be removed, it cannot be moved",
+            ));
+        }
+        dt.forget_user_id(vi);
+        //-- the index of the removed vertex is the first to be reused
+        let (newvi, updated, merged) = match dt.inserting(|t| t.insert_one_pt(p3[0], p3[1], p3[2]))
+        {
+            Ok(x) => (x, true, false),
+            Err((x, b)) => (x, b, true),
+        };
+        if updated {
+            if let Some(a) = a {
+                let _ = dt.t.add_vertex_attributes(newvi, a);
+            }
+        }
+        if !merged {
+            dt.set_user_id(newvi, uid);
+        }
+        Ok(newvi)
+    }
+
     /// :return: number of finite vertices    
     fn number_of_vertices(&self) -> PyResult<usize> {
-        Ok(self.t.number_of_vertices())
+        let dt = self.lock()?;
+        Ok(dt.t.number_of_vertices())
     }
 
     /// :return: number of finite triangles    
     fn number_of_triangles(&self) -> PyResult<usize> {
-        Ok(self.t.number_of_triangles())
+        let dt = self.lock()?;
+        Ok(dt.t.number_of_triangles())
     }
 
-    /// Return the point for the vertex with index *vi*.
-    /// An exception is thrown if the vertex index is invalid.
-    ///
-    /// :param vi: the index of the vertex
-    /// :return: the point
-    ///
-    /// >>> v = dt.get_point(4)
-    /// array([13., 2.0, 11.])
-    #[pyo3(signature = (vi))]
-    fn get_point<'py>(
-        &self,
-        py: Python<'py>,
-        vi: usize,
-    ) -> PyResult<&'py PyArray<f64, numpy::Ix1>> {
-        let re = self.t.get_point(vi);
+    /// Return the quality metrics of the finite triangles (same order as :func:`triangles`).
+    /// The fields of the NumPy structured array (all float64) are:
+    ///
+    /// - "min_angle" and "max_angle": in degrees (in 2D)
+    /// - "aspect_ratio": the longest edge over the shortest altitude, normalised so that it is 1.0 for an equilateral triangle
+    /// - "radius_edge_ratio": the circumradius over the shortest edge (0.577 for an equilateral triangle)
+    /// - "area_2d" and "area_3d": the area of the triangle projected on the xy-plane, and in 3D
+    ///
+    /// The ratios are numpy.inf for a triangle without area.
+    ///
+    /// :return: a NumPy structured array with one row per triangle
+    ///
+    /// >>> q = dt.triangle_quality()
+    /// >>> q["min_angle"].min()
+    /// 0.7312
+    /// >>> dt.triangles[q["aspect_ratio"] > 10.0]
+    fn triangle_quality(&self, py: Python<'_>) -> PyResult<PyObject> {
+        let dt = self.lock()?;
+        let qs: Vec<quality::TriangleQuality> = py.allow_threads(|| {
+            dt.t.all_finite_triangles()
+                .iter()
+                .map(|tr| {
+                    quality::triangle(
+                        &dt.t.get_point(tr.v[0]).unwrap(),
+                        &dt.t.get_point(tr.v[1]).unwrap(),
+                        &dt.t.get_point(tr.v[2]).unwrap(),
+                    )
+                })
+                .collect()
+        });
+        let np = py.import("numpy")?;
+        let names = [
+            "min_angle",
+            "max_angle",
+            "aspect_ratio",
+            "radius_edge_ratio",
+            "area_2d",
+            "area_3d",
+        ];
+        let fields: Vec<(&str, &str)> = names.iter().map(|n| (*n, "<f8")).collect();
+        let dtype = np.call_method1("dtype", (fields,))?;
+        let re = np.call_method1("empty", (qs.len(), dtype))?;
+        let columns: [fn(&quality::TriangleQuality) -> f64; 6] = [
+            |q| q.min_angle,
+            |q| q.max_angle,
+            |q| q.aspect_ratio,
+            |q| q.radius_edge_ratio,
+            |q| q.area_2d,
+            |q| q.area_3d,
+        ];
+        for (name, f) in names.iter().zip(columns) {
+            re.set_item(*name, PyArray::from_vec(py, qs.iter().map(f).collect()))?;
+        }
+        Ok(re.into())
+    }
+
+    /// Return statistics about the DT, in a dict with:
+    ///
+    /// - "number_vertices", "number_triangles", and "number_removed_vertices"
+    /// - "area_2d" and "area_3d": the total area of the triangles
+    /// - "z_min" and "z_max"
+    /// - "mean_edge_length": the mean length (in 2D) of the finite edges
+    /// - "point_density": the number of vertices per unit of (2D) area
+    /// - "degree_histogram": a dict with the number of vertices for each degree (only the finite edges are counted)
+    ///
+    /// :return: a dict
+    ///
+    /// >>> s = dt.statistics()
+    /// >>> s["point_density"]
+    /// 12.31
+    /// >>> s["degree_histogram"]
+    /// {3: 12, 4: 48, 5: 213, 6: 402, 7: 197, 8: 41, 9: 3}
+    fn statistics<'py>(&self, py: Python<'py>) -> PyResult<&'py PyDict> {
+        let dt = self.lock()?;
+        let s = py.allow_threads(|| quality::statistics(&dt.t));
+        let d = PyDict::new(py);
+        d.set_item("number_vertices", s.number_vertices)?;
+        d.set_item("number_triangles", s.number_triangles)?;
+        d.set_item("number_removed_vertices", s.number_removed_vertices)?;
+        d.set_item("area_2d", s.area_2d)?;
+        d.set_item("area_3d", s.area_3d)?;
+        d.set_item("z_min", s.z_min)?;
+        d.set_item("z_max", s.z_max)?;
+        d.set_item("mean_edge_length", s.mean_edge_length)?;
+        d.set_item("point_density", s.point_density)?;
+        let h = PyDict::new(py);
+        for (degree, n) in s.degree_histogram.iter().enumerate() {
+            if *n > 0 {
+                h.set_item(degree, n)?;
+            }
+        }
+        d.set_item("degree_histogram", h)?;
+        Ok(d)
+    }
+
+    /// Refine the DT so that the triangles have a minimum angle (and optionally a maximum area),
+    /// with Ruppert's algorithm: Steiner points are inserted at the circumcentres of the bad
+    /// triangles, and at the midpoints of the edges of the convex hull when needed (the convex
+    /// hull is thus preserved, the midpoints are moved outwards by at most 1e-8 times the length
+    /// of the edge so that no flat triangle is created).
+    /// The triangles whose smallest angle is at a corner of the convex hull with an angle < 60°
+    /// cannot be improved and are left as is, and so are the triangles where the Steiner point
+    /// would be closer than the snap tolerance to a vertex (or where an edge of the convex hull
+    /// is too short to be split in floating-point).
+    ///
+    /// The z-value of a new vertex is interpolated in the DT before the refinement.
+    /// The new vertices have the extra attribute "steiner" set to True, it is added to
+    /// the schema if not present (the other vertices have False).
+    ///
+    /// :param min_angle: (default=25.0) the minimum angle (in degrees) of the triangles, at most 33.0 (else the refinement might not terminate)
+    /// :param max_area: (default=None) the maximum area (in 2D) of the triangles
+    /// :param z_from: (default="TIN") the interpolation method for the z-values of the new vertices, either the name of a method without parameters or a JSON/dict interpolant (see :func:`interpolate`)
+    /// :return: an array with the indices of the new vertices
+    ///
+    /// >>> new = dt.refine(min_angle=30.0, max_area=10.0)
+    /// >>> dt.triangle_quality()["min_angle"].min()
+    /// 30.012
+    /// >>> dt.attributes["steiner"][new]
+    /// array([ True,  True, ...,  True])
+    #[pyo3(signature = (min_angle=25.0, max_area=None, z_from=None))]
+    fn refine<'py>(
+        &self,
+        py: Python<'py>,
+        min_angle: f64,
+        max_area: Option<f64>,
+        z_from: Option<&PyAny>,
+    ) -> PyResult<&'py PyArray<usize, numpy::Ix1>> {
+        let dt = &mut *self.lock()?;
+        if min_angle.is_nan() || min_angle <= 0.0 || min_angle > 33.0 {
+            return Err(exceptions::PyValueError::new_err(
+                "min_angle must be in ]0, 33]",
+            ));
+        }
+        if max_area.is_some_and(|a| a.is_nan() || a <= 0.0) {
+            return Err(exceptions::PyValueError::new_err("max_area must be > 0"));
+        }
+        let interpolant = match z_from {
+            Some(z) => match z.downcast::<PyDict>() {
+                Ok(d) => d,
+                Err(_) => {
+                    let d = PyDict::new(py);
+                    d.set_item("method", z.extract::<String>()?)?;
+                    d
+                }
+            },
+            None => {
+                let d = PyDict::new(py);
+                d.set_item("method", "TIN")?;
+                d
+            }
+        };
+        let method = interpolation_method(interpolant)?;
+        dt.add_steiner_attribute()?;
+        let new = py.allow_threads(|| {
+            //-- the z-values are interpolated in a copy of the DT before the refinement
+            let mut reference = startin::Triangulation::new();
+            reference.set_snap_tolerance(dt.t.get_snap_tolerance());
+            for vi in 1..dt.t.all_vertices().len() {
+                if dt.t.is_vertex_removed(vi) == Ok(false) {
+                    let p = dt.t.get_point(vi).unwrap();
+                    let _ = reference.insert_one_pt(p[0], p[1], p[2]);
+                }
+            }
+            let vorareas = match method {
+                interpolation::Method::NNI { precompute: true } => {
+                    Some(interpolation::voronoi_areas(&reference))
+                }
+                _ => None,
+            };
+            refine::refine(&mut dt.t, min_angle, max_area, |p| {
+                interpolation::estimate(&mut reference, &method, p, vorareas.as_deref(), |t, vi| {
+                    t.get_point(vi).unwrap()[2]
+                })
+                .ok()
+            })
+        });
+        for vi in &new {
+            let _ = dt.t.add_vertex_attributes(*vi, json!({"steiner": true}));
+        }
+        Ok(PyArray::from_vec(py, new))
+    }
+
+    /// Return the point for the vertex with index *vi*.
+    /// An exception is thrown if the vertex index is invalid.
+    ///
+    /// :param vi: the index of the vertex
+    /// :return: the point
+    ///
+    /// >>> v = dt.get_point(4)
+    /// array([13., 2.0, 11.])
+    #[pyo3(signature = (vi))]
+    fn get_point<'py>(
+        &self,
+        py: Python<'py>,
+        vi: usize,
+    ) -> PyResult<&'py PyArray<f64, numpy::Ix1>> {
+        let dt = self.lock()?;
+        let re = dt.t.get_point(vi);
         if re.is_ok() {
             return Ok(PyArray::from_vec(py, re.unwrap()));
         } else {
@@ -725,7 +1502,8 @@ impl DT {
     /// >>> dt.convex_hull()
     /// array([2, 13, 4, 51, 27], dtype=uint64)
     fn convex_hull<'py>(&self, py: Python<'py>) -> PyResult<&'py PyArray<usize, numpy::Ix1>> {
-        Ok(PyArray::from_vec(py, self.t.convex_hull()))
+        let dt = self.lock()?;
+        Ok(PyArray::from_vec(py, dt.t.convex_hull()))
     }
 
     /// Return the bbox of the dataset
@@ -735,7 +1513,8 @@ impl DT {
     /// >>> bbox = dt.get_bbox()
     /// array([ 0., 0., 10., 12. ])
     fn get_bbox<'py>(&self, py: Python<'py>) -> PyResult<&'py PyArray<f64, numpy::Ix1>> {
-        Ok(PyArray::from_vec(py, self.t.get_bbox()))
+        let dt = self.lock()?;
+        Ok(PyArray::from_vec(py, dt.t.get_bbox()))
     }
 
     /// Is the point [x, y] located inside the convex hull of the DT.
@@ -743,8 +1522,9 @@ impl DT {
     /// :param p2: array with [x, y]-coordinates of point to test
     /// :return: True if [x,y] is inside the convex hull or on its boundary, False otherwise.
     #[pyo3(signature = (p2))]
-    fn is_inside_convex_hull(&mut self, p2: [f64; 2]) -> PyResult<bool> {
-        let re = self.t.locate(p2[0], p2[1]);
+    fn is_inside_convex_hull(&self, p2: [f64; 2]) -> PyResult<bool> {
+        let dt = &mut *self.lock()?;
+        let re = dt.t.locate(p2[0], p2[1]);
         if re.is_ok() == true {
             return Ok(true);
         } else {
@@ -759,7 +1539,8 @@ impl DT {
     ///          Also False is returned if the vertex doesn't exist in the DT.
     #[pyo3(signature = (vi))]
     fn is_vertex_convex_hull(&self, vi: usize) -> PyResult<bool> {
-        Ok(self.t.is_vertex_convex_hull(vi))
+        let dt = self.lock()?;
+        Ok(dt.t.is_vertex_convex_hull(vi))
     }
 
     /// Return True if vertex *vi* is labelled as removed, False otherwise.
@@ -769,7 +1550,8 @@ impl DT {
     ///          An exception is raised if *vi* doesn't exist.  
     #[pyo3(signature = (vi))]
     fn is_vertex_removed(&self, vi: usize) -> PyResult<bool> {
-        let re = self.t.is_vertex_removed(vi);
+        let dt = self.lock()?;
+        let re = dt.t.is_vertex_removed(vi);
         if re.is_err() {
             return Err(exceptions::PyIndexError::new_err("Invalid vertex index"));
         } else {
@@ -788,8 +1570,9 @@ impl DT {
     /// >>> except Exception as e:
     /// >>>     print(e)
     #[pyo3(signature = (p2))]
-    fn closest_point(&mut self, p2: [f64; 2]) -> PyResult<usize> {
-        let re = self.t.closest_point(p2[0], p2[1]);
+    fn closest_point(&self, p2: [f64; 2]) -> PyResult<usize> {
+        let dt = &mut *self.lock()?;
+        let re = dt.t.closest_point(p2[0], p2[1]);
         if re.is_err() {
             return Err(exceptions::PyException::new_err("Outside convex hull"));
         } else {
@@ -820,7 +1603,8 @@ impl DT {
         py: Python<'py>,
         vi: usize,
     ) -> PyResult<&'py PyArray<usize, numpy::Ix2>> {
-        let re = self.t.incident_triangles_to_vertex(vi);
+        let dt = self.lock()?;
+        let re = dt.t.incident_triangles_to_vertex(vi);
         if re.is_ok() {
             let l = re.unwrap();
             let mut trs: Vec<Vec<usize>> = Vec::with_capacity(l.len());
@@ -855,10 +1639,11 @@ impl DT {
         py: Python<'py>,
         t: Vec<usize>,
     ) -> PyResult<&'py PyArray<usize, numpy::Ix2>> {
+        let dt = self.lock()?;
         let tr = startin::Triangle {
             v: [t[0], t[1], t[2]],
         };
-        let re = self.t.adjacent_triangles_to_triangle(&tr);
+        let re = dt.t.adjacent_triangles_to_triangle(&tr);
         if re.is_ok() {
             let l = re.unwrap();
             let mut trs: Vec<Vec<usize>> = Vec::with_capacity(l.len());
@@ -887,7 +1672,8 @@ impl DT {
         py: Python<'py>,
         vi: usize,
     ) -> PyResult<&'py PyArray<usize, numpy::Ix1>> {
-        let re = self.t.adjacent_vertices_to_vertex(vi);
+        let dt = self.lock()?;
+        let re = dt.t.adjacent_vertices_to_vertex(vi);
         if re.is_ok() {
             return Ok(PyArray::from_vec(py, re.unwrap()));
         } else {
@@ -906,10 +1692,11 @@ impl DT {
     /// >>> re = dt.is_finite(np.array([11, 162, 666])))
     #[pyo3(signature = (t))]
     fn is_finite(&self, t: Vec<usize>) -> PyResult<bool> {
+        let dt = self.lock()?;
         let tr = startin::Triangle {
             v: [t[0], t[1], t[2]],
         };
-        Ok(self.t.is_finite(&tr))
+        Ok(dt.t.is_finite(&tr))
     }
 
     /// Verify if a triangle exists in the DT.
@@ -921,10 +1708,41 @@ impl DT {
     /// False
     #[pyo3(signature = (t))]
     fn is_triangle(&self, t: Vec<usize>) -> PyResult<bool> {
+        let dt = self.lock()?;
         let tr = startin::Triangle {
             v: [t[0], t[1], t[2]],
         };
-        Ok(self.t.is_triangle(&tr))
+        Ok(dt.t.is_triangle(&tr))
+    }
+
+    /// Verify the validity of the DT (eg after many insertions and deletions), with the robust
+    /// predicates. The report is a dict with:
+    ///
+    /// - "valid": True if all the checks passed
+    /// - "stars": the vertices whose star is not consistent with the stars of their adjacent vertices (each triangle must be in the stars of its 3 vertices)
+    /// - "orientation": the finite triangles that are not CCW (CW or flat)
+    /// - "convex_hull": the vertices where the convex hull is not closed or not convex
+    /// - "delaunay": the triangles with a vertex inside their circumcircle, as tuples (triangle, vertex); it is verified for each edge against the opposite vertex, and only if the stars are consistent
+    ///
+    /// :param check_delaunay: (default=True) verify the empty-circumcircle property
+    /// :return: a dict
+    ///
+    /// >>> re = dt.is_valid()
+    /// >>> re["valid"]
+    /// True
+    /// >>> re["delaunay"]
+    /// []
+    #[pyo3(signature = (check_delaunay=true))]
+    fn is_valid<'py>(&self, py: Python<'py>, check_delaunay: bool) -> PyResult<&'py PyDict> {
+        let dt = self.lock()?;
+        let re = py.allow_threads(|| validity::check(&dt.t, check_delaunay));
+        let d = PyDict::new(py);
+        d.set_item("valid", re.is_valid())?;
+        d.set_item("stars", re.stars)?;
+        d.set_item("orientation", re.orientation)?;
+        d.set_item("convex_hull", re.convex_hull)?;
+        d.set_item("delaunay", re.delaunay)?;
+        Ok(d)
     }
 
     /// Locate the triangle containing the point [x, y] (projected to 2D).
@@ -937,11 +1755,12 @@ impl DT {
     /// array([65, 61, 23], dtype=uint64)
     #[pyo3(signature = (p2))]
     fn locate<'py>(
-        &mut self,
+        &self,
         py: Python<'py>,
         p2: [f64; 2],
     ) -> PyResult<&'py PyArray<usize, numpy::Ix1>> {
-        let re = self.t.locate(p2[0], p2[1]);
+        let dt = &mut *self.lock()?;
+        let re = dt.t.locate(p2[0], p2[1]);
         let mut tr: Vec<usize> = Vec::new();
         if re.is_ok() {
             let t = re.unwrap();
@@ -954,162 +1773,484 @@ impl DT {
         }
     }
 
-    /// Estimate the z-value with 5 different spatial interpolation methods:
+    /// Locate the triangles containing each of the points (projected to 2D).
+    /// No error is thrown for the points outside the convex hull, their triangle is
+    /// [0, 0, 0] (0 being the infinite vertex, no finite triangle can have it).
+    ///
+    /// :param locations: an array of [x, y] locations
+    /// :return: an array of triangles (same order as input array)
+    ///
+    /// >>> trs = dt.locate_many([[34.2, 55.6], [-999.0, 55.6]])
+    /// array([[65, 61, 23],
+    ///        [ 0,  0,  0]], dtype=uint64)
+    #[pyo3(signature = (locations))]
+    fn locate_many<'py>(
+        &self,
+        py: Python<'py>,
+        locations: Vec<[f64; 2]>,
+    ) -> PyResult<&'py PyArray<usize, numpy::Ix2>> {
+        let dt = &mut *self.lock()?;
+        let trs: Vec<Vec<usize>> = py.allow_threads(|| {
+            locations
+                .iter()
+                .map(|p| match dt.t.locate(p[0], p[1]) {
+                    Ok(t) => t.v.to_vec(),
+                    Err(_) => vec![0, 0, 0],
+                })
+                .collect()
+        });
+        if trs.is_empty() {
+            return Ok(PyArray::zeros(py, [0, 3], false));
+        }
+        Ok(PyArray::from_vec2(py, &trs).unwrap())
+    }
+
+    /// Return the closest vertex index to each of the points (distance in 2D).
+    /// No error is thrown for the points outside the convex hull, their closest
+    /// vertex is 0 (the infinite vertex).
+    ///
+    /// :param locations: an array of [x, y] locations
+    /// :return: an array of vertex indices (same order as input array)
+    ///
+    /// >>> dt.closest_point_many([[32.1, 66.9], [-999.0, 66.9]])
+    /// array([12,  0], dtype=uint64)
+    #[pyo3(signature = (locations))]
+    fn closest_point_many<'py>(
+        &self,
+        py: Python<'py>,
+        locations: Vec<[f64; 2]>,
+    ) -> PyResult<&'py PyArray<usize, numpy::Ix1>> {
+        let dt = &mut *self.lock()?;
+        let re: Vec<usize> = py.allow_threads(|| {
+            locations
+                .iter()
+                .map(|p| dt.t.closest_point(p[0], p[1]).unwrap_or(0))
+                .collect()
+        });
+        Ok(PyArray::from_vec(py, re))
+    }
+
+    /// Are the points located inside the convex hull of the DT.
+    ///
+    /// :param locations: an array of [x, y] locations
+    /// :return: a boolean array (same order as input array), True if the point is inside
+    ///          the convex hull or on its boundary, False otherwise
+    ///
+    /// >>> dt.is_inside_convex_hull_many([[32.1, 66.9], [-999.0, 66.9]])
+    /// array([ True, False])
+    #[pyo3(signature = (locations))]
+    fn is_inside_convex_hull_many<'py>(
+        &self,
+        py: Python<'py>,
+        locations: Vec<[f64; 2]>,
+    ) -> PyResult<&'py PyArray<bool, numpy::Ix1>> {
+        let dt = &mut *self.lock()?;
+        let re: Vec<bool> = py.allow_threads(|| {
+            locations
+                .iter()
+                .map(|p| dt.t.locate(p[0], p[1]).is_ok())
+                .collect()
+        });
+        Ok(PyArray::from_vec(py, re))
+    }
+
+    /// Return the *k* closest vertices to [x, y] (distance in 2D), by increasing distance.
+    /// Contrary to :func:`closest_point`, [x, y] can be outside the convex hull.
+    ///
+    /// :param p2: array with [x, y]-coordinates of point
+    /// :param k: the number of vertices
+    /// :return: an array of vertex indices (fewer than *k* if the DT has fewer vertices)
+    ///
+    /// >>> dt.nearest_k([32.1, 66.9], 3)
+    /// array([12, 7, 33], dtype=uint64)
+    #[pyo3(signature = (p2, k))]
+    fn nearest_k<'py>(
+        &self,
+        py: Python<'py>,
+        p2: [f64; 2],
+        k: usize,
+    ) -> PyResult<&'py PyArray<usize, numpy::Ix1>> {
+        let dt = &mut *self.lock()?;
+        let re = py.allow_threads(|| search::nearest_k(&mut dt.t, p2, k));
+        Ok(PyArray::from_vec(py, re))
+    }
+
+    /// Return the vertices at a distance (in 2D) smaller than or equal to *radius* of [x, y],
+    /// by increasing distance. [x, y] can be outside the convex hull.
+    ///
+    /// :param p2: array with [x, y]-coordinates of point
+    /// :param radius: the search radius
+    /// :return: an array of vertex indices
+    ///
+    /// >>> dt.vertices_in_radius([32.1, 66.9], 5.0)
+    /// array([12, 7], dtype=uint64)
+    #[pyo3(signature = (p2, radius))]
+    fn vertices_in_radius<'py>(
+        &self,
+        py: Python<'py>,
+        p2: [f64; 2],
+        radius: f64,
+    ) -> PyResult<&'py PyArray<usize, numpy::Ix1>> {
+        let dt = &mut *self.lock()?;
+        if radius.is_nan() || radius < 0.0 {
+            return Err(exceptions::PyValueError::new_err("radius must be positive"));
+        }
+        let re = py.allow_threads(|| search::in_radius(&mut dt.t, p2, radius));
+        Ok(PyArray::from_vec(py, re))
+    }
+
+    /// Return the vertices inside a bounding box (or on its boundary).
+    ///
+    /// :param bbox: the bounding box [minx, miny, maxx, maxy]
+    /// :return: an array of vertex indices (sorted)
+    ///
+    /// >>> dt.vertices_in_bbox([30.0, 60.0, 40.0, 70.0])
+    /// array([7, 12, 41], dtype=uint64)
+    #[pyo3(signature = (bbox))]
+    fn vertices_in_bbox<'py>(
+        &self,
+        py: Python<'py>,
+        bbox: [f64; 4],
+    ) -> PyResult<&'py PyArray<usize, numpy::Ix1>> {
+        let dt = &mut *self.lock()?;
+        if !(bbox[0] <= bbox[2] && bbox[1] <= bbox[3]) {
+            return Err(exceptions::PyValueError::new_err(
+                "bbox must be [minx, miny, maxx, maxy]",
+            ));
+        }
+        let re = py.allow_threads(|| search::in_bbox(&mut dt.t, bbox));
+        Ok(PyArray::from_vec(py, re))
+    }
+
+    /// Return the vertices inside a polygon.
+    /// The polygon is a ring of [x, y] coordinates (the first and last can be the same),
+    /// holes are not supported.
+    ///
+    /// :param polygon: an array of [x, y] coordinates
+    /// :return: an array of vertex indices (sorted)
+    ///
+    /// >>> dt.vertices_in_polygon([[30.0, 60.0], [40.0, 60.0], [35.0, 70.0]])
+    /// array([12, 41], dtype=uint64)
+    #[pyo3(signature = (polygon))]
+    fn vertices_in_polygon<'py>(
+        &self,
+        py: Python<'py>,
+        polygon: Vec<[f64; 2]>,
+    ) -> PyResult<&'py PyArray<usize, numpy::Ix1>> {
+        let dt = &mut *self.lock()?;
+        if polygon.len() < 3 {
+            return Err(exceptions::PyValueError::new_err(
+                "polygon must have at least 3 vertices",
+            ));
+        }
+        let re = py.allow_threads(|| search::in_polygon(&mut dt.t, &polygon));
+        Ok(PyArray::from_vec(py, re))
+    }
+
+    /// Estimate the z-value with 8 different spatial interpolation methods:
     ///
     /// 1. **IDW**: inverse distance weighing
     /// 2. **Laplace**: a faster NNI with almost the same results
     /// 3. **NN**: nearest neighbour
     /// 4. **NNI**: natural neighbour interpolation
     /// 5. **TIN**: linear interpolation in TIN
+    /// 6. **SibsonC1**: Sibson's C1 natural neighbour interpolation, the gradients at the vertices are estimated and the surface is smooth (except at the vertices)
+    /// 7. **CloughTocher**: the Clough-Tocher C1 interpolant, each triangle is split in 3 and a cubic patch is used in each, with the estimated gradients at the vertices
+    /// 8. **Kriging**: ordinary kriging, with a variogram (see :func:`fit_variogram`) and the natural neighbours or the k-rings of the location as neighbours
     ///
     /// The interpolation does not modify the triangulation, it only returns an estimation for
     /// the z-values at the xy-location provided as argument.
     ///
-    /// :param interpolant: a JSON/dict Python object with a `"method": "IDW"` (or others). IDW has 2 more params: "power" and "radius"; NNI can have the Voronoi cells precomputed with "precompute"
+    /// :param interpolant: a JSON/dict Python object with a `"method": "IDW"` (or others). IDW has a "power" and a search "radius" and/or "k" (the k nearest vertices are used), and optionally "min_points" (default=1, if fewer vertices are found the radius is doubled until enough are found, 0 to disable), "max_points" (the closest ones are kept, at least "min_points"), "sectors" (4 or 8, the neighbours are balanced in the quadrants/octants around the location, at most max_points/sectors in each), and an ellipse with "minor_radius" and "angle" (in degrees, CCW from the x-axis, of the major axis of length "radius"); NNI can have the Voronoi cells precomputed with "precompute"; Kriging has a "variogram" (a dict with "model" (spherical/exponential/gaussian), "sill", "range", and optionally "nugget") and "neighbours" ("natural" (default) or k, then the vertices at most k edges away from the triangle containing the location are used)
     /// :param locations: an array of [x, y] locations where the function should interpolate
     /// :param strict: (default=False) if the interpolation cannot find a value (because outside convex hull or search radius too small) then strict==True will stop at the first error and return that error. If strict==False then numpy.nan is returned.
-    /// :return: a numpy array containing all the interpolation values (same order as input array). numpy.nan when location is outside the convexhull of the DT.
+    /// :param attribute: (default=None) the name of a numerical extra attribute to interpolate instead of the z-values, numpy.nan is returned if one of the vertices used for a location doesn't have the attribute
+    /// :param return_variance: (default=False) only for Kriging, the kriging variance at each location is also returned
+    /// :return: a numpy array containing all the interpolation values (same order as input array). numpy.nan when location is outside the convexhull of the DT. With return_variance=True, a tuple of 2 arrays (the values and the variances).
     ///
     /// >>> locs = [ [50.0, 41.1], [101.1, 33.2], [80.0, 66.0] ]
     /// >>> re = dt.interpolate({"method": "NNI", "precompute": True}, locs)
     /// >>> re = dt.interpolate({"method": "Laplace"}, locs)
     /// >>> re = dt.interpolate({"method": "IDW", "radius": 20, "power": 2.0}, locs, strict=True)
+    /// >>> re = dt.interpolate({"method": "TIN"}, locs, attribute="intensity")
+    /// >>> v = dt.fit_variogram("spherical")
+    /// >>> (re, var) = dt.interpolate({"method": "Kriging", "variogram": v, "neighbours": 2}, locs, return_variance=True)
+    #[pyo3(signature = (interpolant, locations, strict=false, attribute=None, return_variance=false))]
+    fn interpolate(
+        &self,
+        py: Python<'_>,
+        interpolant: &PyDict,
+        locations: Vec<[f64; 2]>,
+        strict: bool,
+        attribute: Option<String>,
+        return_variance: bool,
+    ) -> PyResult<PyObject> {
+        let dt = &mut *self.lock()?;
+        if let Some(a) = &attribute {
+            if !dt.numerical_attributes().contains(a) {
+                let s = format!("'{}' is not a numerical extra attribute", a);
+                return Err(exceptions::PyValueError::new_err(s));
+            }
+        }
+        if return_variance {
+            let (variogram, neighbours) = match interpolation_method(interpolant)? {
+                interpolation::Method::Kriging {
+                    variogram,
+                    neighbours,
+                } => (variogram, neighbours),
+                _ => {
+                    return Err(exceptions::PyValueError::new_err(
+                        "The variance is only available with Kriging",
+                    ))
+                }
+            };
+            let (re, var) = py.allow_threads(|| {
+                dt.interpolate_kriging(&variogram, &neighbours, &locations, strict, &attribute)
+            })?;
+            return Ok((PyArray::from_vec(py, re), PyArray::from_vec(py, var)).into_py(py));
+        }
+        let method = interpolation_method(interpolant)?;
+        let mut re =
+            py.allow_threads(|| dt.interpolate_values(&method, &locations, strict, &[attribute]))?;
+        Ok(PyArray::from_vec(py, re.pop().unwrap()).into_py(py))
+    }
+
+    /// Return the weights of the vertices used to interpolate at one location, the
+    /// interpolated value is the weighted sum of the values of the vertices.
+    /// The weights are the barycentric coordinates for "TIN", the Sibson coordinates for "NNI",
+    /// the Laplace coordinates for "Laplace", the normalised inverse distances for "IDW",
+    /// and 1.0 for the nearest vertex for "NN"; their sum is 1.0.
+    /// An exception is thrown if the interpolation is not possible at that location.
+    /// The C1 methods ("SibsonC1" and "CloughTocher") are not a weighted sum and are not supported.
+    ///
+    /// :param interpolant: a JSON/dict Python object with a `"method": "IDW"` (or others), see :func:`interpolate`
+    /// :param location: the [x, y] location
+    /// :return: a tuple with an array of vertex indices and an array of their weights
+    ///
+    /// >>> (vis, ws) = dt.interpolation_weights({"method": "TIN"}, [50.0, 41.1])
+    /// >>> z = np.sum(dt.points[vis, 2] * ws)
+    #[pyo3(signature = (interpolant, location))]
+    fn interpolation_weights<'py>(
+        &self,
+        py: Python<'py>,
+        interpolant: &PyDict,
+        location: [f64; 2],
+    ) -> PyResult<WeightsArrays<'py>> {
+        let dt = &mut *self.lock()?;
+        let method = interpolation_method(interpolant)?;
+        if !method.has_weights() {
+            return Err(exceptions::PyValueError::new_err(
+                "The C1 interpolation methods have no weights",
+            ));
+        }
+        let vorareas = match method {
+            interpolation::Method::NNI { precompute: true } => {
+                Some(interpolation::voronoi_areas(&dt.t))
+            }
+            _ => None,
+        };
+        match interpolation::weights(&mut dt.t, &method, location, vorareas.as_deref()) {
+            Ok(ws) => {
+                let (vis, ws): (Vec<usize>, Vec<f64>) = ws.into_iter().unzip();
+                Ok((PyArray::from_vec(py, vis), PyArray::from_vec(py, ws)))
+            }
+            Err(why) => Err(exceptions::PyException::new_err(interpolation_error(
+                &why, location,
+            ))),
+        }
+    }
+
+    /// Return the weights of the vertices used to interpolate at several locations, as a sparse
+    /// matrix in the `CSR format <https://docs.scipy.org/doc/scipy/reference/generated/scipy.sparse.csr_array.html>`_:
+    /// one row per location and one column per vertex (same size as :func:`points`).
+    /// Interpolating any value of the vertices is then a sparse matrix-vector product.
+    /// See :func:`interpolation_weights` for the weights of each method (the C1 methods are not supported).
+    ///
+    /// :param interpolant: a JSON/dict Python object with a `"method": "IDW"` (or others), see :func:`interpolate`
+    /// :param locations: an array of [x, y] locations
+    /// :param strict: (default=False) if the interpolation is not possible at a location, then strict==True throws an exception; if strict==False the row of the location is empty
+    /// :return: a tuple (indptr, indices, data) of arrays
+    ///
+    /// >>> import scipy.sparse
+    /// >>> (indptr, indices, data) = dt.interpolation_weights_many({"method": "NNI"}, locs)
+    /// >>> m = scipy.sparse.csr_array((data, indices, indptr), shape=(len(locs), dt.points.shape[0]))
+    /// >>> z = m @ np.nan_to_num(dt.points[:, 2]) #-- same as dt.interpolate({"method": "NNI"}, locs)
     #[pyo3(signature = (interpolant, locations, strict=false))]
-    fn interpolate<'py>(
-        &mut self,
+    fn interpolation_weights_many<'py>(
+        &self,
         py: Python<'py>,
         interpolant: &PyDict,
         locations: Vec<[f64; 2]>,
         strict: bool,
-    ) -> PyResult<&'py PyArray<f64, numpy::Ix1>> {
-        match interpolant.get_item("method") {
-            None => return Err(exceptions::PyValueError::new_err("Wrong parameters")),
-            Some(m) => {
-                let m: String = m.extract()?;
-                let mut re: Vec<f64> = Vec::with_capacity(locations.len());
-                match m.as_str() {
-                    "IDW" => {
-                        let radius = interpolant.get_item("radius");
-                        let power = interpolant.get_item("power");
-                        if radius.is_none() || power.is_none() {
-                            return Err(exceptions::PyValueError::new_err("Wrong parameters"));
-                        } else {
-                            let r1: f64 = radius.unwrap().extract()?;
-                            if r1 <= 0.0 {
-                                return Err(exceptions::PyValueError::new_err("Wrong parameters"));
-                            }
-                            let p1: f64 = power.unwrap().extract()?;
-                            if p1 <= 0.0 {
-                                return Err(exceptions::PyValueError::new_err("Wrong parameters"));
-                            }
-                            for loc in locations {
-                                let a = self.interpolate_idw(loc, r1, p1);
-                                if a.is_ok() {
-                                    re.push(a.unwrap());
-                                } else {
-                                    if strict == true {
-                                        let s = format!(
-                                            "({}, {}) no points in search radius",
-                                            loc[0], loc[1]
-                                        );
-                                        return Err(exceptions::PyException::new_err(s));
-                                    } else {
-                                        re.push(f64::NAN);
-                                    }
-                                }
-                            }
-                            Ok(PyArray::from_vec(py, re))
-                        }
-                    }
-                    "Laplace" => {
-                        for loc in locations {
-                            let a = self.interpolate_laplace(loc);
-                            if a.is_ok() {
-                                re.push(a.unwrap());
-                            } else {
-                                if strict == true {
-                                    let s = format!(
-                                        "({}, {}) is outside the convex hull",
-                                        loc[0], loc[1]
-                                    );
-                                    return Err(exceptions::PyException::new_err(s));
-                                } else {
-                                    re.push(f64::NAN);
-                                }
-                            }
-                        }
-                        Ok(PyArray::from_vec(py, re))
-                    }
-                    "NN" => {
-                        for loc in locations {
-                            let a = self.interpolate_nn(loc);
-                            if a.is_ok() {
-                                re.push(a.unwrap());
-                            } else {
-                                if strict == true {
-                                    let s = format!(
-                                        "({}, {}) is outside the convex hull",
-                                        loc[0], loc[1]
-                                    );
-                                    return Err(exceptions::PyException::new_err(s));
-                                } else {
-                                    re.push(f64::NAN);
-                                }
-                            }
-                        }
-                        Ok(PyArray::from_vec(py, re))
-                    }
-                    "NNI" => {
-                        let re2 = interpolant.get_item("precompute");
-                        let mut precompute: bool = false;
-                        if re2.is_some() {
-                            precompute = re2.unwrap().extract()?;
-                        }
-                        for loc in locations {
-                            let a = self.interpolate_nni(loc, precompute);
-                            if a.is_ok() {
-                                re.push(a.unwrap());
-                            } else {
-                                if strict == true {
-                                    let s = format!(
-                                        "({}, {}) is outside the convex hull",
-                                        loc[0], loc[1]
-                                    );
-                                    return Err(exceptions::PyException::new_err(s));
-                                } else {
-                                    re.push(f64::NAN);
-                                }
-                            }
+    ) -> PyResult<CsrArrays<'py>> {
+        let dt = &mut *self.lock()?;
+        let method = interpolation_method(interpolant)?;
+        if !method.has_weights() {
+            return Err(exceptions::PyValueError::new_err(
+                "The C1 interpolation methods have no weights",
+            ));
+        }
+        let (indptr, indices, data) = py.allow_threads(|| {
+            let vorareas = match method {
+                interpolation::Method::NNI { precompute: true } => {
+                    Some(interpolation::voronoi_areas(&dt.t))
+                }
+                _ => None,
+            };
+            let mut indptr: Vec<usize> = Vec::with_capacity(locations.len() + 1);
+            let mut indices: Vec<usize> = Vec::new();
+            let mut data: Vec<f64> = Vec::new();
+            indptr.push(0);
+            for loc in &locations {
+                match interpolation::weights(&mut dt.t, &method, *loc, vorareas.as_deref()) {
+                    Ok(mut ws) => {
+                        ws.sort_unstable_by_key(|(vi, _)| *vi);
+                        for (vi, w) in ws {
+                            indices.push(vi);
+                            data.push(w);
                         }
-                        Ok(PyArray::from_vec(py, re))
                     }
-                    "TIN" => {
-                        for loc in locations {
-                            let a = self.interpolate_tin_linear(loc);
-                            if a.is_ok() {
-                                re.push(a.unwrap());
-                            } else {
-                                if strict == true {
-                                    let s = format!(
-                                        "({}, {}) is outside the convex hull",
-                                        loc[0], loc[1]
-                                    );
-                                    return Err(exceptions::PyException::new_err(s));
-                                } else {
-                                    re.push(f64::NAN);
-                                }
-                            }
+                    Err(why) => {
+                        if strict {
+                            return Err(exceptions::PyException::new_err(interpolation_error(
+                                &why, *loc,
+                            )));
                         }
-                        Ok(PyArray::from_vec(py, re))
-                    }
-                    _ => {
-                        return Err(exceptions::PyValueError::new_err(
-                            "Unknown interpolation method",
-                        ));
                     }
                 }
+                indptr.push(indices.len());
+            }
+            Ok((indptr, indices, data))
+        })?;
+        Ok((
+            PyArray::from_vec(py, indptr),
+            PyArray::from_vec(py, indices),
+            PyArray::from_vec(py, data),
+        ))
+    }
+
+    /// Compute the experimental variogram of the z-values of the vertices: the pairs of vertices
+    /// at a distance smaller than max_distance are grouped in bins (of equal width) and the
+    /// semivariance of each bin is half the mean of the squared differences of the z-values.
+    /// The pairs are found by walking the DT from each vertex, for large datasets a small
+    /// max_distance should be used.
+    ///
+    /// :param bins: (default=20) the number of bins
+    /// :param max_distance: (default=None) the maximum distance between 2 vertices, if None 10 times the mean length of the edges of the DT (at most half the diagonal of its bbox)
+    /// :return: a tuple of 3 arrays: the lags (the mean distance of the pairs in each bin), the semivariances (numpy.nan for an empty bin), and the number of pairs in each bin
+    ///
+    /// >>> (lags, gammas, counts) = dt.experimental_variogram(bins=15, max_distance=50.0)
+    #[pyo3(signature = (bins=20, max_distance=None))]
+    fn experimental_variogram<'py>(
+        &self,
+        py: Python<'py>,
+        bins: usize,
+        max_distance: Option<f64>,
+    ) -> PyResult<VariogramArrays<'py>> {
+        let dt = &mut *self.lock()?;
+        let maxd = dt.variogram_max_distance(bins, max_distance)?;
+        let (lags, gammas, counts) =
+            py.allow_threads(|| kriging::experimental(&mut dt.t, bins, maxd));
+        Ok((
+            PyArray::from_vec(py, lags),
+            PyArray::from_vec(py, gammas),
+            PyArray::from_vec(py, counts),
+        ))
+    }
+
+    /// Fit a variogram model to the experimental variogram of the z-values of the vertices
+    /// (see :func:`experimental_variogram`), the least-squares are weighted by the number of
+    /// pairs in each bin. The returned dict can be used as the "variogram" for Kriging
+    /// with :func:`interpolate`.
+    /// The range of the exponential and gaussian models is the practical range (where 95%
+    /// of the sill is reached).
+    ///
+    /// :param model: (default="spherical") "spherical", "exponential", or "gaussian"
+    /// :param bins: (default=20) the number of bins of the experimental variogram
+    /// :param max_distance: (default=None) the maximum distance between 2 vertices, if None 10 times the mean length of the edges of the DT (at most half the diagonal of its bbox)
+    /// :return: a dict with the "model", the "sill" (it includes the nugget), the "range", and the "nugget"
+    ///
+    /// >>> v = dt.fit_variogram("exponential")
+    /// >>> v
+    /// {'model': 'exponential', 'sill': 12.1, 'range': 44.7, 'nugget': 0.3}
+    #[pyo3(signature = (model="spherical", bins=20, max_distance=None))]
+    fn fit_variogram<'py>(
+        &self,
+        py: Python<'py>,
+        model: &str,
+        bins: usize,
+        max_distance: Option<f64>,
+    ) -> PyResult<&'py PyDict> {
+        let dt = &mut *self.lock()?;
+        let model = match kriging::Model::from_name(model) {
+            Some(m) => m,
+            None => return Err(exceptions::PyValueError::new_err("Unknown variogram model")),
+        };
+        let maxd = dt.variogram_max_distance(bins, max_distance)?;
+        let v = py.allow_threads(|| {
+            let (lags, gammas, counts) = kriging::experimental(&mut dt.t, bins, maxd);
+            kriging::fit(model, &lags, &gammas, &counts)
+        });
+        match v {
+            Some(v) => {
+                let d = PyDict::new(py);
+                d.set_item("model", v.model.name())?;
+                d.set_item("sill", v.sill)?;
+                d.set_item("range", v.range)?;
+                d.set_item("nugget", v.nugget)?;
+                Ok(d)
             }
+            None => Err(exceptions::PyException::new_err(
+                "Not enough pairs of vertices to fit a variogram",
+            )),
+        }
+    }
+
+    /// Interpolate all the numerical extra attributes (f64, i64, u64) at once, with
+    /// the same methods and parameters as :func:`interpolate`.
+    /// numpy.nan is returned for an attribute if one of the vertices used for a location
+    /// doesn't have it.
+    ///
+    /// :param interpolant: a JSON/dict Python object with a `"method": "IDW"` (or others), see :func:`interpolate`
+    /// :param locations: an array of [x, y] locations where the function should interpolate
+    /// :param strict: (default=False) see :func:`interpolate`
+    /// :return: a NumPy structured array with one float64 field for each numerical attribute
+    ///
+    /// >>> dt = startinpy.DT(np.dtype([("intensity", float), ("temperature", float)]))
+    /// >>> ...
+    /// >>> re = dt.interpolate_attributes({"method": "Laplace"}, locs)
+    /// >>> re["temperature"]
+    /// array([21.2, 22.9, nan])
+    #[pyo3(signature = (interpolant, locations, strict=false))]
+    fn interpolate_attributes(
+        &self,
+        py: Python<'_>,
+        interpolant: &PyDict,
+        locations: Vec<[f64; 2]>,
+        strict: bool,
+    ) -> PyResult<PyObject> {
+        let dt = &mut *self.lock()?;
+        let names = dt.numerical_attributes();
+        if names.is_empty() {
+            return Err(exceptions::PyValueError::new_err(
+                "The DT has no numerical extra attributes",
+            ));
         }
+        let method = interpolation_method(interpolant)?;
+        let attributes: Vec<Option<String>> = names.iter().cloned().map(Some).collect();
+        let values =
+            py.allow_threads(|| dt.interpolate_values(&method, &locations, strict, &attributes))?;
+        let np = py.import("numpy")?;
+        let fields: Vec<(String, String)> = names
+            .iter()
+            .map(|n| (n.clone(), "<f8".to_string()))
+            .collect();
+        let dtype = np.call_method1("dtype", (fields,))?;
+        let re = np.call_method1("empty", (locations.len(), dtype))?;
+        for (name, v) in names.iter().zip(values) {
+            re.set_item(name, PyArray::from_vec(py, v))?;
+        }
+        Ok(re.into())
     }
 
     /// Write an `OBJ <https://en.wikipedia.org/wiki/Wavefront_.obj_file>`_ of
@@ -1121,14 +2262,17 @@ impl DT {
     ///
     /// >>> dt.write_obj("/home/elvis/myfile.obj")
     #[pyo3(signature = (path))]
-    fn write_obj(&self, path: String) -> PyResult<()> {
-        let re = self.t.write_obj(path.to_string());
-        if re.is_err() {
-            return Err(exceptions::PyFileNotFoundError::new_err(
-                "No such file or directory",
-            ));
-        }
-        Ok(())
+    fn write_obj(&self, py: Python<'_>, path: String) -> PyResult<()> {
+        let dt = self.lock()?;
+        py.allow_threads(|| {
+            let re = dt.t.write_obj(path.to_string());
+            if re.is_err() {
+                return Err(exceptions::PyFileNotFoundError::new_err(
+                    "No such file or directory",
+                ));
+            }
+            Ok(())
+        })
     }
 
     /// Write an `PLY <https://en.wikipedia.org/wiki/PLY_(file_format)>`_ of the DT to the path (a string).
@@ -1139,14 +2283,17 @@ impl DT {
     ///
     /// >>> dt.write_ply("/home/elvis/myfile.ply")
     #[pyo3(signature = (path))]
-    fn write_ply(&self, path: String) -> PyResult<()> {
-        let re = self.t.write_ply(path.to_string());
-        if re.is_err() {
-            return Err(exceptions::PyFileNotFoundError::new_err(
-                "No such file or directory",
-            ));
-        }
-        Ok(())
+    fn write_ply(&self, py: Python<'_>, path: String) -> PyResult<()> {
+        let dt = self.lock()?;
+        py.allow_threads(|| {
+            let re = dt.t.write_ply(path.to_string());
+            if re.is_err() {
+                return Err(exceptions::PyFileNotFoundError::new_err(
+                    "No such file or directory",
+                ));
+            }
+            Ok(())
+        })
     }
 
     /// Write a `GeoJSON <https://geojson.org>`_ file of the DT (vertices+triangles) to the path (a string).
@@ -1157,65 +2304,71 @@ impl DT {
     ///
     /// >>> dt.write_geojson("/home/elvis/myfile.geojson")
     #[pyo3(signature = (path))]
-    pub fn write_geojson(&self, path: String) -> PyResult<()> {
-        let mut fc = FeatureCollection {
-            bbox: None,
-            features: vec![],
-            foreign_members: None,
-        };
-        //-- vertices
-        let allv_f = self.t.all_vertices();
-        for i in 1..allv_f.len() {
-            // println!("i: {:?}", i);
-            if self.t.is_vertex_removed(i).unwrap() == true {
-                continue;
-            }
-            let pt = Geometry::new(GeoValue::Point(vec![allv_f[i][0], allv_f[i][1]]));
-            let mut attributes = Map::new();
-            attributes.insert(String::from("id"), to_value(i.to_string()).unwrap());
-            attributes.insert(
-                String::from("z"),
-                to_value(allv_f[i][2].to_string()).unwrap(),
-            );
-            let f = Feature {
-                bbox: None,
-                geometry: Some(pt),
-                id: None,
-                properties: Some(attributes),
-                foreign_members: None,
-            };
-            fc.features.push(f);
-        }
-        //-- triangles
-        let trs = self.t.all_finite_triangles();
-        for tr in trs.iter() {
-            // s.push_str(&format!("f {} {} {}\n", tr.v[0], tr.v[1], tr.v[2]));
-            let mut l: Vec<Vec<Vec<f64>>> = vec![vec![Vec::with_capacity(1); 4]];
-            l[0][0].push(allv_f[tr.v[0]][0]);
-            l[0][0].push(allv_f[tr.v[0]][1]);
-            l[0][1].push(allv_f[tr.v[1]][0]);
-            l[0][1].push(allv_f[tr.v[1]][1]);
-            l[0][2].push(allv_f[tr.v[2]][0]);
-            l[0][2].push(allv_f[tr.v[2]][1]);
-            l[0][3].push(allv_f[tr.v[0]][0]);
-            l[0][3].push(allv_f[tr.v[0]][1]);
-            let gtr = Geometry::new(GeoValue::Polygon(l));
-            // let mut attributes = Map::new();
-            // if self.stars[]
-            // attributes.insert(String::from("active"), to_value();
-            let f = Feature {
+    pub fn write_geojson(&self, py: Python<'_>, path: String) -> PyResult<()> {
+        let dt = self.lock()?;
+        py.allow_threads(|| {
+            let mut fc = FeatureCollection {
                 bbox: None,
-                geometry: Some(gtr),
-                id: None,
-                properties: None, //Some(attributes),
+                features: vec![],
                 foreign_members: None,
             };
-            fc.features.push(f);
-        }
-        //-- write the file to disk
-        let mut fo = File::create(path)?;
-        let _ = write!(fo, "{}", fc.to_string());
-        Ok(())
+            //-- vertices
+            let allv_f = dt.t.all_vertices();
+            for i in 1..allv_f.len() {
+                // println!("i: {:?}", i);
+                if dt.t.is_vertex_removed(i).unwrap() == true {
+                    continue;
+                }
+                let pt = Geometry::new(GeoValue::Point(vec![allv_f[i][0], allv_f[i][1]]));
+                let mut attributes = Map::new();
+                attributes.insert(String::from("id"), to_value(i.to_string()).unwrap());
+                attributes.insert(
+                    String::from("z"),
+                    to_value(allv_f[i][2].to_string()).unwrap(),
+                );
+                if !dt.uids.is_empty() {
+                    attributes.insert(String::from("user_id"), json!(dt.user_id(i)));
+                }
+                let f = Feature {
+                    bbox: None,
+                    geometry: Some(pt),
+                    id: None,
+                    properties: Some(attributes),
+                    foreign_members: None,
+                };
+                fc.features.push(f);
+            }
+            //-- triangles
+            let trs = dt.t.all_finite_triangles();
+            for tr in trs.iter() {
+                // s.push_str(&format!("f {} {} {}\n", tr.v[0], tr.v[1], tr.v[2]));
+                let mut l: Vec<Vec<Vec<f64>>> = vec![vec![Vec::with_capacity(1); 4]];
+                l[0][0].push(allv_f[tr.v[0]][0]);
+                l[0][0].push(allv_f[tr.v[0]][1]);
+                l[0][1].push(allv_f[tr.v[1]][0]);
+                l[0][1].push(allv_f[tr.v[1]][1]);
+                l[0][2].push(allv_f[tr.v[2]][0]);
+                l[0][2].push(allv_f[tr.v[2]][1]);
+                l[0][3].push(allv_f[tr.v[0]][0]);
+                l[0][3].push(allv_f[tr.v[0]][1]);
+                let gtr = Geometry::new(GeoValue::Polygon(l));
+                // let mut attributes = Map::new();
+                // if dt.stars[]
+                // attributes.insert(String::from("active"), to_value();
+                let f = Feature {
+                    bbox: None,
+                    geometry: Some(gtr),
+                    id: None,
+                    properties: None, //Some(attributes),
+                    foreign_members: None,
+                };
+                fc.features.push(f);
+            }
+            //-- write the file to disk
+            let mut fo = File::create(path)?;
+            let _ = write!(fo, "{}", fc.to_string());
+            Ok(())
+        })
     }
 
     /// Write a `CityJSON <https://www.cityjson.org>`_ file of the DT (vertices+triangles) to the path (a string).
@@ -1228,164 +2381,1305 @@ impl DT {
     ///
     /// >>> dt.write_cityjson("/home/elvis/myfile.city.json")
     #[pyo3(signature = (path, digits=3))]
-    fn write_cityjson(&self, path: String, digits: usize) -> PyResult<()> {
-        let bbox = self.t.get_bbox();
-        let d: f64 = 1.0 / (f64::powf(10., digits as f64));
-        let trans = json!({
-            "scale": vec![d, d, d],
-            "translate": vec![bbox[0], bbox[1], 0.0],
-        });
-        //-- vertices
-        let allv_f = self.t.all_vertices();
-        let mut onevertex: Vec<f64> = Vec::new();
-        for (i, _each) in allv_f.iter().enumerate() {
-            if i != 0 && (self.t.is_vertex_removed(i).unwrap() == false) {
-                onevertex = vec![allv_f[i][0], allv_f[i][1], allv_f[i][2]];
-                break;
-            }
-        }
-        let mut allv_i: Vec<Vec<i64>> = Vec::new();
-        for i in 1..allv_f.len() {
-            let mut x = allv_f[i][0];
-            let mut y = allv_f[i][1];
-            let mut z = allv_f[i][2];
-            // if i == 0 || (self.t.is_vertex_removed(i).unwrap() == true) {
-            if self.t.is_vertex_removed(i).unwrap() == true {
-                x = onevertex[0];
-                y = onevertex[1];
-                z = onevertex[2];
-            }
-            x -= bbox[0];
-            y -= bbox[1];
-            let s0 = format!("{:.*}", digits, x).replace(".", "");
-            let s1 = format!("{:.*}", digits, y).replace(".", "");
-            let s2 = format!("{:.*}", digits, z).replace(".", "");
-            allv_i.push(vec![
-                s0.parse::<i64>().unwrap(),
-                s1.parse::<i64>().unwrap(),
-                s2.parse::<i64>().unwrap(),
-            ]);
-        }
-        let mut alltrs: Vec<Vec<Vec<usize>>> = Vec::new();
-        let trs = self.t.all_finite_triangles();
-        for tr in &trs {
-            let mut t: Vec<Vec<usize>> = Vec::new();
-            t.push(vec![tr.v[0] - 1, tr.v[1] - 1, tr.v[2] - 1]);
-            alltrs.push(t);
-        }
-        //-- CityObjects
-        let cos = json!({
-            "type": "TINRelief".to_owned(),
-            "geometry": [ {
-                "type": "CompositeSurface",
-                "lod": "1",
-                "boundaries": alltrs
-            }
-            ]
-        });
-        let cj = Cityjson {
-            r#type: "CityJSON".to_owned(),
-            version: "2.0".to_owned(),
-            transform: trans,
-            city_objects: json!({"myterrain": cos}),
-            vertices: allv_i,
-        };
-        // Serialize it to a JSON string.
-        let mut fo = File::create(path)?;
-        let j = serde_json::to_string(&cj);
-        let _ = write!(fo, "{}", j.unwrap());
-        Ok(())
+    fn write_cityjson(&self, py: Python<'_>, path: String, digits: usize) -> PyResult<()> {
+        let dt = self.lock()?;
+        py.allow_threads(|| {
+            let bbox = dt.t.get_bbox();
+            let d: f64 = 1.0 / (f64::powf(10., digits as f64));
+            let trans = json!({
+                "scale": vec![d, d, d],
+                "translate": vec![bbox[0], bbox[1], 0.0],
+            });
+            //-- vertices
+            let allv_f = dt.t.all_vertices();
+            let mut onevertex: Vec<f64> = Vec::new();
+            for (i, _each) in allv_f.iter().enumerate() {
+                if i != 0 && (dt.t.is_vertex_removed(i).unwrap() == false) {
+                    onevertex = vec![allv_f[i][0], allv_f[i][1], allv_f[i][2]];
+                    break;
+                }
+            }
+            let mut allv_i: Vec<Vec<i64>> = Vec::new();
+            for i in 1..allv_f.len() {
+                let mut x = allv_f[i][0];
+                let mut y = allv_f[i][1];
+                let mut z = allv_f[i][2];
+                // if i == 0 || (dt.t.is_vertex_removed(i).unwrap() == true) {
+                if dt.t.is_vertex_removed(i).unwrap() == true {
+                    x = onevertex[0];
+                    y = onevertex[1];
+                    z = onevertex[2];
+                }
+                x -= bbox[0];
+                y -= bbox[1];
+                let s0 = format!("{:.*}", digits, x).replace(".", "");
+                let s1 = format!("{:.*}", digits, y).replace(".", "");
+                let s2 = format!("{:.*}", digits, z).replace(".", "");
+                allv_i.push(vec![
+                    s0.parse::<i64>().unwrap(),
+                    s1.parse::<i64>().unwrap(),
+                    s2.parse::<i64>().unwrap(),
+                ]);
+            }
+            let mut alltrs: Vec<Vec<Vec<usize>>> = Vec::new();
+            let trs = dt.t.all_finite_triangles();
+            for tr in &trs {
+                let mut t: Vec<Vec<usize>> = Vec::new();
+                t.push(vec![tr.v[0] - 1, tr.v[1] - 1, tr.v[2] - 1]);
+                alltrs.push(t);
+            }
+            //-- CityObjects
+            let cos = json!({
+                "type": "TINRelief".to_owned(),
+                "geometry": [ {
+                    "type": "CompositeSurface",
+                    "lod": "1",
+                    "boundaries": alltrs
+                }
+                ]
+            });
+            let cj = Cityjson {
+                r#type: "CityJSON".to_owned(),
+                version: "2.0".to_owned(),
+                transform: trans,
+                city_objects: json!({"myterrain": cos}),
+                vertices: allv_i,
+            };
+            // Serialize it to a JSON string.
+            let mut fo = File::create(path)?;
+            let j = serde_json::to_string(&cj);
+            let _ = write!(fo, "{}", j.unwrap());
+            Ok(())
+        })
     }
 
-    /// Vertically exaggerate the elevation values of the vertices.
-    /// Used mostly for visualisation.
+    /// Write a `glTF 2.0 <https://www.khronos.org/gltf/>`_ file of the DT to the path (a string),
+    /// for instance to display it in three.js or Cesium.
+    /// The coordinates are translated to a local origin (the lower-left corner of
+    /// :func:`startinpy.DT.get_bbox`) so that they can be stored as float32 without precision loss,
+    /// the offset is kept in the translation of the node.
+    /// glTF is y-up, the root node thus contains a rotation of -90deg around the x-axis.
+    /// Throws an exception if the path is invalid or if the DT is empty.
     ///
-    /// :param factor: a positive value (can be <1.0 to remove exaggeration)
+    /// :param path: full path (a string) on disk of the file to create (will overwrite)
+    /// :param binary: (default=True) write a GLB (True) or a .gltf file with the buffer embedded (False)
+    /// :param normals: (default=True) write the normals of the vertices
+    /// :param vertex_colors_from: (default=None) colour the vertices with a colour ramp, either
+    ///        from the elevation ("z") or from the name of a (numerical) extra attribute
     /// :return: (nothing)
     ///
-    /// >>> dt.vertical_exaggeration(2.0)
-    /// >>> dt.vertical_exaggeration(0.5)
-    #[pyo3(signature = (factor))]
-    fn vertical_exaggeration(&mut self, factor: f64) {
-        self.t.vertical_exaggeration(factor);
+    /// >>> dt.write_gltf("/home/elvis/myfile.glb")
+    /// >>> dt.write_gltf("/home/elvis/myfile.gltf", binary=False, vertex_colors_from="z")
+    /// >>> dt.write_gltf("/home/elvis/myfile.glb", vertex_colors_from="intensity")
+    #[pyo3(signature = (path, binary=true, normals=true, vertex_colors_from=None))]
+    fn write_gltf(
+        &self,
+        py: Python<'_>,
+        path: String,
+        binary: bool,
+        normals: bool,
+        vertex_colors_from: Option<String>,
+    ) -> PyResult<()> {
+        let dt = self.lock()?;
+        py.allow_threads(|| {
+            let trs = dt.t.all_finite_triangles();
+            if trs.is_empty() {
+                return Err(exceptions::PyException::new_err("Empty triangulation"));
+            }
+            let allv_f = dt.t.all_vertices();
+            let (kept, newids) = dt.compact_vertices(&allv_f);
+            let bbox = dt.t.get_bbox();
+            let origin = [bbox[0], bbox[1], 0.0];
+            let positions: Vec<[f32; 3]> = kept
+                .iter()
+                .map(|vi| {
+                    [
+                        (allv_f[*vi][0] - origin[0]) as f32,
+                        (allv_f[*vi][1] - origin[1]) as f32,
+                        (allv_f[*vi][2] - origin[2]) as f32,
+                    ]
+                })
+                .collect();
+            let mut ns: Option<Vec<[f32; 3]>> = None;
+            if normals {
+                let mut l: Vec<[f32; 3]> = Vec::with_capacity(kept.len());
+                for vi in &kept {
+                    let n = dt.t.normal_vertex(*vi).unwrap();
+                    l.push([n[0] as f32, n[1] as f32, n[2] as f32]);
+                }
+                ns = Some(l);
+            }
+            let mut colours: Option<Vec<[f32; 3]>> = None;
+            if let Some(a) = vertex_colors_from {
+                let values: Vec<f64> = match a.as_str() {
+                    "z" => kept.iter().map(|vi| allv_f[*vi][2]).collect(),
+                    _ => {
+                        let schema = dt.t.get_attributes_schema();
+                        match schema.iter().find(|(name, _)| *name == a) {
+                            Some((_, dtype))
+                                if dtype == "f64" || dtype == "i64" || dtype == "u64" => {}
+                            _ => {
+                                let s = format!("'{}' is not a numerical extra attribute", a);
+                                return Err(exceptions::PyValueError::new_err(s));
+                            }
+                        }
+                        kept.iter()
+                            .map(|vi| match dt.t.get_vertex_attributes(*vi) {
+                                Ok(v) => v.get(&a).and_then(|x| x.as_f64()).unwrap_or(f64::NAN),
+                                Err(_) => f64::NAN,
+                            })
+                            .collect()
+                    }
+                };
+                let min = values
+                    .iter()
+                    .cloned()
+                    .filter(|x| !x.is_nan())
+                    .fold(f64::MAX, f64::min);
+                let max = values
+                    .iter()
+                    .cloned()
+                    .filter(|x| !x.is_nan())
+                    .fold(f64::MIN, f64::max);
+                let range = if max > min { max - min } else { 1.0 };
+                colours = Some(
+                    values
+                        .iter()
+                        .map(|x| match x.is_nan() {
+                            true => [0.5, 0.5, 0.5],
+                            false => gltf::colour_ramp((x - min) / range),
+                        })
+                        .collect(),
+                );
+            }
+            let mesh = gltf::Mesh {
+                positions,
+                normals: ns,
+                colours,
+                indices: trs
+                    .iter()
+                    .map(|tr| {
+                        [
+                            newids[tr.v[0]] as u32,
+                            newids[tr.v[1]] as u32,
+                            newids[tr.v[2]] as u32,
+                        ]
+                    })
+                    .collect(),
+                translation: origin,
+            };
+            let re = gltf::write(&path, &mesh, binary);
+            if re.is_err() {
+                return Err(exceptions::PyFileNotFoundError::new_err(
+                    "No such file or directory",
+                ));
+            }
+            Ok(())
+        })
     }
 
-    /// Returns True if some vertices are marked as to be deleted (but still in memory)
-    /// , False otherwise.
+    /// Write one `quantized-mesh-1.0 <https://github.com/CesiumGS/quantized-mesh>`_ tile
+    /// (the terrain format of CesiumJS) to the path (a string).
+    /// The TIN is cut along the bounds of the tile, and the elevation of the new vertices
+    /// on the boundary is linearly interpolated in the triangles.
+    /// The coordinates of the DT must be longitude/latitude in degrees (WGS84) and the elevations
+    /// in metres.
+    /// Throws an exception if the path is invalid or if no triangles are inside the bounds.
     ///
-    /// :return: True/False
-    fn has_garbage(&self) -> PyResult<bool> {
-        Ok(self.t.has_garbage())
-    }
+    /// :param path: full path (a string) on disk of the file to create (will overwrite)
+    /// :param tile_bounds: the bounds of the tile: [west, south, east, north]
+    /// :param normals: (default=False) add the extension with the oct-encoded vertex normals
+    /// :return: (nothing)
+    ///
+    /// >>> dt.write_quantized_mesh("/home/elvis/0.terrain", [4.0, 52.0, 4.1, 52.1])
+    #[pyo3(signature = (path, tile_bounds, normals=false))]
+    fn write_quantized_mesh(
+        &self,
+        py: Python<'_>,
+        path: String,
+        tile_bounds: [f64; 4],
+        normals: bool,
+    ) -> PyResult<()> {
+        let dt = self.lock()?;
+        py.allow_threads(|| {
+            if tile_bounds[0] >= tile_bounds[2] || tile_bounds[1] >= tile_bounds[3] {
+                return Err(exceptions::PyValueError::new_err("Invalid tile bounds"));
+            }
+            let (pts, trs) = dt.compact_mesh();
+            let tile = quantizedmesh::clip(&pts, &trs, &tile_bounds);
+            if tile.trs.is_empty() {
+                return Err(exceptions::PyValueError::new_err(
+                    "No triangles inside the tile bounds",
+                ));
+            }
+            let re = quantizedmesh::write(&path, &tile, &tile_bounds, normals);
+            if re.is_err() {
+                return Err(exceptions::PyFileNotFoundError::new_err(
+                    "No such file or directory",
+                ));
+            }
+            Ok(())
+        })
+    }
+
+    /// Cut the DT along the tiles of one zoom level of the geographic tiling scheme of
+    /// Cesium (2 tiles at level 0, each of 180deg), and write each tile that is not empty
+    /// as a `quantized-mesh-1.0 <https://github.com/CesiumGS/quantized-mesh>`_ file
+    /// in the folder (as "zoom/x/y.terrain", y is counted from the south).
+    /// The coordinates of the DT must be longitude/latitude in degrees (WGS84) and the elevations
+    /// in metres.
+    ///
+    /// :param folder: full path (a string) on disk of the folder (the subfolders are created)
+    /// :param zoom: the zoom level (at most 30)
+    /// :param normals: (default=False) add the extension with the oct-encoded vertex normals
+    /// :return: a list of the tiles written (row by row, from the south), each is [zoom, x, y]
+    ///
+    /// >>> tiles = dt.write_quantized_mesh_tiles("/home/elvis/terrain/", 14)
+    #[pyo3(signature = (folder, zoom, normals=false))]
+    fn write_quantized_mesh_tiles(
+        &self,
+        py: Python<'_>,
+        folder: String,
+        zoom: u32,
+        normals: bool,
+    ) -> PyResult<Vec<[u32; 3]>> {
+        let dt = self.lock()?;
+        py.allow_threads(|| {
+            let bbox = dt.t.get_bbox();
+            if bbox[0] < -180.0 || bbox[2] > 180.0 || bbox[1] < -90.0 || bbox[3] > 90.0 {
+                return Err(exceptions::PyValueError::new_err(
+                    "Coordinates must be longitude/latitude in degrees",
+                ));
+            }
+            if zoom > quantizedmesh::MAX_ZOOM {
+                let s = format!("zoom must be <= {}", quantizedmesh::MAX_ZOOM);
+                return Err(exceptions::PyValueError::new_err(s));
+            }
+            let (pts, trs) = dt.compact_mesh();
+            let mut written: Vec<[u32; 3]> = Vec::new();
+            quantizedmesh::tiling(&pts, &trs, zoom, |[x, y], trs| {
+                let tile_bounds = quantizedmesh::tile_bounds(zoom, x, y);
+                let tile = quantizedmesh::clip(&pts, trs, &tile_bounds);
+                if tile.trs.is_empty() {
+                    return Ok(());
+                }
+                let dir = std::path::Path::new(&folder)
+                    .join(zoom.to_string())
+                    .join(x.to_string());
+                std::fs::create_dir_all(&dir)?;
+                let path = dir.join(format!("{}.terrain", y));
+                quantizedmesh::write(path.to_str().unwrap(), &tile, &tile_bounds, normals)?;
+                written.push([zoom, x, y]);
+                std::io::Result::Ok(())
+            })?;
+            Ok(written)
+        })
+    }
+
+    /// Write a `LandXML <http://www.landxml.org>`_ file of the DT to the path (a string).
+    /// One TIN Surface is created, with its points (``<Pnts>``) and triangles (``<Faces>``).
+    /// The ids of the points are 1-based, and the infinite vertex and the removed vertices
+    /// are omitted (thus the vertices are renumbered); the user IDs (if any) are the names
+    /// of the points.
+    /// Throws an exception if the path is invalid.
+    ///
+    /// :param path: full path (a string) on disk of the file to create (will overwrite)
+    /// :param name: (default="TIN") the name of the Surface
+    /// :param units: (default="meter") the linear unit: "meter", "foot", or "USSurveyFoot"
+    /// :return: (nothing)
+    ///
+    /// >>> dt.write_landxml("/home/elvis/myfile.xml", name="myterrain")
+    #[pyo3(signature = (path, name="TIN", units="meter"))]
+    fn write_landxml(&self, py: Python<'_>, path: String, name: &str, units: &str) -> PyResult<()> {
+        let dt = self.lock()?;
+        py.allow_threads(|| {
+            match units {
+                "meter" | "foot" | "USSurveyFoot" => (),
+                _ => {
+                    let s = format!("'{}' is an unknown unit (meter/foot/USSurveyFoot)", units);
+                    return Err(exceptions::PyValueError::new_err(s));
+                }
+            }
+            let (pts, trs) = dt.compact_mesh();
+            let mut uids: Vec<i64> = Vec::new();
+            if !dt.uids.is_empty() {
+                let (kept, _) = dt.compact_vertices(&dt.t.all_vertices());
+                uids = kept.iter().map(|vi| dt.user_id(*vi)).collect();
+            }
+            let re = landxml::write(&path, name, units, &pts, &trs, &uids);
+            if re.is_err() {
+                return Err(exceptions::PyFileNotFoundError::new_err(
+                    "No such file or directory",
+                ));
+            }
+            Ok(())
+        })
+    }
+
+    /// Read the points of a TIN Surface in a `LandXML <http://www.landxml.org>`_ file
+    /// and insert them in the DT (the triangles of the file are not used, and the breaklines
+    /// are ignored).
+    /// Throws an exception if the path is invalid or if the file has no such Surface.
+    ///
+    /// :param path: full path (a string) on disk of the file to read
+    /// :param name: (default=None) the name of the Surface to read, the first one if None
+    /// :param optional insertionstrategy: "AsIs" (*default*), "BBox", "Hilbert", or "BRIO"
+    ///    (see :func:`insert`)
+    /// :return: the number of points read
+    ///
+    /// >>> dt = startinpy.DT()
+    /// >>> dt.read_landxml("/home/elvis/myfile.xml", insertionstrategy="BRIO")
+    /// 1432
+    #[pyo3(signature = (path, name=None, insertionstrategy="AsIs"))]
+    fn read_landxml(
+        &self,
+        py: Python<'_>,
+        path: String,
+        name: Option<&str>,
+        insertionstrategy: &str,
+    ) -> PyResult<usize> {
+        let dt = &mut *self.lock()?;
+        py.allow_threads(|| {
+            if !std::path::Path::new(&path).exists() {
+                return Err(exceptions::PyFileNotFoundError::new_err(
+                    "No such file or directory",
+                ));
+            }
+            match landxml::read_points(&path, name) {
+                Ok(pts) => {
+                    let n = pts.len();
+                    dt.insert_with_strategy(pts, insertionstrategy)?;
+                    Ok(n)
+                }
+                Err(why) => Err(exceptions::PyValueError::new_err(why)),
+            }
+        })
+    }
+
+    /// Write an `STL <https://en.wikipedia.org/wiki/STL_(file_format)>`_ file of the DT
+    /// to the path (a string).
+    /// With ``solid=True`` the boundary of the convex hull is extruded down to the
+    /// plane ``base_z`` and the bottom is closed, so that the mesh is a closed
+    /// (manifold) volume, eg for 3D printing.
+    /// Notice that the binary STL stores the coordinates as float32.
+    /// Throws an exception if the path is invalid.
+    ///
+    /// :param path: full path (a string) on disk of the file to create (will overwrite)
+    /// :param binary: (default=True) binary STL, otherwise ASCII
+    /// :param solid: (default=False) close the TIN to a solid
+    /// :param base_z: (default=None) elevation of the base plane of the solid; if None
+    ///    it is 10% of the elevation range below the lowest vertex (1.0 if all vertices have the same elevation)
+    /// :return: (nothing)
+    ///
+    /// >>> dt.write_stl("/home/elvis/myfile.stl", solid=True, base_z=0.0)
+    #[pyo3(signature = (path, binary=true, solid=false, base_z=None))]
+    fn write_stl(
+        &self,
+        py: Python<'_>,
+        path: String,
+        binary: bool,
+        solid: bool,
+        base_z: Option<f64>,
+    ) -> PyResult<()> {
+        let dt = self.lock()?;
+        py.allow_threads(|| {
+            let (pts, trs) = match solid {
+                true => dt.solid_mesh(base_z)?,
+                false => dt.compact_mesh(),
+            };
+            let re = mesh::write_stl(&path, &pts, &trs, binary);
+            if re.is_err() {
+                return Err(exceptions::PyFileNotFoundError::new_err(
+                    "No such file or directory",
+                ));
+            }
+            Ok(())
+        })
+    }
+
+    /// Write an `OFF <https://en.wikipedia.org/wiki/OFF_(file_format)>`_ file of the DT
+    /// to the path (a string).
+    /// The infinite vertex and the removed vertices are omitted (thus the vertices
+    /// are renumbered, starting at 0).
+    /// With ``solid=True`` the TIN is closed to a volume, see :func:`write_stl`.
+    /// Throws an exception if the path is invalid.
+    ///
+    /// :param path: full path (a string) on disk of the file to create (will overwrite)
+    /// :param solid: (default=False) close the TIN to a solid
+    /// :param base_z: (default=None) elevation of the base plane of the solid
+    /// :return: (nothing)
+    ///
+    /// >>> dt.write_off("/home/elvis/myfile.off")
+    #[pyo3(signature = (path, solid=false, base_z=None))]
+    fn write_off(
+        &self,
+        py: Python<'_>,
+        path: String,
+        solid: bool,
+        base_z: Option<f64>,
+    ) -> PyResult<()> {
+        let dt = self.lock()?;
+        py.allow_threads(|| {
+            let (pts, trs) = match solid {
+                true => dt.solid_mesh(base_z)?,
+                false => dt.compact_mesh(),
+            };
+            let re = mesh::write_off(&path, &pts, &trs);
+            if re.is_err() {
+                return Err(exceptions::PyFileNotFoundError::new_err(
+                    "No such file or directory",
+                ));
+            }
+            Ok(())
+        })
+    }
+
+    /// Write a `VTK XML UnstructuredGrid <https://docs.vtk.org/en/latest/design_documents/VTKFileFormats.html>`_
+    /// file (.vtu) of the DT to the path (a string), eg for ParaView.
+    /// The infinite vertex and the removed vertices are omitted (thus the vertices
+    /// are renumbered, starting at 0).
+    /// The extra attributes of the vertices (see :func:`set_attributes_schema`) are stored as
+    /// PointData (except the strings, which are skipped; missing values are stored as for :func:`attributes`),
+    /// and the area (3D), the slope (in degrees) and the normal of each triangle as CellData.
+    /// Throws an exception if the path is invalid.
+    ///
+    /// :param path: full path (a string) on disk of the file to create (will overwrite)
+    /// :param binary: (default=True) the data arrays are binary (base64-encoded), otherwise ascii
+    /// :return: (nothing)
+    ///
+    /// >>> dt.write_vtu("/home/elvis/myfile.vtu")
+    #[pyo3(signature = (path, binary=true))]
+    fn write_vtu(&self, py: Python<'_>, path: String, binary: bool) -> PyResult<()> {
+        let dt = self.lock()?;
+        py.allow_threads(|| {
+            let re = vtk::write_vtu(&path, &dt.vtk_grid(), binary);
+            if re.is_err() {
+                return Err(exceptions::PyFileNotFoundError::new_err(
+                    "No such file or directory",
+                ));
+            }
+            Ok(())
+        })
+    }
+
+    /// Write a legacy VTK file (.vtk) of the DT to the path (a string), with the same
+    /// PointData and CellData as :func:`write_vtu`.
+    /// Throws an exception if the path is invalid.
+    ///
+    /// :param path: full path (a string) on disk of the file to create (will overwrite)
+    /// :param binary: (default=True) binary (big endian), otherwise ascii
+    /// :return: (nothing)
+    ///
+    /// >>> dt.write_vtk("/home/elvis/myfile.vtk", binary=False)
+    #[pyo3(signature = (path, binary=true))]
+    fn write_vtk(&self, py: Python<'_>, path: String, binary: bool) -> PyResult<()> {
+        let dt = self.lock()?;
+        py.allow_threads(|| {
+            let re = vtk::write_vtk(&path, &dt.vtk_grid(), binary);
+            if re.is_err() {
+                return Err(exceptions::PyFileNotFoundError::new_err(
+                    "No such file or directory",
+                ));
+            }
+            Ok(())
+        })
+    }
+
+    /// Vertically exaggerate the elevation values of the vertices.
+    /// Used mostly for visualisation.
+    ///
+    /// :param factor: a positive value (can be <1.0 to remove exaggeration)
+    /// :return: (nothing)
+    ///
+    /// >>> dt.vertical_exaggeration(2.0)
+    /// >>> dt.vertical_exaggeration(0.5)
+    #[pyo3(signature = (factor))]
+    fn vertical_exaggeration(&self, factor: f64) -> PyResult<()> {
+        let dt = &mut *self.lock()?;
+        dt.t.vertical_exaggeration(factor);
+        Ok(())
+    }
+
+    /// Returns True if some vertices are marked as to be deleted (but still in memory)
+    /// , False otherwise.
+    ///
+    /// :return: True/False
+    fn has_garbage(&self) -> PyResult<bool> {
+        let dt = self.lock()?;
+        Ok(dt.t.has_garbage())
+    }
 
     /// Collect garbage, that is remove from memory the vertices
     /// marked as removed (modifies the array dt.points and all indices of the triangles).
     ///
     /// **Watch out:** the vertices get new IDs, and thus the triangles get updated too.
     /// And this can be a slow operation.
+    /// The mapping from the old IDs to the new ones is returned, and the
+    /// :func:`user_ids` are kept.
+    ///
+    /// :return: an array with the new ID of each old vertex (-1 if the vertex was removed)
     ///
     /// >>> if dt.has_garbage():
-    /// >>>     dt.collect_garbage()
+    /// >>>     mapping = dt.collect_garbage()
     /// >>> assert (dt.has_garbage() == False)
-    fn collect_garbage(&mut self) -> PyResult<()> {
-        self.t.collect_garbage();
+    /// >>> newvi = mapping[oldvi]
+    fn collect_garbage<'py>(&self, py: Python<'py>) -> PyResult<&'py PyArray<i64, numpy::Ix1>> {
+        let dt = &mut *self.lock()?;
+        let mapping = py.allow_threads(|| dt.collect_garbage_mapping());
+        Ok(PyArray::from_vec(py, mapping))
+    }
+
+    /// Get/set the user IDs of the vertices, an array of integers with the same length as
+    /// :func:`points` (-1 means no ID).
+    /// Contrary to the vertex indices, the user IDs are persistent: they are kept when vertices
+    /// are removed, when :func:`collect_garbage` is called (or when startin renumbers the
+    /// vertices because the DT has no triangles anymore), and they are written to the files
+    /// with GeoJSON, VTK, and LandXML (as the names of the points).
+    /// OBJ, PLY, CityJSON, glTF, STL, OFF, and quantized-mesh cannot store them: their
+    /// vertices have no attributes, or no 64-bit integers.
+    /// A newly inserted vertex has no user ID (-1), even if it reuses the index of a removed vertex.
+    ///
+    /// >>> dt.insert(pts)
+    /// >>> dt.user_ids = np.arange(-1, pts.shape[0]) #-- ID of the point in pts
+    /// >>> dt.remove(3)
+    /// >>> dt.collect_garbage()
+    /// >>> dt.user_ids[3]
+    /// 3
+    #[getter(user_ids)]
+    fn get_user_ids<'py>(&self, py: Python<'py>) -> PyResult<&'py PyArray<i64, numpy::Ix1>> {
+        let dt = self.lock()?;
+        let n = dt.t.all_vertices().len();
+        let re: Vec<i64> = (0..n).map(|vi| dt.user_id(vi)).collect();
+        Ok(PyArray::from_vec(py, re))
+    }
+
+    #[setter(user_ids)]
+    fn set_user_ids(&self, ids: Vec<i64>) -> PyResult<()> {
+        let dt = &mut *self.lock()?;
+        if ids.len() != dt.t.all_vertices().len() {
+            return Err(exceptions::PyValueError::new_err(
+                "user_ids must have the same length as the points",
+            ));
+        }
+        dt.uids = ids;
+        dt.uids[0] = -1;
+        for vi in 1..dt.uids.len() {
+            if dt.t.is_vertex_removed(vi) != Ok(false) {
+                dt.uids[vi] = -1;
+            }
+        }
         Ok(())
     }
 }
 
 impl DT {
-    fn interpolate_nn(&mut self, p2: [f64; 2]) -> PyResult<f64> {
-        let i_nn = startin::interpolation::NN {};
-        let mut re = startin::interpolation::interpolate(&i_nn, &mut self.t, &vec![p2]);
-        let re1 = re.pop().expect("no results");
-        if re1.is_err() {
-            return Err(exceptions::PyException::new_err("Outside convex hull"));
+    /// Locks the DT, if another thread has it then waits with the GIL released.
+    /// Throws an exception if this thread already has it.
+    fn lock(&self) -> PyResult<Guard<'_>> {
+        let me = std::thread::current().id();
+        loop {
+            let g = match self.inner.try_lock() {
+                Ok(g) => g,
+                Err(TryLockError::Poisoned(e)) => e.into_inner(),
+                Err(TryLockError::WouldBlock) => {
+                    if *self.owner.lock().unwrap() == Some(me) {
+                        return Err(exceptions::PyRuntimeError::new_err("Already borrowed"));
+                    }
+                    Python::with_gil(|py| py.allow_threads(|| drop(self.inner.lock())));
+                    continue;
+                }
+            };
+            *self.owner.lock().unwrap() = Some(me);
+            return Ok(Guard {
+                inner: g,
+                owner: &self.owner,
+            });
         }
-        Ok(re1.unwrap())
     }
+}
 
-    fn interpolate_tin_linear(&mut self, p2: [f64; 2]) -> PyResult<f64> {
-        let i_tin = startin::interpolation::TIN {};
-        let mut re = startin::interpolation::interpolate(&i_tin, &mut self.t, &vec![p2]);
-        let re1 = re.pop().expect("no results");
-        if re1.is_err() {
-            return Err(exceptions::PyException::new_err("Outside convex hull"));
+/// The lock of a DT, the owner is forgotten when it is released.
+struct Guard<'a> {
+    inner: MutexGuard<'a, Inner>,
+    owner: &'a Mutex<Option<ThreadId>>,
+}
+
+impl Deref for Guard<'_> {
+    type Target = Inner;
+    fn deref(&self) -> &Inner {
+        &self.inner
+    }
+}
+
+impl DerefMut for Guard<'_> {
+    fn deref_mut(&mut self) -> &mut Inner {
+        &mut self.inner
+    }
+}
+
+impl Drop for Guard<'_> {
+    fn drop(&mut self) {
+        *self.owner.lock().unwrap() = None;
+    }
+}
+
+impl Inner {
+    /// See :func:`DT.set_attributes_schema`.
+    fn set_attributes_schema(&mut self, dtype: &PyAny) -> PyResult<bool> {
+        let descr: &PyArrayDescr = dtype.extract()?;
+        let names: &PyTuple = descr.getattr("names")?.extract()?;
+        let mut v: Vec<(String, String)> = Vec::new();
+        self.dtype.clear();
+        for name in names.iter() {
+            let name: &str = name.extract()?;
+            let field = descr.getattr("fields")?.get_item(name)?;
+            let field_type = field.get_item(0)?;
+            // println!("{:?}", field_type);
+            match field_type.to_string().as_ref() {
+                "bool" => {
+                    v.push((name.to_string(), "bool".to_string()));
+                    self.dtype.push((name.to_string(), "?".to_string()));
+                }
+                "float32" => {
+                    v.push((name.to_string(), "f64".to_string()));
+                    self.dtype.push((name.to_string(), "<f4".to_string()));
+                }
+                "float64" => {
+                    v.push((name.to_string(), "f64".to_string()));
+                    self.dtype.push((name.to_string(), "<f8".to_string()));
+                }
+                "int32" => {
+                    v.push((name.to_string(), "i64".to_string()));
+                    self.dtype.push((name.to_string(), "<i4".to_string()));
+                }
+                "int64" => {
+                    v.push((name.to_string(), "i64".to_string()));
+                    self.dtype.push((name.to_string(), "<i8".to_string()));
+                }
+                "uint32" => {
+                    v.push((name.to_string(), "u64".to_string()));
+                    self.dtype.push((name.to_string(), "<u4".to_string()));
+                }
+                "uint64" => {
+                    v.push((name.to_string(), "u64".to_string()));
+                    self.dtype.push((name.to_string(), "<u8".to_string()));
+                }
+                other if other.starts_with("<U") => {
+                    v.push((name.to_string(), "String".to_string()));
+                    self.dtype.push((name.to_string(), other.to_string()));
+                }
+                _ => {
+                    return {
+                        Err(PyErr::new::<pyo3::exceptions::PyAttributeError, _>(
+                            format!("{} is not a valid dype for startinpy", field_type),
+                        ))
+                    };
+                }
+            };
         }
-        Ok(re1.unwrap())
+        let _ = self.t.set_attributes_schema(v);
+        Ok(true)
     }
 
-    fn interpolate_laplace(&mut self, p2: [f64; 2]) -> PyResult<f64> {
-        let i_lp = startin::interpolation::Laplace {};
-        let mut re = startin::interpolation::interpolate(&i_lp, &mut self.t, &vec![p2]);
-        let re1 = re.pop().expect("no results");
-        if re1.is_err() {
-            return Err(exceptions::PyException::new_err("Outside convex hull"));
+    /// See :func:`DT.set_vertex_attributes`.
+    fn set_vertex_attributes(&mut self, vi: usize, py_kwargs: Option<&PyDict>) -> PyResult<bool> {
+        let mut m = Map::new();
+        if py_kwargs.is_some() {
+            let tmp = py_kwargs.unwrap();
+            let keys = tmp.keys();
+            let am = self.t.get_attributes_schema();
+            for k in keys {
+                let b: &String = &k.extract()?;
+                let c = am.iter().position(|(first, _)| first == b);
+                if c.is_some() {
+                    match am[c.unwrap()].1.as_ref() {
+                        "f64" => {
+                            let t1: f64 = tmp.get_item(b).unwrap().extract()?;
+                            m.insert(b.to_string(), t1.into());
+                        }
+                        "i64" => {
+                            let t1: i64 = tmp.get_item(b).unwrap().extract()?;
+                            m.insert(b.to_string(), t1.into());
+                        }
+                        "u64" => {
+                            let t1: u64 = tmp.get_item(b).unwrap().extract()?;
+                            m.insert(b.to_string(), t1.into());
+                        }
+                        "bool" => {
+                            let t1: bool = tmp.get_item(b).unwrap().extract()?;
+                            m.insert(b.to_string(), t1.into());
+                        }
+                        "String" => {
+                            let t1: String = tmp.get_item(b).unwrap().extract()?;
+                            m.insert(b.to_string(), t1.into());
+                        }
+                        &_ => continue,
+                    }
+                }
+            }
+        }
+        match self
+            .t
+            .add_vertex_attributes(vi, serde_json::to_value(m).unwrap())
+        {
+            Ok(b) => return Ok(b),
+            Err(_) => return Ok(false),
         }
-        Ok(re1.unwrap())
+    }
+    /// Inserts the points with the insertion strategy (see :func:`insert`), and keeps the
+    /// statistics.
+    fn insert_with_strategy(
+        &mut self,
+        pts: Vec<[f64; 3]>,
+        insertionstrategy: &str,
+    ) -> PyResult<()> {
+        let nv = self.t.number_of_vertices();
+        let t0 = std::time::Instant::now();
+        let mut rounds: usize = 1;
+        let sorted: Option<Vec<[f64; 3]>> = match insertionstrategy {
+            "AsIs" | "BBox" => None,
+            "Hilbert" => {
+                let order = sfc::hilbert_order(&pts);
+                Some(order.iter().map(|i| pts[*i]).collect())
+            }
+            "BRIO" => {
+                let (order, r) = sfc::brio_order(&pts);
+                rounds = r;
+                Some(order.iter().map(|i| pts[*i]).collect())
+            }
+            _ => {
+                let s = format!("'{}' is an unknown insertion strategy", insertionstrategy);
+                return Err(exceptions::PyAttributeError::new_err(s));
+            }
+        };
+        let sort_time = t0.elapsed().as_secs_f64();
+        let t1 = std::time::Instant::now();
+        self.inserting(|t| match (insertionstrategy, &sorted) {
+            ("BBox", _) => t.insert(&pts, startin::InsertionStrategy::BBox),
+            (_, Some(s)) => t.insert(s, startin::InsertionStrategy::AsIs),
+            (_, None) => t.insert(&pts, startin::InsertionStrategy::AsIs),
+        });
+        let inserted = self.t.number_of_vertices() - nv;
+        self.insert_stats = json!({
+            "strategy": insertionstrategy,
+            "points": pts.len(),
+            "inserted": inserted,
+            "duplicates": pts.len() - inserted,
+            "rounds": rounds,
+            "sort_time": sort_time,
+            "insert_time": t1.elapsed().as_secs_f64(),
+        });
+        Ok(())
+    }
+
+    /// The names of the numerical (f64, i64, u64) extra attributes.
+    fn numerical_attributes(&self) -> Vec<String> {
+        self.t
+            .get_attributes_schema()
+            .into_iter()
+            .filter(|(_, dtype)| dtype == "f64" || dtype == "i64" || dtype == "u64")
+            .map(|(name, _)| name)
+            .collect()
+    }
+
+    /// Adds the bool extra attribute "steiner" to the schema (if not already there), the
+    /// attributes of the vertices are kept.
+    fn add_steiner_attribute(&mut self) -> PyResult<()> {
+        match self
+            .t
+            .get_attributes_schema()
+            .iter()
+            .find(|(name, _)| name == "steiner")
+        {
+            Some((_, dtype)) if dtype == "bool" => return Ok(()),
+            Some(_) => {
+                return Err(exceptions::PyValueError::new_err(
+                    "The extra attribute 'steiner' exists and is not a bool",
+                ))
+            }
+            None => (),
+        }
+        //-- adding to the schema resets the attributes of all the vertices
+        let saved: Vec<(usize, Value)> = (1..self.t.all_vertices().len())
+            .filter_map(|vi| self.t.get_vertex_attributes(vi).ok().map(|a| (vi, a)))
+            .collect();
+        let _ = self
+            .t
+            .set_attributes_schema(vec![("steiner".to_string(), "bool".to_string())]);
+        self.dtype.push(("steiner".to_string(), "?".to_string()));
+        for (vi, a) in saved {
+            let _ = self.t.add_vertex_attributes(vi, a);
+        }
+        Ok(())
     }
 
-    fn interpolate_nni(&mut self, p2: [f64; 2], precompute: bool) -> PyResult<f64> {
-        let i_nni = startin::interpolation::NNI {
-            precompute: precompute,
+    /// The estimations at the locations of the z-values (None) or of the extra attributes,
+    /// one list per entry of `attributes`.
+    fn interpolate_values(
+        &mut self,
+        method: &interpolation::Method,
+        locations: &[[f64; 2]],
+        strict: bool,
+        attributes: &[Option<String>],
+    ) -> PyResult<Vec<Vec<f64>>> {
+        let vorareas = match method {
+            interpolation::Method::NNI { precompute: true } => {
+                Some(interpolation::voronoi_areas(&self.t))
+            }
+            _ => None,
         };
-        let mut re = startin::interpolation::interpolate(&i_nni, &mut self.t, &vec![p2]);
-        let re1 = re.pop().expect("no results");
-        if re1.is_err() {
-            return Err(exceptions::PyException::new_err("Outside convex hull"));
+        if let [None] = attributes {
+            if let Some(zs) = self.interpolate_z_startin(method, locations) {
+                let mut re: Vec<f64> = Vec::with_capacity(locations.len());
+                for (z, loc) in zs.into_iter().zip(locations) {
+                    match z {
+                        Ok(z) => re.push(z),
+                        Err(why) => {
+                            if strict {
+                                return Err(exceptions::PyException::new_err(interpolation_error(
+                                    &why, *loc,
+                                )));
+                            }
+                            re.push(f64::NAN);
+                        }
+                    }
+                }
+                return Ok(vec![re]);
+            }
+        }
+        let mut re: Vec<Vec<f64>> = vec![Vec::with_capacity(locations.len()); attributes.len()];
+        for loc in locations {
+            if !method.has_weights() {
+                for (name, r) in attributes.iter().zip(re.iter_mut()) {
+                    let value = |t: &startin::Triangulation, vi: usize| {
+                        vertex_value(t, vi, name.as_deref())
+                    };
+                    match interpolation::estimate(&mut self.t, method, *loc, None, value) {
+                        Ok(v) => r.push(v),
+                        Err(why) => {
+                            if strict {
+                                return Err(exceptions::PyException::new_err(interpolation_error(
+                                    &why, *loc,
+                                )));
+                            }
+                            r.push(f64::NAN);
+                        }
+                    }
+                }
+                continue;
+            }
+            match interpolation::weights(&mut self.t, method, *loc, vorareas.as_deref()) {
+                Ok(ws) => {
+                    for (name, r) in attributes.iter().zip(re.iter_mut()) {
+                        r.push(
+                            ws.iter()
+                                .map(|(vi, w)| w * vertex_value(&self.t, *vi, name.as_deref()))
+                                .sum(),
+                        );
+                    }
+                }
+                Err(why) => {
+                    if strict {
+                        return Err(exceptions::PyException::new_err(interpolation_error(
+                            &why, *loc,
+                        )));
+                    }
+                    for r in re.iter_mut() {
+                        r.push(f64::NAN);
+                    }
+                }
+            }
         }
-        Ok(re1.unwrap())
+        Ok(re)
     }
 
-    fn interpolate_idw(&mut self, p2: [f64; 2], radius: f64, pow: f64) -> PyResult<f64> {
-        let i_idw = startin::interpolation::IDW {
-            radius: radius,
-            power: pow,
+    /// The z-values at the locations with the interpolants of startin, for the methods it
+    /// has (NN, TIN, Laplace, NNI, and IDW with only a radius); None for the others.
+    /// With IDW, if the search circle is empty and `min_points` is 1 then the radius grows
+    /// (see :func:`interpolate`).
+    fn interpolate_z_startin(
+        &mut self,
+        method: &interpolation::Method,
+        locations: &[[f64; 2]],
+    ) -> Option<Vec<Result<f64, startin::StartinError>>> {
+        use startin::interpolation as si;
+        let locs = locations.to_vec();
+        match method {
+            interpolation::Method::NN => Some(si::interpolate(&si::NN {}, &mut self.t, &locs)),
+            interpolation::Method::TIN => Some(si::interpolate(&si::TIN {}, &mut self.t, &locs)),
+            interpolation::Method::Laplace => {
+                Some(si::interpolate(&si::Laplace {}, &mut self.t, &locs))
+            }
+            interpolation::Method::NNI { precompute } => Some(si::interpolate(
+                &si::NNI {
+                    precompute: *precompute,
+                },
+                &mut self.t,
+                &locs,
+            )),
+            interpolation::Method::IDW(idw)
+                if idw.max_points.is_none()
+                    && idw.sectors == 1
+                    && idw.anisotropy.is_none()
+                    && idw.min_points <= 1 =>
+            {
+                let i_idw = si::IDW {
+                    radius: idw.radius?,
+                    power: idw.power,
+                };
+                let mut re = si::interpolate(&i_idw, &mut self.t, &locs);
+                if idw.min_points == 1 {
+                    for (z, loc) in re.iter_mut().zip(locations) {
+                        if let Err(startin::StartinError::SearchCircleEmpty) = z {
+                            *z = interpolation::estimate(
+                                &mut self.t,
+                                method,
+                                *loc,
+                                None,
+                                |t, vi| t.get_point(vi).unwrap()[2],
+                            );
+                        }
+                    }
+                }
+                Some(re)
+            }
+            _ => None,
+        }
+    }
+
+    /// The kriging estimations (of z or of an attribute) at the locations, and their variances.
+    fn interpolate_kriging(
+        &mut self,
+        variogram: &kriging::Variogram,
+        neighbours: &interpolation::Neighbourhood,
+        locations: &[[f64; 2]],
+        strict: bool,
+        attribute: &Option<String>,
+    ) -> PyResult<(Vec<f64>, Vec<f64>)> {
+        let mut re: Vec<f64> = Vec::with_capacity(locations.len());
+        let mut var: Vec<f64> = Vec::with_capacity(locations.len());
+        for loc in locations {
+            match interpolation::kriging_weights(&mut self.t, variogram, neighbours, *loc) {
+                Ok((ws, v)) => {
+                    re.push(
+                        ws.iter()
+                            .map(|(vi, w)| {
+                                w * match attribute {
+                                    Some(name) => self
+                                        .t
+                                        .get_vertex_attributes(*vi)
+                                        .ok()
+                                        .and_then(|a| a.get(name).and_then(|x| x.as_f64()))
+                                        .unwrap_or(f64::NAN),
+                                    None => self.t.get_point(*vi).unwrap()[2],
+                                }
+                            })
+                            .sum(),
+                    );
+                    var.push(v);
+                }
+                Err(why) => {
+                    if strict {
+                        return Err(exceptions::PyException::new_err(interpolation_error(
+                            &why, *loc,
+                        )));
+                    }
+                    re.push(f64::NAN);
+                    var.push(f64::NAN);
+                }
+            }
+        }
+        Ok((re, var))
+    }
+
+    /// The maximum distance for the experimental variogram, after the parameters are checked.
+    /// If None, 10 times the mean length of the edges (at most half the diagonal of the
+    /// bbox): the number of pairs per vertex does not then depend on the size of the dataset.
+    fn variogram_max_distance(&self, bins: usize, max_distance: Option<f64>) -> PyResult<f64> {
+        if bins == 0 {
+            return Err(exceptions::PyValueError::new_err("bins must be > 0"));
+        }
+        let maxd = match max_distance {
+            Some(d) => d,
+            None => {
+                let (mut sum, mut n) = (0.0, 0);
+                for vi in 1..self.t.all_vertices().len() {
+                    if self.t.is_vertex_removed(vi) != Ok(false) {
+                        continue;
+                    }
+                    let p = self.t.get_point(vi).unwrap();
+                    for w in self.t.adjacent_vertices_to_vertex(vi).unwrap() {
+                        if w > vi {
+                            sum += startin::geom::distance2d(&p, &self.t.get_point(w).unwrap());
+                            n += 1;
+                        }
+                    }
+                }
+                if n == 0 {
+                    return Err(exceptions::PyException::new_err("Empty triangulation"));
+                }
+                let b = self.t.get_bbox();
+                let halfdiag = ((b[2] - b[0]).powi(2) + (b[3] - b[1]).powi(2)).sqrt() / 2.0;
+                (10.0 * sum / n as f64).min(halfdiag)
+            }
         };
-        let mut re = startin::interpolation::interpolate(&i_idw, &mut self.t, &vec![p2]);
-        let re1 = re.pop().expect("no results");
-        if re1.is_err() {
-            return Err(exceptions::PyException::new_err("Search Circle Empty"));
+        if maxd.is_nan() || maxd <= 0.0 {
+            return Err(exceptions::PyValueError::new_err(
+                "max_distance must be > 0",
+            ));
+        }
+        Ok(maxd)
+    }
+
+    /// The user ID of the vertex vi, -1 if none.
+    fn user_id(&self, vi: usize) -> i64 {
+        self.uids.get(vi).copied().unwrap_or(-1)
+    }
+
+    /// Removes the vertices (the invalid ones are skipped) and returns how many were removed.
+    fn remove_vertices(&mut self, mut vis: Vec<usize>, collect_garbage: bool) -> usize {
+        vis.sort_unstable();
+        vis.dedup();
+        let mut n: usize = 0;
+        for vi in vis {
+            if vi != 0 && self.t.is_vertex_removed(vi) == Ok(false) && self.t.remove(vi).is_ok() {
+                self.forget_user_id(vi);
+                n += 1;
+            }
+        }
+        if collect_garbage {
+            self.collect_garbage_mapping();
+        }
+        n
+    }
+
+    /// Runs f, which inserts points in the DT, and keeps the user IDs in sync: when the DT
+    /// has no triangles (eg after removing vertices) startin collects the garbage at the
+    /// next insertion, the user IDs are then reassigned from the coordinates of the vertices.
+    fn inserting<R>(&mut self, f: impl FnOnce(&mut startin::Triangulation) -> R) -> R {
+        if self.uids.is_empty() || self.t.number_of_vertices_on_convex_hull() > 0 {
+            return f(&mut self.t);
+        }
+        let xy = |p: &Vec<f64>| (p[0].to_bits(), p[1].to_bits());
+        let ids: HashMap<(u64, u64), i64> = self
+            .t
+            .all_vertices()
+            .iter()
+            .zip(&self.uids)
+            .filter(|(_, u)| **u != -1)
+            .map(|(p, u)| (xy(p), *u))
+            .collect();
+        let re = f(&mut self.t);
+        self.uids = self
+            .t
+            .all_vertices()
+            .iter()
+            .map(|p| ids.get(&xy(p)).copied().unwrap_or(-1))
+            .collect();
+        self.uids[0] = -1;
+        re
+    }
+
+    /// Sets the user ID of the vertex vi, if the DT has user IDs.
+    fn set_user_id(&mut self, vi: usize, uid: i64) {
+        if self.uids.is_empty() {
+            return;
+        }
+        if self.uids.len() <= vi {
+            self.uids.resize(vi + 1, -1);
+        }
+        self.uids[vi] = uid;
+    }
+
+    /// Whether the DT would have no triangles without the vertex vi (its other vertices
+    /// are collinear), startin then goes back to its initial phase.
+    fn is_needed_for_triangles(&self, vi: usize) -> bool {
+        if !self.t.is_vertex_convex_hull(vi) {
+            return false;
+        }
+        let robust = self.t.is_using_robust_predicates();
+        let spans = |vs: &[usize]| -> bool {
+            if vs.len() < 3 {
+                return false;
+            }
+            let a = self.t.get_point(vs[0]).unwrap();
+            let b = self.t.get_point(vs[1]).unwrap();
+            vs[2..].iter().any(|v| {
+                let c = self.t.get_point(*v).unwrap();
+                startin::geom::orient2d(&a, &b, &c, robust) != 0
+            })
+        };
+        let hull: Vec<usize> = self
+            .t
+            .convex_hull()
+            .into_iter()
+            .filter(|v| *v != vi)
+            .collect();
+        if spans(&hull) {
+            return false;
+        }
+        let others: Vec<usize> = (1..self.t.all_vertices().len())
+            .filter(|v| *v != vi && self.t.is_vertex_removed(*v) == Ok(false))
+            .collect();
+        !spans(&others)
+    }
+
+    /// The vertex vi was removed, its index can be reused by the next insertion.
+    fn forget_user_id(&mut self, vi: usize) {
+        if let Some(u) = self.uids.get_mut(vi) {
+            *u = -1;
+        }
+    }
+
+    /// Collects the garbage and returns the new index of each vertex (-1 if removed),
+    /// the user IDs are compacted too.
+    fn collect_garbage_mapping(&mut self) -> Vec<i64> {
+        let n = self.t.all_vertices().len();
+        let mut mapping: Vec<i64> = Vec::with_capacity(n);
+        let mut cur: i64 = 0;
+        for vi in 0..n {
+            if vi == 0 || self.t.is_vertex_removed(vi) == Ok(false) {
+                mapping.push(cur);
+                cur += 1;
+            } else {
+                mapping.push(-1);
+            }
+        }
+        self.t.collect_garbage();
+        if !self.uids.is_empty() {
+            let mut uids: Vec<i64> = vec![-1; cur as usize];
+            for (old, new) in mapping.iter().enumerate() {
+                if *new >= 0 {
+                    uids[*new as usize] = self.uids.get(old).copied().unwrap_or(-1);
+                }
+            }
+            self.uids = uids;
+        }
+        mapping
+    }
+
+    /// Returns the indices of the finite vertices that are not removed, and for
+    /// each vertex of the DT its new index in that list (usize::MAX if not kept).
+    /// Needed by the writers since the index 0 is the infinite vertex.
+    fn compact_vertices(&self, allv_f: &[Vec<f64>]) -> (Vec<usize>, Vec<usize>) {
+        let mut kept: Vec<usize> = Vec::with_capacity(allv_f.len());
+        let mut newids: Vec<usize> = vec![usize::MAX; allv_f.len()];
+        for (vi, newid) in newids.iter_mut().enumerate().skip(1) {
+            if self.t.is_vertex_removed(vi).unwrap() {
+                continue;
+            }
+            *newid = kept.len();
+            kept.push(vi);
+        }
+        (kept, newids)
+    }
+
+    /// Returns the mesh of the DT without the infinite vertex and the removed
+    /// vertices (the vertices of the triangles are renumbered accordingly).
+    fn compact_mesh(&self) -> (Vec<[f64; 3]>, Vec<[usize; 3]>) {
+        let allv_f = self.t.all_vertices();
+        let (kept, newids) = self.compact_vertices(&allv_f);
+        let pts: Vec<[f64; 3]> = kept
+            .iter()
+            .map(|vi| [allv_f[*vi][0], allv_f[*vi][1], allv_f[*vi][2]])
+            .collect();
+        let trs: Vec<[usize; 3]> = self
+            .t
+            .all_finite_triangles()
+            .iter()
+            .map(|tr| [newids[tr.v[0]], newids[tr.v[1]], newids[tr.v[2]]])
+            .collect();
+        (pts, trs)
+    }
+
+    /// Returns the compact mesh of the DT closed to a solid, the convex hull
+    /// is extruded down to `base_z` (see `mesh::extrude()`).
+    fn solid_mesh(&self, base_z: Option<f64>) -> PyResult<mesh::TriMesh> {
+        if self.t.number_of_triangles() == 0 {
+            return Err(exceptions::PyException::new_err("Empty triangulation"));
+        }
+        let allv_f = self.t.all_vertices();
+        let (kept, newids) = self.compact_vertices(&allv_f);
+        let mut zmin = f64::MAX;
+        let mut zmax = f64::MIN;
+        for vi in &kept {
+            zmin = zmin.min(allv_f[*vi][2]);
+            zmax = zmax.max(allv_f[*vi][2]);
+        }
+        let base_z = match base_z {
+            Some(z) => z,
+            None if zmax > zmin => zmin - 0.1 * (zmax - zmin),
+            None => zmin - 1.0,
+        };
+        if base_z >= zmin {
+            return Err(exceptions::PyValueError::new_err(
+                "base_z must be below the lowest vertex",
+            ));
+        }
+        let (pts, trs) = self.compact_mesh();
+        let hull: Vec<usize> = self.t.convex_hull().iter().map(|vi| newids[*vi]).collect();
+        Ok(mesh::extrude(
+            &pts,
+            &trs,
+            &hull,
+            base_z,
+            self.t.get_snap_tolerance(),
+        ))
+    }
+
+    /// Returns the compact mesh of the DT with the extra attributes of the vertices
+    /// and the area/slope/normal of the triangles, for the VTK writers.
+    fn vtk_grid(&self) -> vtk::Grid {
+        let allv_f = self.t.all_vertices();
+        let (kept, _newids) = self.compact_vertices(&allv_f);
+        let (pts, trs) = self.compact_mesh();
+        let mut point_data: Vec<vtk::DataArray> = Vec::new();
+        if let Some(allt) = self.t.all_attributes() {
+            for (key, dtype) in &self.t.get_attributes_schema() {
+                let get = |vi: &usize| allt.get(*vi).and_then(|a| a.get(key));
+                let values = match dtype.as_ref() {
+                    "f64" => vtk::Values::F64(
+                        kept.iter()
+                            .map(|vi| get(vi).and_then(|x| x.as_f64()).unwrap_or(f64::NAN))
+                            .collect(),
+                    ),
+                    "i64" => vtk::Values::I64(
+                        kept.iter()
+                            .map(|vi| get(vi).and_then(|x| x.as_i64()).unwrap_or(i64::MAX))
+                            .collect(),
+                    ),
+                    "u64" => vtk::Values::U64(
+                        kept.iter()
+                            .map(|vi| get(vi).and_then(|x| x.as_u64()).unwrap_or(u64::MAX))
+                            .collect(),
+                    ),
+                    "bool" => vtk::Values::U8(
+                        kept.iter()
+                            .map(|vi| get(vi).and_then(|x| x.as_bool()).unwrap_or(false) as u8)
+                            .collect(),
+                    ),
+                    _ => continue,
+                };
+                point_data.push(vtk::DataArray {
+                    name: key.clone(),
+                    ncomp: 1,
+                    values,
+                });
+            }
+        }
+        if !self.uids.is_empty() {
+            point_data.push(vtk::DataArray {
+                name: "user_id".to_string(),
+                ncomp: 1,
+                values: vtk::Values::I64(kept.iter().map(|vi| self.user_id(*vi)).collect()),
+            });
+        }
+        let cell_data = vtk::triangle_data(&pts, &trs);
+        vtk::Grid {
+            pts,
+            trs,
+            point_data,
+            cell_data,
         }
-        Ok(re1.unwrap())
     }
 }
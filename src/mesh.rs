@@ -0,0 +1,124 @@
+//! # mesh
+//!
+//! Writers for simple triangle meshes ([STL](https://en.wikipedia.org/wiki/STL_(file_format))
+//! and [OFF](https://en.wikipedia.org/wiki/OFF_(file_format))), and the extrusion of a TIN
+//! to a closed solid (for 3D printing).
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+/// The points and the triangles (indices in the points) of a mesh.
+pub type TriMesh = (Vec<[f64; 3]>, Vec<[usize; 3]>);
+
+/// Close the TIN (`pts` + `trs`) to a solid: the boundary of its convex hull (`hull`, CCW,
+/// indices in `pts`) is extruded down to the plane `base_z`, and the base is closed with
+/// the triangulation of the hull vertices (with the snap tolerance of the TIN, so that none
+/// is merged).
+/// Returns the new points and triangles (all oriented CCW as seen from outside).
+pub fn extrude(
+    pts: &[[f64; 3]],
+    trs: &[[usize; 3]],
+    hull: &[usize],
+    base_z: f64,
+    snap_tolerance: f64,
+) -> TriMesh {
+    let mut spts: Vec<[f64; 3]> = pts.to_vec();
+    let mut strs: Vec<[usize; 3]> = trs.to_vec();
+    let n = pts.len();
+    for vi in hull {
+        spts.push([pts[*vi][0], pts[*vi][1], base_z]);
+    }
+    //-- walls
+    for i in 0..hull.len() {
+        let j = (i + 1) % hull.len();
+        strs.push([hull[i], n + i, n + j]);
+        strs.push([hull[i], n + j, hull[j]]);
+    }
+    //-- base: the DT of the hull vertices, reversed so that it faces down
+    let mut base = startin::Triangulation::new();
+    base.set_snap_tolerance(snap_tolerance);
+    let mut ids: Vec<usize> = vec![0];
+    for (i, vi) in hull.iter().enumerate() {
+        match base.insert_one_pt(pts[*vi][0], pts[*vi][1], base_z) {
+            Ok(_) => ids.push(n + i),
+            Err(_) => continue,
+        }
+    }
+    for tr in base.all_finite_triangles() {
+        strs.push([ids[tr.v[0]], ids[tr.v[2]], ids[tr.v[1]]]);
+    }
+    (spts, strs)
+}
+
+fn normal(a: &[f64; 3], b: &[f64; 3], c: &[f64; 3]) -> [f64; 3] {
+    let u = [b[0] - a[0], b[1] - a[1], b[2] - a[2]];
+    let v = [c[0] - a[0], c[1] - a[1], c[2] - a[2]];
+    let n = [
+        u[1] * v[2] - u[2] * v[1],
+        u[2] * v[0] - u[0] * v[2],
+        u[0] * v[1] - u[1] * v[0],
+    ];
+    let l = (n[0] * n[0] + n[1] * n[1] + n[2] * n[2]).sqrt();
+    match l > 0.0 {
+        true => [n[0] / l, n[1] / l, n[2] / l],
+        false => [0.0, 0.0, 0.0],
+    }
+}
+
+/// Write an STL file, binary (with float32 coordinates) or ASCII.
+pub fn write_stl(
+    path: &str,
+    pts: &[[f64; 3]],
+    trs: &[[usize; 3]],
+    binary: bool,
+) -> std::io::Result<()> {
+    let mut fo = BufWriter::new(File::create(path)?);
+    if binary {
+        let mut header = [0_u8; 80];
+        let s = b"startinpy TIN";
+        header[..s.len()].copy_from_slice(s);
+        fo.write_all(&header)?;
+        fo.write_all(&(trs.len() as u32).to_le_bytes())?;
+        for tr in trs {
+            let n = normal(&pts[tr[0]], &pts[tr[1]], &pts[tr[2]]);
+            for c in n {
+                fo.write_all(&(c as f32).to_le_bytes())?;
+            }
+            for vi in tr {
+                for c in pts[*vi] {
+                    fo.write_all(&(c as f32).to_le_bytes())?;
+                }
+            }
+            fo.write_all(&0_u16.to_le_bytes())?;
+        }
+    } else {
+        writeln!(fo, "solid startinpy")?;
+        for tr in trs {
+            let n = normal(&pts[tr[0]], &pts[tr[1]], &pts[tr[2]]);
+            writeln!(fo, "facet normal {} {} {}", n[0], n[1], n[2])?;
+            writeln!(fo, "  outer loop")?;
+            for vi in tr {
+                let p = pts[*vi];
+                writeln!(fo, "    vertex {} {} {}", p[0], p[1], p[2])?;
+            }
+            writeln!(fo, "  endloop")?;
+            writeln!(fo, "endfacet")?;
+        }
+        writeln!(fo, "endsolid startinpy")?;
+    }
+    fo.flush()
+}
+
+/// Write an OFF file.
+pub fn write_off(path: &str, pts: &[[f64; 3]], trs: &[[usize; 3]]) -> std::io::Result<()> {
+    let mut fo = BufWriter::new(File::create(path)?);
+    writeln!(fo, "OFF")?;
+    writeln!(fo, "{} {} 0", pts.len(), trs.len())?;
+    for p in pts {
+        writeln!(fo, "{} {} {}", p[0], p[1], p[2])?;
+    }
+    for tr in trs {
+        writeln!(fo, "3 {} {} {}", tr[0], tr[1], tr[2])?;
+    }
+    fo.flush()
+}
@@ -0,0 +1,72 @@
+//! # pointcloud
+//!
+//! Filtering of [LAS/LAZ](https://www.asprs.org/divisions-committees/lidar-division/laser-las-file-format-exchange-activities)
+//! points and access to their dimensions (with the same names as in laspy).
+
+/// The dimensions of a LAS point that can be stored as extra attributes.
+pub const DIMENSIONS: [&str; 19] = [
+    "intensity",
+    "return_number",
+    "number_of_returns",
+    "scan_direction_flag",
+    "edge_of_flight_line",
+    "classification",
+    "synthetic",
+    "key_point",
+    "withheld",
+    "overlap",
+    "scanner_channel",
+    "scan_angle",
+    "user_data",
+    "point_source_id",
+    "gps_time",
+    "red",
+    "green",
+    "blue",
+    "nir",
+];
+
+/// The value of the dimension `name` of the point, NaN if the point `format` doesn't have it.
+pub fn dimension(p: &las::Point, format: &las::point::Format, name: &str) -> f64 {
+    let b = |x: bool| if x { 1.0 } else { 0.0 };
+    match name {
+        "intensity" => p.intensity as f64,
+        "return_number" => p.return_number as f64,
+        "number_of_returns" => p.number_of_returns as f64,
+        "scan_direction_flag" => b(p.scan_direction == las::point::ScanDirection::LeftToRight),
+        "edge_of_flight_line" => b(p.is_edge_of_flight_line),
+        "classification" => u8::from(p.classification) as f64,
+        "synthetic" => b(p.is_synthetic),
+        "key_point" => b(p.is_key_point),
+        "withheld" => b(p.is_withheld),
+        "overlap" => b(p.is_overlap),
+        "scanner_channel" => p.scanner_channel as f64,
+        "scan_angle" => p.scan_angle as f64,
+        "user_data" => p.user_data as f64,
+        "point_source_id" => p.point_source_id as f64,
+        "gps_time" => p.gps_time.unwrap_or(f64::NAN),
+        "red" => p.color.map_or(f64::NAN, |c| c.red as f64),
+        "green" => p.color.map_or(f64::NAN, |c| c.green as f64),
+        "blue" => p.color.map_or(f64::NAN, |c| c.blue as f64),
+        //-- the las crate reads a NIR of 0 as None
+        "nir" if format.has_nir => p.nir.unwrap_or(0) as f64,
+        _ => f64::NAN,
+    }
+}
+
+/// Whether the point is kept: its class is in `classes` and its return number
+/// in `returns` (-1 being the last return), None meaning all.
+pub fn keep(p: &las::Point, classes: &Option<Vec<u8>>, returns: &Option<Vec<i64>>) -> bool {
+    if let Some(cs) = classes {
+        if !cs.contains(&u8::from(p.classification)) {
+            return false;
+        }
+    }
+    if let Some(rs) = returns {
+        let last = p.return_number == p.number_of_returns;
+        return rs
+            .iter()
+            .any(|r| *r == p.return_number as i64 || (*r == -1 && last));
+    }
+    true
+}
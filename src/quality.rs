@@ -0,0 +1,123 @@
+//! # quality
+//!
+//! The quality metrics of the triangles (angles, aspect ratio, areas) and the
+//! statistics of the whole DT.
+
+use startin::geom;
+
+/// The quality metrics of one triangle, the angles are in degrees.
+pub struct TriangleQuality {
+    pub min_angle: f64,
+    pub max_angle: f64,
+    /// The longest edge over the shortest altitude, normalised so that it is 1.0 for an
+    /// equilateral triangle.
+    pub aspect_ratio: f64,
+    /// The circumradius over the shortest edge (1/sqrt(3) for an equilateral triangle).
+    pub radius_edge_ratio: f64,
+    pub area_2d: f64,
+    pub area_3d: f64,
+}
+
+/// The statistics of a DT.
+pub struct Statistics {
+    pub number_vertices: usize,
+    pub number_triangles: usize,
+    pub number_removed_vertices: usize,
+    pub area_2d: f64,
+    pub area_3d: f64,
+    pub z_min: f64,
+    pub z_max: f64,
+    pub mean_edge_length: f64,
+    /// The number of vertices per unit of 2D area.
+    pub point_density: f64,
+    /// The number of vertices for each degree (the index), only the finite edges are counted.
+    pub degree_histogram: Vec<usize>,
+}
+
+pub fn triangle(a: &[f64], b: &[f64], c: &[f64]) -> TriangleQuality {
+    let pts = [a, b, c];
+    let mut angles = [0.0; 3];
+    let mut lengths = [0.0; 3];
+    for i in 0..3 {
+        let (o, p, q) = (pts[i], pts[(i + 1) % 3], pts[(i + 2) % 3]);
+        let (ux, uy) = (p[0] - o[0], p[1] - o[1]);
+        let (vx, vy) = (q[0] - o[0], q[1] - o[1]);
+        angles[i] = (ux * vy - uy * vx)
+            .abs()
+            .atan2(ux * vx + uy * vy)
+            .to_degrees();
+        lengths[i] = geom::distance2d(p, q);
+    }
+    let area_2d = geom::area2d_triangle(a, b, c);
+    let longest = lengths.iter().cloned().fold(0.0, f64::max);
+    let shortest = lengths.iter().cloned().fold(f64::INFINITY, f64::min);
+    let (aspect_ratio, radius_edge_ratio) = if area_2d > 0.0 {
+        let circumradius = lengths[0] * lengths[1] * lengths[2] / (4.0 * area_2d);
+        (
+            3.0_f64.sqrt() * longest * longest / (4.0 * area_2d),
+            circumradius / shortest,
+        )
+    } else {
+        (f64::INFINITY, f64::INFINITY)
+    };
+    TriangleQuality {
+        min_angle: angles.iter().cloned().fold(f64::INFINITY, f64::min),
+        max_angle: angles.iter().cloned().fold(0.0, f64::max),
+        aspect_ratio,
+        radius_edge_ratio,
+        area_2d,
+        area_3d: geom::area3d_triangle(a, b, c),
+    }
+}
+
+pub fn statistics(t: &startin::Triangulation) -> Statistics {
+    let mut s = Statistics {
+        number_vertices: t.number_of_vertices(),
+        number_triangles: t.number_of_triangles(),
+        number_removed_vertices: t.number_of_removed_vertices(),
+        area_2d: 0.0,
+        area_3d: 0.0,
+        z_min: f64::NAN,
+        z_max: f64::NAN,
+        mean_edge_length: f64::NAN,
+        point_density: f64::NAN,
+        degree_histogram: Vec::new(),
+    };
+    for tr in t.all_finite_triangles() {
+        let a = t.get_point(tr.v[0]).unwrap();
+        let b = t.get_point(tr.v[1]).unwrap();
+        let c = t.get_point(tr.v[2]).unwrap();
+        s.area_2d += geom::area2d_triangle(&a, &b, &c);
+        s.area_3d += geom::area3d_triangle(&a, &b, &c);
+    }
+    for vi in 1..t.all_vertices().len() {
+        if t.is_vertex_removed(vi) != Ok(false) {
+            continue;
+        }
+        let z = t.get_point(vi).unwrap()[2];
+        s.z_min = if s.z_min.is_nan() { z } else { s.z_min.min(z) };
+        s.z_max = if s.z_max.is_nan() { z } else { s.z_max.max(z) };
+        let degree = t
+            .adjacent_vertices_to_vertex(vi)
+            .unwrap()
+            .iter()
+            .filter(|w| **w != 0)
+            .count();
+        if s.degree_histogram.len() <= degree {
+            s.degree_histogram.resize(degree + 1, 0);
+        }
+        s.degree_histogram[degree] += 1;
+    }
+    let edges = t.all_finite_edges();
+    if !edges.is_empty() {
+        let total: f64 = edges
+            .chunks(2)
+            .map(|e| geom::distance2d(&t.get_point(e[0]).unwrap(), &t.get_point(e[1]).unwrap()))
+            .sum();
+        s.mean_edge_length = total / (edges.len() / 2) as f64;
+    }
+    if s.area_2d > 0.0 {
+        s.point_density = s.number_vertices as f64 / s.area_2d;
+    }
+    s
+}
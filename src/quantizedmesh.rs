@@ -0,0 +1,406 @@
+//! # quantizedmesh
+//!
+//! Writer for Cesium's [quantized-mesh-1.0](https://github.com/CesiumGS/quantized-mesh)
+//! terrain tiles.
+//! The coordinates of the TIN are expected to be longitude/latitude in degrees (WGS84)
+//! and the elevations in metres, as for the geographic tiling scheme of Cesium.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::Write;
+
+/// The deepest zoom level (the tiles are then about 2 cm wide).
+pub const MAX_ZOOM: u32 = 30;
+
+const QMAX: f64 = 32767.0;
+const WGS84_A: f64 = 6378137.0;
+const WGS84_B: f64 = 6356752.314_245_179;
+const WGS84_E2: f64 = 0.006_694_379_990_141_316;
+
+/// A tile with its vertices (lon, lat, h) and its triangles (CCW).
+pub struct Tile {
+    pub pts: Vec<[f64; 3]>,
+    pub trs: Vec<[usize; 3]>,
+}
+
+fn ecef(p: &[f64; 3]) -> [f64; 3] {
+    let lon = p[0].to_radians();
+    let lat = p[1].to_radians();
+    let n = WGS84_A / (1.0 - WGS84_E2 * lat.sin() * lat.sin()).sqrt();
+    [
+        (n + p[2]) * lat.cos() * lon.cos(),
+        (n + p[2]) * lat.cos() * lon.sin(),
+        (n * (1.0 - WGS84_E2) + p[2]) * lat.sin(),
+    ]
+}
+
+fn norm(v: &[f64; 3]) -> f64 {
+    (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt()
+}
+
+/// Clip one polygon against the half-plane `coord[axis] >= value` (if `keep_above`)
+/// or `coord[axis] <= value`; z is interpolated linearly along the edges.
+fn clip_polygon(poly: &[[f64; 3]], axis: usize, value: f64, keep_above: bool) -> Vec<[f64; 3]> {
+    let inside = |p: &[f64; 3]| match keep_above {
+        true => p[axis] >= value,
+        false => p[axis] <= value,
+    };
+    let mut re: Vec<[f64; 3]> = Vec::new();
+    for i in 0..poly.len() {
+        let a = &poly[i];
+        let b = &poly[(i + 1) % poly.len()];
+        if inside(a) {
+            re.push(*a);
+        }
+        if inside(a) != inside(b) {
+            //-- the endpoints are ordered so that the 2 triangles incident to an edge
+            //-- compute exactly the same intersection
+            let (a, b) = if (a[0], a[1]) <= (b[0], b[1]) {
+                (a, b)
+            } else {
+                (b, a)
+            };
+            let t = (value - a[axis]) / (b[axis] - a[axis]);
+            let mut p = [
+                a[0] + t * (b[0] - a[0]),
+                a[1] + t * (b[1] - a[1]),
+                a[2] + t * (b[2] - a[2]),
+            ];
+            p[axis] = value;
+            re.push(p);
+        }
+    }
+    re
+}
+
+/// The bounds [west, south, east, north] of the tile (x, y) of the zoom level.
+pub fn tile_bounds(zoom: u32, x: u32, y: u32) -> [f64; 4] {
+    let size = 180.0 / (1u64 << zoom) as f64;
+    [
+        -180.0 + x as f64 * size,
+        -90.0 + y as f64 * size,
+        -180.0 + (x + 1) as f64 * size,
+        -90.0 + (y + 1) as f64 * size,
+    ]
+}
+
+/// Calls `f` with the triangles intersecting each tile (x, y) of the zoom level, the
+/// tiles are processed row by row (from the south) so that only the triangles of one row
+/// are held in memory.
+/// In a row, a triangle is clipped to the row and is put only in the tiles spanned by
+/// the clipped polygon, which are all the tiles it intersects.
+pub fn tiling<F, E>(pts: &[[f64; 3]], trs: &[[usize; 3]], zoom: u32, mut f: F) -> Result<(), E>
+where
+    F: FnMut([u32; 2], &[[usize; 3]]) -> Result<(), E>,
+{
+    let n = 1u64 << zoom;
+    let size = 180.0 / n as f64;
+    let col = |lon: f64| {
+        ((lon + 180.0) / size)
+            .floor()
+            .clamp(0.0, (2 * n - 1) as f64) as u32
+    };
+    let row = |lat: f64| ((lat + 90.0) / size).floor().clamp(0.0, (n - 1) as f64) as u32;
+    //-- the first and last rows of each triangle
+    let rows: Vec<(u32, u32)> = trs
+        .iter()
+        .map(|tr| {
+            let lats = tr.iter().map(|v| pts[*v][1]);
+            let lo = lats.clone().fold(f64::MAX, f64::min);
+            let hi = lats.fold(f64::MIN, f64::max);
+            (row(lo), row(hi))
+        })
+        .collect();
+    let mut order: Vec<usize> = (0..trs.len()).collect();
+    order.sort_by_key(|i| rows[*i].0);
+    let mut active: Vec<usize> = Vec::new();
+    let mut next = 0;
+    let mut y = 0;
+    loop {
+        if active.is_empty() {
+            match order.get(next) {
+                Some(i) => y = rows[*i].0,
+                None => break,
+            }
+        }
+        while next < order.len() && rows[order[next]].0 == y {
+            active.push(order[next]);
+            next += 1;
+        }
+        //-- the triangles are kept in their order, as the vertices of a tile are numbered
+        //-- by their first use
+        active.sort_unstable();
+        let b = tile_bounds(zoom, 0, y);
+        let mut buckets: BTreeMap<u32, Vec<[usize; 3]>> = BTreeMap::new();
+        for i in &active {
+            let tr = trs[*i];
+            let mut poly = vec![pts[tr[0]], pts[tr[1]], pts[tr[2]]];
+            poly = clip_polygon(&poly, 1, b[1], true);
+            poly = clip_polygon(&poly, 1, b[3], false);
+            if poly.is_empty() {
+                continue;
+            }
+            let lo = poly.iter().map(|p| p[0]).fold(f64::MAX, f64::min);
+            let hi = poly.iter().map(|p| p[0]).fold(f64::MIN, f64::max);
+            for x in col(lo)..=col(hi) {
+                buckets.entry(x).or_default().push(tr);
+            }
+        }
+        for (x, trs) in buckets {
+            f([x, y], &trs)?;
+        }
+        active.retain(|i| rows[*i].1 > y);
+        y += 1;
+    }
+    Ok(())
+}
+
+/// Cut the triangles to the bounds [west, south, east, north], the polygons
+/// obtained are triangulated as fans.
+pub fn clip(pts: &[[f64; 3]], trs: &[[usize; 3]], bounds: &[f64; 4]) -> Tile {
+    let mut tile = Tile {
+        pts: Vec::new(),
+        trs: Vec::new(),
+    };
+    //-- vertices are merged on their coordinates: the intersections computed by 2
+    //-- adjacent triangles are bitwise the same, and the vertices of the TIN are distinct
+    let mut ids: HashMap<(u64, u64), usize> = HashMap::new();
+    for tr in trs {
+        let poly = [pts[tr[0]], pts[tr[1]], pts[tr[2]]];
+        if poly.iter().all(|p| p[0] < bounds[0])
+            || poly.iter().all(|p| p[0] > bounds[2])
+            || poly.iter().all(|p| p[1] < bounds[1])
+            || poly.iter().all(|p| p[1] > bounds[3])
+        {
+            continue;
+        }
+        let mut poly = poly.to_vec();
+        poly = clip_polygon(&poly, 0, bounds[0], true);
+        poly = clip_polygon(&poly, 0, bounds[2], false);
+        poly = clip_polygon(&poly, 1, bounds[1], true);
+        poly = clip_polygon(&poly, 1, bounds[3], false);
+        if poly.len() < 3 {
+            continue;
+        }
+        let vs: Vec<usize> = poly
+            .iter()
+            .map(|p| {
+                let key = (p[0].to_bits(), p[1].to_bits());
+                *ids.entry(key).or_insert_with(|| {
+                    tile.pts.push(*p);
+                    tile.pts.len() - 1
+                })
+            })
+            .collect();
+        for i in 1..(vs.len() - 1) {
+            let t = [vs[0], vs[i], vs[i + 1]];
+            if t[0] != t[1] && t[1] != t[2] && t[0] != t[2] {
+                tile.trs.push(t);
+            }
+        }
+    }
+    tile
+}
+
+fn quantize_uv(p: &[f64; 3], bounds: &[f64; 4]) -> (u16, u16) {
+    let u = ((p[0] - bounds[0]) / (bounds[2] - bounds[0]) * QMAX).round();
+    let v = ((p[1] - bounds[1]) / (bounds[3] - bounds[1]) * QMAX).round();
+    (u.clamp(0.0, QMAX) as u16, v.clamp(0.0, QMAX) as u16)
+}
+
+fn zigzag(values: &[u16]) -> Vec<u16> {
+    let mut prev: i32 = 0;
+    values
+        .iter()
+        .map(|v| {
+            let delta = *v as i32 - prev;
+            prev = *v as i32;
+            ((delta << 1) ^ (delta >> 31)) as u16
+        })
+        .collect()
+}
+
+/// Oct-encoding of a unit vector on 2 bytes.
+fn oct_encode(n: &[f64; 3]) -> [u8; 2] {
+    let l1 = n[0].abs() + n[1].abs() + n[2].abs();
+    let mut x = n[0] / l1;
+    let mut y = n[1] / l1;
+    if n[2] < 0.0 {
+        let sx = if x >= 0.0 { 1.0 } else { -1.0 };
+        let sy = if y >= 0.0 { 1.0 } else { -1.0 };
+        let ox = x;
+        x = (1.0 - y.abs()) * sx;
+        y = (1.0 - ox.abs()) * sy;
+    }
+    let snorm = |v: f64| ((v.clamp(-1.0, 1.0) * 0.5 + 0.5) * 255.0).round() as u8;
+    [snorm(x), snorm(y)]
+}
+
+/// Horizon occlusion point (in the ellipsoid-scaled frame), as computed
+/// by Cesium's `EllipsoidalOccluder.computeHorizonCullingPoint()`.
+fn horizon_occlusion_point(centre: &[f64; 3], pts: &[[f64; 3]]) -> [f64; 3] {
+    let scaled = |p: &[f64; 3]| [p[0] / WGS84_A, p[1] / WGS84_A, p[2] / WGS84_B];
+    let d = scaled(centre);
+    let nd = norm(&d);
+    let d = [d[0] / nd, d[1] / nd, d[2] / nd];
+    let mut magnitude: f64 = 0.0;
+    for p in pts {
+        let s = scaled(p);
+        let m = norm(&s);
+        let dir = [s[0] / m, s[1] / m, s[2] / m];
+        let m = m.max(1.0);
+        let cos_alpha = dir[0] * d[0] + dir[1] * d[1] + dir[2] * d[2];
+        let cross = [
+            dir[1] * d[2] - dir[2] * d[1],
+            dir[2] * d[0] - dir[0] * d[2],
+            dir[0] * d[1] - dir[1] * d[0],
+        ];
+        let sin_alpha = norm(&cross);
+        let cos_beta = 1.0 / m;
+        let sin_beta = (m * m - 1.0).sqrt() * cos_beta;
+        magnitude = magnitude.max(1.0 / (cos_alpha * cos_beta - sin_alpha * sin_beta));
+    }
+    [d[0] * magnitude, d[1] * magnitude, d[2] * magnitude]
+}
+
+/// Write the tile (already clipped to `bounds`) to `path`.
+pub fn write(path: &str, tile: &Tile, bounds: &[f64; 4], normals: bool) -> std::io::Result<()> {
+    //-- the vertices must be ordered by first use in the index buffer
+    //-- for the high-water mark encoding
+    let mut order: Vec<usize> = Vec::with_capacity(tile.pts.len());
+    let mut newids: Vec<usize> = vec![usize::MAX; tile.pts.len()];
+    for tr in &tile.trs {
+        for v in tr {
+            if newids[*v] == usize::MAX {
+                newids[*v] = order.len();
+                order.push(*v);
+            }
+        }
+    }
+    let pts: Vec<[f64; 3]> = order.iter().map(|i| tile.pts[*i]).collect();
+    let trs: Vec<[usize; 3]> = tile
+        .trs
+        .iter()
+        .map(|tr| [newids[tr[0]], newids[tr[1]], newids[tr[2]]])
+        .collect();
+    let minh = pts.iter().map(|p| p[2]).fold(f64::MAX, f64::min);
+    let maxh = pts.iter().map(|p| p[2]).fold(f64::MIN, f64::max);
+    let hrange = if maxh > minh { maxh - minh } else { 1.0 };
+    //-- header
+    let xyz: Vec<[f64; 3]> = pts.iter().map(ecef).collect();
+    let mut lo = [f64::MAX; 3];
+    let mut hi = [f64::MIN; 3];
+    for p in &xyz {
+        for i in 0..3 {
+            lo[i] = lo[i].min(p[i]);
+            hi[i] = hi[i].max(p[i]);
+        }
+    }
+    let centre = [
+        (lo[0] + hi[0]) / 2.0,
+        (lo[1] + hi[1]) / 2.0,
+        (lo[2] + hi[2]) / 2.0,
+    ];
+    let radius = xyz
+        .iter()
+        .map(|p| norm(&[p[0] - centre[0], p[1] - centre[1], p[2] - centre[2]]))
+        .fold(0.0, f64::max);
+    let hop = horizon_occlusion_point(&centre, &xyz);
+    let mut b: Vec<u8> = Vec::new();
+    for c in centre {
+        b.extend_from_slice(&c.to_le_bytes());
+    }
+    b.extend_from_slice(&(minh as f32).to_le_bytes());
+    b.extend_from_slice(&(maxh as f32).to_le_bytes());
+    for c in centre {
+        b.extend_from_slice(&c.to_le_bytes());
+    }
+    b.extend_from_slice(&radius.to_le_bytes());
+    for c in hop {
+        b.extend_from_slice(&c.to_le_bytes());
+    }
+    //-- vertex data
+    let mut us: Vec<u16> = Vec::with_capacity(pts.len());
+    let mut vs: Vec<u16> = Vec::with_capacity(pts.len());
+    let mut hs: Vec<u16> = Vec::with_capacity(pts.len());
+    for p in &pts {
+        let (u, v) = quantize_uv(p, bounds);
+        us.push(u);
+        vs.push(v);
+        hs.push(((p[2] - minh) / hrange * QMAX).round() as u16);
+    }
+    b.extend_from_slice(&(pts.len() as u32).to_le_bytes());
+    for a in [&us, &vs, &hs] {
+        for each in zigzag(a) {
+            b.extend_from_slice(&each.to_le_bytes());
+        }
+    }
+    //-- index data (high-water mark encoded)
+    let use32 = pts.len() > 65536;
+    let push_index = |b: &mut Vec<u8>, i: usize| match use32 {
+        true => b.extend_from_slice(&(i as u32).to_le_bytes()),
+        false => b.extend_from_slice(&(i as u16).to_le_bytes()),
+    };
+    if use32 {
+        b.resize(b.len().div_ceil(4) * 4, 0);
+    }
+    b.extend_from_slice(&(trs.len() as u32).to_le_bytes());
+    let mut highest: usize = 0;
+    for tr in &trs {
+        for i in tr {
+            let code = highest - i;
+            push_index(&mut b, code);
+            if code == 0 {
+                highest += 1;
+            }
+        }
+    }
+    //-- edge indices: west, south, east, north
+    let edges: [Box<dyn Fn(usize) -> bool>; 4] = [
+        Box::new(|i| us[i] == 0),
+        Box::new(|i| vs[i] == 0),
+        Box::new(|i| us[i] == QMAX as u16),
+        Box::new(|i| vs[i] == QMAX as u16),
+    ];
+    for onedge in edges.iter() {
+        let l: Vec<usize> = (0..pts.len()).filter(|i| onedge(*i)).collect();
+        b.extend_from_slice(&(l.len() as u32).to_le_bytes());
+        for i in l {
+            push_index(&mut b, i);
+        }
+    }
+    //-- extension 1: oct-encoded vertex normals (in the ECEF frame)
+    if normals {
+        let mut ns: Vec<[f64; 3]> = vec![[0.0; 3]; pts.len()];
+        for tr in &trs {
+            let (a, c, d) = (&xyz[tr[0]], &xyz[tr[1]], &xyz[tr[2]]);
+            let u = [c[0] - a[0], c[1] - a[1], c[2] - a[2]];
+            let v = [d[0] - a[0], d[1] - a[1], d[2] - a[2]];
+            let n = [
+                u[1] * v[2] - u[2] * v[1],
+                u[2] * v[0] - u[0] * v[2],
+                u[0] * v[1] - u[1] * v[0],
+            ];
+            for i in tr {
+                for j in 0..3 {
+                    ns[*i][j] += n[j];
+                }
+            }
+        }
+        b.push(1);
+        b.extend_from_slice(&(2 * pts.len() as u32).to_le_bytes());
+        for (i, n) in ns.iter().enumerate() {
+            let l = norm(n);
+            match l > 0.0 {
+                true => b.extend_from_slice(&oct_encode(&[n[0] / l, n[1] / l, n[2] / l])),
+                false => {
+                    let u = norm(&xyz[i]);
+                    b.extend_from_slice(&oct_encode(&[xyz[i][0] / u, xyz[i][1] / u, xyz[i][2] / u]))
+                }
+            }
+        }
+    }
+    let mut fo = File::create(path)?;
+    fo.write_all(&b)?;
+    Ok(())
+}
@@ -0,0 +1,306 @@
+//! # refine
+//!
+//! Delaunay refinement (Ruppert, 1995), where the edges of the convex hull are the
+//! segments of the domain. The triangles with a too small angle (or a too large area)
+//! are removed by inserting their circumcentre; if the circumcentre encroaches upon a
+//! segment (it is inside its diametral circle) then the segment is split at its midpoint
+//! instead.
+//!
+//! The midpoint of a segment is rarely exactly on it (in floating-point), and startin
+//! has no constraints: a split point slightly inside the convex hull would create a flat
+//! triangle on the boundary. The split points are thus moved outwards by a tiny amount,
+//! so that the boundary stays strictly convex (see `split_point()`); the segments are
+//! kept in a list since they are then not exactly the edges of the original hull.
+//!
+//! The refinement stops after MAX_ROUNDS rounds, or when a segment that must be split
+//! is too short for the floating-point precision (the triangles near it are left as is).
+//!
+//! The triangles whose smallest angle is at a sharp corner of the convex hull (< 60°)
+//! cannot be improved and are left as is.
+
+use std::collections::{HashMap, HashSet};
+
+use startin::geom;
+
+use crate::interpolation;
+use crate::search;
+
+/// The maximum number of rounds (each one splits the encroached segments and then
+/// treats all the bad triangles).
+const MAX_ROUNDS: usize = 1000;
+/// The offset of the split points of an original hull edge, relative to its length.
+const BULGE: f64 = 1e-8;
+/// The number of floats (in x and in y) searched around a split point.
+const SEARCH: i64 = 8;
+
+/// The segments (the subdivided edges of the original convex hull).
+struct Segments {
+    /// The endpoints of each segment, and the original hull edge it is part of.
+    edges: Vec<(usize, usize, usize)>,
+    /// The length of each original hull edge.
+    lengths: Vec<f64>,
+    /// The vertex before each vertex on the boundary (counter-clockwise).
+    prev: HashMap<usize, usize>,
+    /// The vertex after each vertex on the boundary (counter-clockwise).
+    next: HashMap<usize, usize>,
+}
+
+impl Segments {
+    fn new(t: &startin::Triangulation, hull: &[usize]) -> Segments {
+        let n = hull.len();
+        let mut s = Segments {
+            edges: Vec::with_capacity(n),
+            lengths: Vec::with_capacity(n),
+            prev: HashMap::new(),
+            next: HashMap::new(),
+        };
+        for i in 0..n {
+            let (a, b) = (hull[i], hull[(i + 1) % n]);
+            s.edges.push((a, b, i));
+            s.lengths.push(geom::distance2d(
+                &t.get_point(a).unwrap(),
+                &t.get_point(b).unwrap(),
+            ));
+            s.prev.insert(b, a);
+            s.next.insert(a, b);
+        }
+        s
+    }
+}
+
+/// Refines the DT, the z-value at a new vertex is given by `z` (if None, the linear
+/// interpolation in the current DT is used).
+/// Returns the indices of the new vertices.
+pub fn refine<F>(
+    t: &mut startin::Triangulation,
+    min_angle: f64,
+    max_area: Option<f64>,
+    mut z: F,
+) -> Vec<usize>
+where
+    F: FnMut([f64; 2]) -> Option<f64>,
+{
+    let mut steiner: Vec<usize> = Vec::new();
+    if t.number_of_triangles() == 0 {
+        return steiner;
+    }
+    let hull = t.convex_hull();
+    let sharp = sharp_corners(t, &hull);
+    let mut segments = Segments::new(t, &hull);
+    for _ in 0..MAX_ROUNDS {
+        let mut progress = false;
+        //-- the segments encroached upon by a vertex
+        loop {
+            let encroached: Vec<usize> = (0..segments.edges.len())
+                .filter(|i| segment_encroached(t, &segments, *i))
+                .collect();
+            let n = steiner.len();
+            for i in encroached {
+                split(t, &mut segments, i, &mut z, &mut steiner);
+            }
+            if steiner.len() == n {
+                break;
+            }
+            progress = true;
+        }
+        //-- the bad triangles, the worst first
+        let mut bad: Vec<(f64, [usize; 3])> = t
+            .all_finite_triangles()
+            .into_iter()
+            .filter_map(|tr| {
+                let (angle, at) = smallest_angle(t, tr.v);
+                let too_large = max_area.is_some_and(|m| area(t, tr.v) > m);
+                if too_large || (angle < min_angle && !sharp.contains(&at)) {
+                    Some((angle, tr.v))
+                } else {
+                    None
+                }
+            })
+            .collect();
+        bad.sort_by(|a, b| a.0.total_cmp(&b.0));
+        for (_, v) in bad {
+            if !t.is_triangle(&startin::Triangle { v }) {
+                continue;
+            }
+            let pts: Vec<Vec<f64>> = v.iter().map(|vi| t.get_point(*vi).unwrap()).collect();
+            let c = geom::circle_centre(&pts[0], &pts[1], &pts[2]);
+            let c = [c[0], c[1]];
+            if !(c[0].is_finite() && c[1].is_finite()) {
+                continue;
+            }
+            let encroached: Vec<usize> = (0..segments.edges.len())
+                .filter(|i| {
+                    let (a, b, _) = segments.edges[*i];
+                    in_diametral_circle(t, a, b, c)
+                })
+                .collect();
+            let n = steiner.len();
+            if !encroached.is_empty() {
+                for i in encroached {
+                    split(t, &mut segments, i, &mut z, &mut steiner);
+                }
+            } else if t.locate(c[0], c[1]).is_ok() {
+                insert(t, c, &mut z, &mut steiner);
+            }
+            if steiner.len() > n {
+                progress = true;
+            }
+        }
+        if !progress {
+            break;
+        }
+    }
+    steiner
+}
+
+fn area(t: &startin::Triangulation, v: [usize; 3]) -> f64 {
+    geom::area2d_triangle(
+        &t.get_point(v[0]).unwrap(),
+        &t.get_point(v[1]).unwrap(),
+        &t.get_point(v[2]).unwrap(),
+    )
+}
+
+/// The angle (in degrees) at o between the directions to p and q.
+fn angle(o: &[f64], p: &[f64], q: &[f64]) -> f64 {
+    let (ux, uy) = (p[0] - o[0], p[1] - o[1]);
+    let (vx, vy) = (q[0] - o[0], q[1] - o[1]);
+    (ux * vy - uy * vx)
+        .abs()
+        .atan2(ux * vx + uy * vy)
+        .to_degrees()
+}
+
+/// The smallest angle of the triangle, and the vertex where it is.
+fn smallest_angle(t: &startin::Triangulation, v: [usize; 3]) -> (f64, usize) {
+    let pts: Vec<Vec<f64>> = v.iter().map(|vi| t.get_point(*vi).unwrap()).collect();
+    (0..3)
+        .map(|i| (angle(&pts[i], &pts[(i + 1) % 3], &pts[(i + 2) % 3]), v[i]))
+        .min_by(|a, b| a.0.total_cmp(&b.0))
+        .unwrap()
+}
+
+/// The vertices of the convex hull with an angle < 60°.
+fn sharp_corners(t: &startin::Triangulation, hull: &[usize]) -> HashSet<usize> {
+    let n = hull.len();
+    (0..n)
+        .filter(|i| {
+            let o = t.get_point(hull[*i]).unwrap();
+            let p = t.get_point(hull[(i + n - 1) % n]).unwrap();
+            let q = t.get_point(hull[(i + 1) % n]).unwrap();
+            angle(&o, &p, &q) < 60.0
+        })
+        .map(|i| hull[i])
+        .collect()
+}
+
+/// Whether p is strictly inside the diametral circle of the segment ab.
+fn in_diametral_circle(t: &startin::Triangulation, a: usize, b: usize, p: [f64; 2]) -> bool {
+    let a = t.get_point(a).unwrap();
+    let b = t.get_point(b).unwrap();
+    (a[0] - p[0]) * (b[0] - p[0]) + (a[1] - p[1]) * (b[1] - p[1]) < 0.0
+}
+
+/// Whether a vertex is inside the diametral circle of the segment i.
+fn segment_encroached(t: &mut startin::Triangulation, segments: &Segments, i: usize) -> bool {
+    let (a, b, _) = segments.edges[i];
+    let pa = t.get_point(a).unwrap();
+    let pb = t.get_point(b).unwrap();
+    let centre = [(pa[0] + pb[0]) / 2.0, (pa[1] + pb[1]) / 2.0];
+    let radius = geom::distance2d(&pa, &pb) / 2.0;
+    search::in_radius(t, centre, radius)
+        .into_iter()
+        .filter(|w| *w != a && *w != b)
+        .any(|w| {
+            let q = t.get_point(w).unwrap();
+            in_diametral_circle(t, a, b, [q[0], q[1]])
+        })
+}
+
+/// Splits the segment i near its midpoint, the z-value is the average of the endpoints
+/// if `z` gives none.
+fn split<F>(
+    t: &mut startin::Triangulation,
+    segments: &mut Segments,
+    i: usize,
+    z: &mut F,
+    re: &mut Vec<usize>,
+) where
+    F: FnMut([f64; 2]) -> Option<f64>,
+{
+    let (a, b, e) = segments.edges[i];
+    let pa = t.get_point(a).unwrap();
+    let pb = t.get_point(b).unwrap();
+    let po = t.get_point(segments.prev[&a]).unwrap();
+    let pp = t.get_point(segments.next[&b]).unwrap();
+    let Some(m) = split_point(&po, &pa, &pb, &pp, segments.lengths[e]) else {
+        return;
+    };
+    let zm = z(m).unwrap_or((pa[2] + pb[2]) / 2.0);
+    if let Some(vi) = insert_z(t, m, zm) {
+        segments.edges[i] = (a, vi, e);
+        segments.edges.push((vi, b, e));
+        segments.next.insert(a, vi);
+        segments.prev.insert(vi, a);
+        segments.next.insert(vi, b);
+        segments.prev.insert(b, vi);
+        re.push(vi);
+    }
+}
+
+/// The point where the segment ab is split, o is the vertex before a on the boundary
+/// and p the one after b, and l is the length of the original hull edge.
+/// The midpoint is moved outwards a tiny bit, onto the parabola through the endpoints of
+/// the original edge whose apex is BULGE * l away from it, so that the boundary stays
+/// strictly convex (a point slightly inside, or a neighbour pushed slightly inside,
+/// would create a flat triangle).
+/// The float nearest to it for which a, the point, and b are convex is returned; None
+/// if there is none (the segment is then too short to be split).
+fn split_point(o: &[f64], a: &[f64], b: &[f64], p: &[f64], l: f64) -> Option<[f64; 2]> {
+    let (dx, dy) = (b[0] - a[0], b[1] - a[1]);
+    let h = BULGE * (dx * dx + dy * dy).sqrt() / l;
+    let m = [(a[0] + b[0]) / 2.0, (a[1] + b[1]) / 2.0];
+    let c = [m[0] + h * dy, m[1] - h * dx];
+    let ulp = |x: f64| f64::from_bits(x.abs().to_bits() + 1) - x.abs();
+    let mut steps: Vec<(i64, i64)> = (-SEARCH..=SEARCH)
+        .flat_map(|i| (-SEARCH..=SEARCH).map(move |j| (i, j)))
+        .collect();
+    steps.sort_by_key(|(i, j)| i.abs() + j.abs());
+    [c, m].iter().find_map(|c| {
+        steps
+            .iter()
+            .map(|(i, j)| [c[0] + *i as f64 * ulp(c[0]), c[1] + *j as f64 * ulp(c[1])])
+            .find(|q| {
+                geom::orient2d(a, b, q, true) <= 0
+                    && geom::orient2d(o, a, q, true) > 0
+                    && geom::orient2d(q, b, p, true) > 0
+            })
+    })
+}
+
+fn insert<F>(t: &mut startin::Triangulation, p: [f64; 2], z: &mut F, re: &mut Vec<usize>)
+where
+    F: FnMut([f64; 2]) -> Option<f64>,
+{
+    let zp = match z(p) {
+        Some(zp) => zp,
+        None => interpolation::estimate(t, &interpolation::Method::TIN, p, None, |t, vi| {
+            t.get_point(vi).unwrap()[2]
+        })
+        .unwrap_or(f64::NAN),
+    };
+    if let Some(vi) = insert_z(t, p, zp) {
+        re.push(vi);
+    }
+}
+
+/// Inserts p, unless it is a duplicate of a vertex (which would then get the z of p).
+fn insert_z(t: &mut startin::Triangulation, p: [f64; 2], z: f64) -> Option<usize> {
+    let snaptol = t.get_snap_tolerance();
+    if let Some(vi) = search::nearest_k(t, p, 1).first() {
+        if geom::distance2d_squared(&p, &t.get_point(*vi).unwrap()) <= snaptol * snaptol {
+            return None;
+        }
+    }
+    t.insert_one_pt(p[0], p[1], z).ok()
+}
@@ -0,0 +1,197 @@
+//! # search
+//!
+//! Spatial queries over the vertices of the DT, done by walking the triangulation.
+//!
+//! They rely on a property of the DT: if a vertex *v* is not the closest to a location *q*,
+//! then one of its adjacent vertices is closer to *q* than *v* (the segment from *v* to *q*
+//! leaves the Voronoi cell of *v* through the dual of one of its Delaunay edges).
+//! A greedy walk thus always ends at the closest vertex (also if *q* is outside the convex hull),
+//! and a best-first expansion from it visits the vertices by increasing distance to *q*.
+
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashSet};
+
+/// A vertex in the priority queue of the expansion (smallest distance first).
+struct Candidate {
+    d2: f64,
+    vi: usize,
+}
+
+impl PartialEq for Candidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .d2
+            .total_cmp(&self.d2)
+            .then_with(|| other.vi.cmp(&self.vi))
+    }
+}
+
+fn sqdist(t: &startin::Triangulation, vi: usize, q: [f64; 2]) -> f64 {
+    let p = t.get_point(vi).unwrap();
+    (p[0] - q[0]) * (p[0] - q[0]) + (p[1] - q[1]) * (p[1] - q[1])
+}
+
+fn finite_neighbours(t: &startin::Triangulation, vi: usize) -> impl Iterator<Item = usize> {
+    t.adjacent_vertices_to_vertex(vi)
+        .unwrap()
+        .into_iter()
+        .filter(|w| *w != 0)
+}
+
+/// The closest vertex to q, None if the DT has no triangles yet.
+fn closest_vertex(t: &mut startin::Triangulation, q: [f64; 2]) -> Option<usize> {
+    let hull = t.convex_hull();
+    if hull.is_empty() {
+        return None;
+    }
+    let mut v = t.closest_point(q[0], q[1]).unwrap_or(hull[0]);
+    let mut d = sqdist(t, v, q);
+    loop {
+        let closer = finite_neighbours(t, v)
+            .map(|w| (sqdist(t, w, q), w))
+            .find(|(dw, _)| *dw < d);
+        match closer {
+            Some((dw, w)) => {
+                v = w;
+                d = dw;
+            }
+            None => return Some(v),
+        }
+    }
+}
+
+/// Calls `visit(vi, squared distance)` for the vertices by increasing distance to q,
+/// until it returns false.
+fn expand<F>(t: &mut startin::Triangulation, q: [f64; 2], mut visit: F)
+where
+    F: FnMut(usize, f64) -> bool,
+{
+    match closest_vertex(t, q) {
+        //-- no triangles (less than 3 vertices or all collinear): brute-force
+        None => {
+            let mut all: Vec<(f64, usize)> = (1..t.all_vertices().len())
+                .filter(|vi| !t.is_vertex_removed(*vi).unwrap())
+                .map(|vi| (sqdist(t, vi, q), vi))
+                .collect();
+            all.sort_by(|a, b| a.0.total_cmp(&b.0));
+            for (d2, vi) in all {
+                if !visit(vi, d2) {
+                    break;
+                }
+            }
+        }
+        Some(v) => {
+            let mut heap = BinaryHeap::new();
+            let mut seen: HashSet<usize> = HashSet::new();
+            heap.push(Candidate {
+                d2: sqdist(t, v, q),
+                vi: v,
+            });
+            seen.insert(v);
+            while let Some(c) = heap.pop() {
+                if !visit(c.vi, c.d2) {
+                    break;
+                }
+                for w in finite_neighbours(t, c.vi) {
+                    if seen.insert(w) {
+                        heap.push(Candidate {
+                            d2: sqdist(t, w, q),
+                            vi: w,
+                        });
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The k closest vertices to q, by increasing distance.
+pub fn nearest_k(t: &mut startin::Triangulation, q: [f64; 2], k: usize) -> Vec<usize> {
+    let mut re: Vec<usize> = Vec::with_capacity(k);
+    if k == 0 {
+        return re;
+    }
+    expand(t, q, |vi, _| {
+        re.push(vi);
+        re.len() < k
+    });
+    re
+}
+
+/// The vertices at a distance <= radius of q, by increasing distance.
+pub fn in_radius(t: &mut startin::Triangulation, q: [f64; 2], radius: f64) -> Vec<usize> {
+    let mut re: Vec<usize> = Vec::new();
+    let r2 = radius * radius;
+    expand(t, q, |vi, d2| {
+        if d2 > r2 {
+            return false;
+        }
+        re.push(vi);
+        true
+    });
+    re
+}
+
+/// The vertices inside the bbox [minx, miny, maxx, maxy] (or on its boundary), sorted.
+/// The vertices in the circumscribed circle of the bbox are visited.
+pub fn in_bbox(t: &mut startin::Triangulation, bbox: [f64; 4]) -> Vec<usize> {
+    let c = [(bbox[0] + bbox[2]) / 2.0, (bbox[1] + bbox[3]) / 2.0];
+    let r = ((bbox[2] - bbox[0]).powi(2) + (bbox[3] - bbox[1]).powi(2)).sqrt() / 2.0;
+    let mut re: Vec<usize> = in_radius(t, c, r)
+        .into_iter()
+        .filter(|vi| {
+            let p = t.get_point(*vi).unwrap();
+            p[0] >= bbox[0] && p[0] <= bbox[2] && p[1] >= bbox[1] && p[1] <= bbox[3]
+        })
+        .collect();
+    re.sort_unstable();
+    re
+}
+
+/// The vertices inside the polygon (a ring, closed or not), sorted.
+pub fn in_polygon(t: &mut startin::Triangulation, polygon: &[[f64; 2]]) -> Vec<usize> {
+    let mut bbox = [f64::MAX, f64::MAX, f64::MIN, f64::MIN];
+    for p in polygon {
+        bbox[0] = bbox[0].min(p[0]);
+        bbox[1] = bbox[1].min(p[1]);
+        bbox[2] = bbox[2].max(p[0]);
+        bbox[3] = bbox[3].max(p[1]);
+    }
+    in_bbox(t, bbox)
+        .into_iter()
+        .filter(|vi| {
+            let p = t.get_point(*vi).unwrap();
+            point_in_polygon([p[0], p[1]], polygon)
+        })
+        .collect()
+}
+
+/// Whether p is inside the polygon (crossing number, even-odd rule).
+pub fn point_in_polygon(p: [f64; 2], polygon: &[[f64; 2]]) -> bool {
+    let mut inside = false;
+    let n = polygon.len();
+    for i in 0..n {
+        let a = polygon[i];
+        let b = polygon[(i + 1) % n];
+        if (a[1] > p[1]) != (b[1] > p[1])
+            && p[0] < (b[0] - a[0]) * (p[1] - a[1]) / (b[1] - a[1]) + a[0]
+        {
+            inside = !inside;
+        }
+    }
+    inside
+}
@@ -0,0 +1,102 @@
+//! # sfc
+//!
+//! Sorting of points along a space-filling curve (Hilbert), to insert them in
+//! the DT with a good spatial coherence (the walks of the point location are then short).
+
+use rand::seq::SliceRandom;
+
+/// The number of bits for each coordinate of the Hilbert grid.
+const ORDER: u32 = 21;
+
+/// The size under which the first round of BRIO is not split further.
+const BRIO_MIN_ROUND: usize = 64;
+
+/// The index on the Hilbert curve of the cell (x, y) of a 2^ORDER x 2^ORDER grid.
+pub fn hilbert_key(x: u32, y: u32) -> u64 {
+    let n: u32 = 1 << ORDER;
+    let (mut x, mut y) = (x, y);
+    let mut d: u64 = 0;
+    let mut s: u32 = n / 2;
+    while s > 0 {
+        let rx = ((x & s) > 0) as u32;
+        let ry = ((y & s) > 0) as u32;
+        d += (s as u64) * (s as u64) * ((3 * rx) ^ ry) as u64;
+        //-- rotate the quadrant
+        if ry == 0 {
+            if rx == 1 {
+                x = n - 1 - x;
+                y = n - 1 - y;
+            }
+            std::mem::swap(&mut x, &mut y);
+        }
+        s /= 2;
+    }
+    d
+}
+
+fn bbox2d(pts: &[[f64; 3]]) -> [f64; 4] {
+    let mut bbox = [f64::MAX, f64::MAX, f64::MIN, f64::MIN];
+    for p in pts {
+        bbox[0] = bbox[0].min(p[0]);
+        bbox[1] = bbox[1].min(p[1]);
+        bbox[2] = bbox[2].max(p[0]);
+        bbox[3] = bbox[3].max(p[1]);
+    }
+    bbox
+}
+
+/// The Hilbert keys of the points, in the grid covering the bbox [minx, miny, maxx, maxy].
+fn keys(pts: &[[f64; 3]], bbox: &[f64; 4], keys: &mut [u64]) {
+    let n = ((1_u32 << ORDER) - 1) as f64;
+    let dx = if bbox[2] > bbox[0] {
+        bbox[2] - bbox[0]
+    } else {
+        1.0
+    };
+    let dy = if bbox[3] > bbox[1] {
+        bbox[3] - bbox[1]
+    } else {
+        1.0
+    };
+    for (k, p) in keys.iter_mut().zip(pts) {
+        let x = ((p[0] - bbox[0]) / dx * n) as u32;
+        let y = ((p[1] - bbox[1]) / dy * n) as u32;
+        *k = hilbert_key(x, y);
+    }
+}
+
+/// Returns the order of the points along the Hilbert curve of their bbox, the points
+/// with the same key keep their original order.
+pub fn hilbert_order(pts: &[[f64; 3]]) -> Vec<usize> {
+    let mut ks: Vec<u64> = vec![0; pts.len()];
+    keys(pts, &bbox2d(pts), &mut ks);
+    let mut ids: Vec<usize> = (0..pts.len()).collect();
+    ids.sort_by_key(|i| ks[*i]);
+    ids
+}
+
+/// Returns the biased randomised insertion order (BRIO) of the points
+/// (Amenta et al., 2003), and the number of rounds (at least 1, even if there are no
+/// points).
+/// The points are shuffled and split in rounds, the last round has half of the
+/// points, the one before a quarter, etc. Each round is sorted along the Hilbert
+/// curve of the bbox of all the points.
+pub fn brio_order(pts: &[[f64; 3]]) -> (Vec<usize>, usize) {
+    let mut ks: Vec<u64> = vec![0; pts.len()];
+    keys(pts, &bbox2d(pts), &mut ks);
+    let mut ids: Vec<usize> = (0..pts.len()).collect();
+    ids.shuffle(&mut rand::thread_rng());
+    let mut bounds: Vec<usize> = vec![pts.len()];
+    let mut m = pts.len();
+    while m > BRIO_MIN_ROUND {
+        m /= 2;
+        bounds.push(m);
+    }
+    bounds.push(0);
+    bounds.reverse();
+    bounds.dedup();
+    for w in bounds.windows(2) {
+        ids[w[0]..w[1]].sort_by_key(|i| ks[*i]);
+    }
+    (ids, (bounds.len() - 1).max(1))
+}
@@ -0,0 +1,480 @@
+//! # streaming
+//!
+//! Streaming construction of a DT, with spatial finalisation
+//! (Isenburg et al., 2006, "Streaming computation of Delaunay triangulations").
+//! The space is partitioned with a grid, computed in a first pass where the points of
+//! each cell are counted. In the second pass, once a cell is finalised (its last point
+//! is inserted) the triangles whose circumcircle is entirely inside finalised cells are
+//! final: they are emitted (written to an OBJ file and/or passed to a callback) and the
+//! vertices whose triangles are all emitted are removed from memory.
+//!
+//! Removing a vertex keeps all the (non-final) Delaunay triangles, the triangles filling
+//! the hole are not part of the DT of all the points: they are flagged as "done" so that
+//! they are never emitted. The flags of the triangles that do not exist anymore are
+//! pruned, since startin reuses the indices of the removed vertices, and the removed
+//! vertices are garbage collected once they outnumber the vertices in memory.
+
+use numpy::PyArray;
+use pyo3::exceptions;
+use pyo3::prelude::*;
+use startin::Triangle;
+
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+/// The number of removed vertices under which the garbage is not collected.
+const COMPACT_MIN: usize = 1024;
+
+fn key(v: &[usize; 3]) -> [usize; 3] {
+    let mut k = *v;
+    k.sort_unstable();
+    k
+}
+
+#[pyclass]
+/// A streaming Delaunay triangulation, for datasets that do not fit in memory
+pub struct StreamingDT {
+    t: startin::Triangulation,
+    cellsize: f64,
+    //-- number of points of each cell (i, j) counted in the first pass
+    counts: HashMap<(i64, i64), usize>,
+    //-- the grid: its first cell (i, j), and its number of columns and rows
+    origin: (i64, i64),
+    nx: usize,
+    ny: usize,
+    //-- number of points still expected in each cell
+    expected: Vec<usize>,
+    finalised: Vec<bool>,
+    started: bool,
+    //-- prefix sums of the cells that are not finalised, for O(1) queries
+    pending: Vec<usize>,
+    //-- triangles (sorted vertex ids) emitted or filling the holes of removed vertices
+    done: HashSet<[usize; 3]>,
+    //-- the id in the output of the vertices in memory that were emitted
+    outids: HashMap<usize, usize>,
+    nout_v: usize,
+    nout_t: usize,
+    fo: Option<BufWriter<File>>,
+    callback: Option<PyObject>,
+}
+
+#[pymethods]
+impl StreamingDT {
+    /// Constructor for a streaming DT, the finalisation grid has square cells of
+    /// size ``cellsize`` and covers the points counted with :func:`count`.
+    /// The final triangles are written to an OBJ file (path) and/or passed to
+    /// a callback function, called with 2 NumPy arrays: the vertices (with their xyz)
+    /// emitted for the first time, and the triangles (indices of the vertices in the
+    /// order they were emitted, 0-based).
+    ///
+    /// :param cellsize: the size of the cells of the finalisation grid
+    /// :param path: (default=None) the path of the OBJ file to write
+    /// :param callback: (default=None) the function called with the final triangles
+    ///
+    /// >>> def f(vertices, triangles):
+    /// >>>     print(len(vertices), len(triangles))
+    /// >>> sdt = startinpy.StreamingDT(100.0, callback=f)
+    #[new]
+    #[pyo3(signature = (cellsize, path=None, callback=None))]
+    fn new(cellsize: f64, path: Option<String>, callback: Option<PyObject>) -> PyResult<Self> {
+        if !(cellsize > 0.0 && cellsize.is_finite()) {
+            return Err(exceptions::PyValueError::new_err("Invalid cellsize"));
+        }
+        let fo = match path {
+            Some(p) => match File::create(p) {
+                Ok(f) => {
+                    let mut fo = BufWriter::new(f);
+                    writeln!(fo, "# startinpy streaming DT")?;
+                    Some(fo)
+                }
+                Err(_) => {
+                    return Err(exceptions::PyFileNotFoundError::new_err(
+                        "No such file or directory",
+                    ))
+                }
+            },
+            None => None,
+        };
+        Ok(StreamingDT {
+            t: startin::Triangulation::new(),
+            cellsize,
+            counts: HashMap::new(),
+            origin: (0, 0),
+            nx: 0,
+            ny: 0,
+            expected: Vec::new(),
+            finalised: Vec::new(),
+            started: false,
+            pending: Vec::new(),
+            done: HashSet::new(),
+            outids: HashMap::new(),
+            nout_v: 0,
+            nout_t: 0,
+            fo,
+            callback,
+        })
+    }
+
+    /// First pass: count the points that will be inserted in each cell, the grid covers
+    /// the cells with points.
+    /// A cell is then finalised when its last point is inserted.
+    /// Must be called, with all the points, before :func:`insert`.
+    ///
+    /// :param pts: an array of points (which is itself an array)
+    /// :return: (nothing)
+    ///
+    /// >>> for chunk in chunks:
+    /// >>>     sdt.count(chunk)
+    fn count(&mut self, pts: Vec<[f64; 3]>) -> PyResult<()> {
+        if self.started {
+            return Err(exceptions::PyValueError::new_err(
+                "count() must be called before insert()",
+            ));
+        }
+        for p in &pts {
+            if !(p[0].is_finite() && p[1].is_finite()) {
+                let s = format!("({}, {}) is not a valid point", p[0], p[1]);
+                return Err(exceptions::PyValueError::new_err(s));
+            }
+        }
+        for p in &pts {
+            *self.counts.entry(self.cell_key(p)).or_insert(0) += 1;
+        }
+        Ok(())
+    }
+
+    /// Second pass: insert the points, and emit the final triangles if cells were
+    /// finalised.
+    /// Throws an exception if a point was not counted with :func:`count` (or if its
+    /// cell already has all its points).
+    ///
+    /// :param pts: an array of points (which is itself an array)
+    /// :return: the number of triangles emitted
+    ///
+    /// >>> sdt.insert(pts)
+    fn insert(&mut self, py: Python<'_>, pts: Vec<[f64; 3]>) -> PyResult<usize> {
+        if !self.started {
+            if self.counts.is_empty() {
+                return Err(exceptions::PyValueError::new_err(
+                    "The points must be counted with count() first",
+                ));
+            }
+            self.start();
+        }
+        let cells = self.cells_of(&pts)?;
+        let mut tofinalise: Vec<usize> = Vec::new();
+        py.allow_threads(|| {
+            for (p, c) in pts.iter().zip(&cells) {
+                let _ = self.t.insert_one_pt(p[0], p[1], p[2]);
+                self.expected[*c] -= 1;
+                if self.expected[*c] == 0 {
+                    tofinalise.push(*c);
+                }
+            }
+        });
+        if tofinalise.is_empty() {
+            return Ok(0);
+        }
+        for c in tofinalise {
+            self.finalised[c] = true;
+        }
+        self.flush(py)
+    }
+
+    /// Finalise all the cells, emit all the remaining triangles, and close the file.
+    ///
+    /// :return: the number of triangles emitted
+    ///
+    /// >>> sdt.finalise()
+    fn finalise(&mut self, py: Python<'_>) -> PyResult<usize> {
+        self.finalised.iter_mut().for_each(|b| *b = true);
+        let n = self.flush(py)?;
+        if let Some(mut fo) = self.fo.take() {
+            fo.flush()?;
+        }
+        Ok(n)
+    }
+
+    /// The size of the finalisation grid, as (number of columns, number of rows), it is
+    /// known once the points are counted.
+    ///
+    /// :return: (nx, ny)
+    fn grid_size(&self) -> (usize, usize) {
+        if self.started {
+            return (self.nx, self.ny);
+        }
+        match self.grid() {
+            Some((_, nx, ny)) => (nx, ny),
+            None => (0, 0),
+        }
+    }
+
+    /// The number of vertices currently in memory.
+    ///
+    /// :return: number of vertices
+    fn number_of_vertices(&self) -> usize {
+        self.t.number_of_vertices()
+    }
+
+    /// The number of vertices emitted so far.
+    ///
+    /// :return: number of vertices
+    fn number_of_emitted_vertices(&self) -> usize {
+        self.nout_v
+    }
+
+    /// The number of triangles emitted so far.
+    ///
+    /// :return: number of triangles
+    fn number_of_emitted_triangles(&self) -> usize {
+        self.nout_t
+    }
+}
+
+impl StreamingDT {
+    fn cell_key(&self, p: &[f64; 3]) -> (i64, i64) {
+        (
+            (p[0] / self.cellsize).floor() as i64,
+            (p[1] / self.cellsize).floor() as i64,
+        )
+    }
+
+    /// The grid covering the counted cells: its origin, nx, and ny.
+    fn grid(&self) -> Option<((i64, i64), usize, usize)> {
+        let imin = self.counts.keys().map(|k| k.0).min()?;
+        let imax = self.counts.keys().map(|k| k.0).max()?;
+        let jmin = self.counts.keys().map(|k| k.1).min()?;
+        let jmax = self.counts.keys().map(|k| k.1).max()?;
+        let nx = (imax - imin + 1) as usize;
+        let ny = (jmax - jmin + 1) as usize;
+        Some(((imin, jmin), nx, ny))
+    }
+
+    /// Fixes the grid from the counts, the empty cells are finalised.
+    fn start(&mut self) {
+        let (origin, nx, ny) = self.grid().unwrap();
+        self.origin = origin;
+        self.nx = nx;
+        self.ny = ny;
+        self.expected = vec![0; nx * ny];
+        for ((i, j), n) in self.counts.drain() {
+            let c = (j - origin.1) as usize * nx + (i - origin.0) as usize;
+            self.expected[c] = n;
+        }
+        self.finalised = self.expected.iter().map(|n| *n == 0).collect();
+        self.update_pending();
+        self.started = true;
+    }
+
+    /// The cells of the points, an error if a point was not expected.
+    fn cells_of(&self, pts: &[[f64; 3]]) -> PyResult<Vec<usize>> {
+        let mut cells: Vec<usize> = Vec::with_capacity(pts.len());
+        let mut left: HashMap<usize, usize> = HashMap::new();
+        for p in pts {
+            let (i, j) = self.cell_key(p);
+            let (i, j) = (i - self.origin.0, j - self.origin.1);
+            let c = if p[0].is_finite()
+                && p[1].is_finite()
+                && (0..self.nx as i64).contains(&i)
+                && (0..self.ny as i64).contains(&j)
+            {
+                Some(j as usize * self.nx + i as usize)
+            } else {
+                None
+            };
+            match c.map(|c| (c, left.entry(c).or_insert(self.expected[c]))) {
+                Some((c, n)) if *n > 0 => {
+                    *n -= 1;
+                    cells.push(c);
+                }
+                _ => {
+                    let s = format!("({}, {}) was not counted", p[0], p[1]);
+                    return Err(exceptions::PyValueError::new_err(s));
+                }
+            }
+        }
+        Ok(cells)
+    }
+
+    fn update_pending(&mut self) {
+        let w = self.nx + 1;
+        self.pending = vec![0; w * (self.ny + 1)];
+        for j in 0..self.ny {
+            for i in 0..self.nx {
+                let v = if self.finalised[j * self.nx + i] {
+                    0
+                } else {
+                    1
+                };
+                self.pending[(j + 1) * w + i + 1] =
+                    v + self.pending[j * w + i + 1] + self.pending[(j + 1) * w + i]
+                        - self.pending[j * w + i];
+            }
+        }
+    }
+
+    /// Whether all the cells overlapping the circle are finalised (outside
+    /// the grid no points can be inserted).
+    fn is_final(&self, c: &[f64], r: f64) -> bool {
+        if !c[0].is_finite() || !c[1].is_finite() || !r.is_finite() {
+            return false;
+        }
+        let f = |v: f64, origin: i64, n: usize| -> i64 {
+            ((v / self.cellsize).floor() as i64 - origin).clamp(-1, n as i64)
+        };
+        let i0 = f(c[0] - r, self.origin.0, self.nx).max(0) as usize;
+        let i1 = f(c[0] + r, self.origin.0, self.nx).min(self.nx as i64 - 1);
+        let j0 = f(c[1] - r, self.origin.1, self.ny).max(0) as usize;
+        let j1 = f(c[1] + r, self.origin.1, self.ny).min(self.ny as i64 - 1);
+        if i1 < i0 as i64 || j1 < j0 as i64 {
+            return true;
+        }
+        let (i1, j1) = (i1 as usize + 1, j1 as usize + 1);
+        let w = self.nx + 1;
+        let n = self.pending[j1 * w + i1] + self.pending[j0 * w + i0]
+            - self.pending[j0 * w + i1]
+            - self.pending[j1 * w + i0];
+        n == 0
+    }
+
+    /// Emit the final triangles, and remove from memory the vertices that are
+    /// not needed anymore.
+    fn flush(&mut self, py: Python<'_>) -> PyResult<usize> {
+        self.update_pending();
+        //-- the triangles destroyed since the last flush, their indices can be reused
+        let t = &self.t;
+        self.done.retain(|k| {
+            t.is_triangle(&Triangle { v: *k })
+                || t.is_triangle(&Triangle {
+                    v: [k[0], k[2], k[1]],
+                })
+        });
+        let all = self.finalised.iter().all(|b| *b);
+        let mut trs: Vec<[usize; 3]> = Vec::new();
+        for tr in self.t.all_finite_triangles() {
+            if self.done.contains(&key(&tr.v)) {
+                continue;
+            }
+            if !all {
+                let a = self.t.get_point(tr.v[0]).unwrap();
+                let b = self.t.get_point(tr.v[1]).unwrap();
+                let c = self.t.get_point(tr.v[2]).unwrap();
+                let cc = startin::geom::circle_centre(&a, &b, &c);
+                if !self.is_final(&cc, startin::geom::distance2d(&cc, &a)) {
+                    continue;
+                }
+            }
+            trs.push(tr.v);
+        }
+        //-- emit the triangles
+        let mut newvs: Vec<Vec<f64>> = Vec::new();
+        let mut outtrs: Vec<Vec<usize>> = Vec::with_capacity(trs.len());
+        for tr in &trs {
+            let mut o: Vec<usize> = Vec::with_capacity(3);
+            for vi in tr {
+                let id = match self.outids.get(vi) {
+                    Some(id) => *id,
+                    None => {
+                        let p = self.t.get_point(*vi).unwrap();
+                        if let Some(fo) = &mut self.fo {
+                            writeln!(fo, "v {} {} {}", p[0], p[1], p[2])?;
+                        }
+                        newvs.push(p);
+                        self.outids.insert(*vi, self.nout_v);
+                        self.nout_v += 1;
+                        self.nout_v - 1
+                    }
+                };
+                o.push(id);
+            }
+            if let Some(fo) = &mut self.fo {
+                writeln!(fo, "f {} {} {}", o[0] + 1, o[1] + 1, o[2] + 1)?;
+            }
+            outtrs.push(o);
+            self.done.insert(key(tr));
+        }
+        self.nout_t += trs.len();
+        if let Some(f) = &self.callback {
+            if !outtrs.is_empty() {
+                let vs = PyArray::from_vec2(py, &newvs).unwrap();
+                let ts = PyArray::from_vec2(py, &outtrs).unwrap();
+                f.call1(py, (vs, ts))?;
+            }
+        }
+        //-- evict the vertices whose triangles are all emitted
+        if !all {
+            let mut candidates: Vec<usize> = trs.iter().flatten().copied().collect();
+            candidates.sort_unstable();
+            candidates.dedup();
+            for vi in candidates {
+                self.evict(vi);
+            }
+            self.compact();
+        }
+        Ok(trs.len())
+    }
+
+    /// Collects the garbage of the DT if it has more removed vertices than vertices,
+    /// the vertices in `done` and `outids` are renumbered.
+    fn compact(&mut self) {
+        let removed = self.t.number_of_removed_vertices();
+        if removed < COMPACT_MIN || removed < self.t.number_of_vertices() {
+            return;
+        }
+        let n = self.t.all_vertices().len();
+        let mut newids: Vec<usize> = vec![usize::MAX; n];
+        let mut cur: usize = 0;
+        for (vi, newid) in newids.iter_mut().enumerate() {
+            if vi == 0 || self.t.is_vertex_removed(vi) == Ok(false) {
+                *newid = cur;
+                cur += 1;
+            }
+        }
+        self.t.collect_garbage();
+        self.done = self
+            .done
+            .iter()
+            .filter(|k| k.iter().all(|vi| newids[*vi] != usize::MAX))
+            .map(|k| [newids[k[0]], newids[k[1]], newids[k[2]]])
+            .collect();
+        self.outids = self
+            .outids
+            .iter()
+            .map(|(vi, id)| (newids[*vi], *id))
+            .collect();
+    }
+
+    fn evict(&mut self, vi: usize) {
+        if self.t.is_vertex_removed(vi).unwrap_or(true) || self.t.is_vertex_convex_hull(vi) {
+            return;
+        }
+        let star = self.t.incident_triangles_to_vertex(vi).unwrap();
+        if !star.iter().all(|tr| self.done.contains(&key(&tr.v))) {
+            return;
+        }
+        let ring = self.t.adjacent_vertices_to_vertex(vi).unwrap();
+        let mut before: HashSet<[usize; 3]> = HashSet::new();
+        for v in &ring {
+            for tr in self.t.incident_triangles_to_vertex(*v).unwrap() {
+                before.insert(key(&tr.v));
+            }
+        }
+        for tr in &star {
+            self.done.remove(&key(&tr.v));
+        }
+        if self.t.remove(vi).is_err() {
+            return;
+        }
+        self.outids.remove(&vi);
+        //-- the triangles filling the hole are not Delaunay triangles of all the points
+        for v in &ring {
+            for tr in self.t.incident_triangles_to_vertex(*v).unwrap() {
+                let k = key(&tr.v);
+                if !before.contains(&k) {
+                    self.done.insert(k);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,259 @@
+//! # terrain
+//!
+//! Terrain derivatives at the vertices: the curvatures (from a quadric fitted to the
+//! k-ring of the vertex), the roughness, and the topographic position index (TPI); and
+//! the hillshade raster, from the normals of the triangles.
+//!
+//! The curvatures are positive where the surface is concave upwards (eg in a valley or
+//! a pit), and negative where it is convex (eg on a ridge or a peak).
+
+use crate::kriging;
+use crate::search;
+
+/// The kinds of curvature.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Curvature {
+    /// In the direction of the slope.
+    Profile,
+    /// In the direction of the contour line (the tangential curvature).
+    Plan,
+    Mean,
+}
+
+impl Curvature {
+    pub fn from_name(name: &str) -> Option<Curvature> {
+        match name {
+            "profile" => Some(Curvature::Profile),
+            "plan" => Some(Curvature::Plan),
+            "mean" => Some(Curvature::Mean),
+            _ => None,
+        }
+    }
+
+    /// The curvature from the derivatives [zx, zy, zxx, zyy, zxy].
+    fn value(&self, d: [f64; 5]) -> f64 {
+        let [zx, zy, zxx, zyy, zxy] = d;
+        let p = zx * zx + zy * zy;
+        let q = 1.0 + p;
+        match self {
+            Curvature::Mean => {
+                ((1.0 + zy * zy) * zxx - 2.0 * zx * zy * zxy + (1.0 + zx * zx) * zyy)
+                    / (2.0 * q.powf(1.5))
+            }
+            //-- undefined on a horizontal plane
+            _ if p == 0.0 => 0.0,
+            Curvature::Profile => {
+                (zxx * zx * zx + 2.0 * zxy * zx * zy + zyy * zy * zy) / (p * q.powf(1.5))
+            }
+            Curvature::Plan => {
+                (zxx * zy * zy - 2.0 * zxy * zx * zy + zyy * zx * zx) / (p * q.sqrt())
+            }
+        }
+    }
+}
+
+/// The vertices at most k edges away from vi (vi and the infinite vertex excluded).
+pub fn k_ring(t: &startin::Triangulation, vi: usize, k: usize) -> Vec<usize> {
+    let mut re: Vec<usize> = Vec::new();
+    let mut ring: Vec<usize> = vec![vi];
+    for _ in 0..k {
+        let mut next: Vec<usize> = Vec::new();
+        for v in ring {
+            for w in t.adjacent_vertices_to_vertex(v).unwrap() {
+                if w != 0 && w != vi && !re.contains(&w) {
+                    re.push(w);
+                    next.push(w);
+                }
+            }
+        }
+        ring = next;
+    }
+    re
+}
+
+fn is_alive(t: &startin::Triangulation, vi: usize) -> bool {
+    vi != 0 && t.is_vertex_removed(vi) == Ok(false)
+}
+
+/// The curvature at each vertex (NaN for the infinite vertex and the removed ones).
+/// The quadric needs 5 neighbours, if the k-ring has fewer then the next ring is added.
+pub fn curvature(t: &startin::Triangulation, kind: Curvature, rings: usize) -> Vec<f64> {
+    (0..t.all_vertices().len())
+        .map(|vi| {
+            if !is_alive(t, vi) {
+                return f64::NAN;
+            }
+            let mut k = rings;
+            let mut nbs = k_ring(t, vi, k);
+            while nbs.len() < 5 {
+                k += 1;
+                let more = k_ring(t, vi, k);
+                if more.len() == nbs.len() {
+                    break;
+                }
+                nbs = more;
+            }
+            match quadric(t, vi, &nbs) {
+                Some(d) => kind.value(d),
+                None => f64::NAN,
+            }
+        })
+        .collect()
+}
+
+/// The derivatives [zx, zy, zxx, zyy, zxy] at vi of the quadric
+/// z = a.x^2 + b.y^2 + c.xy + d.x + e.y passing through vi and fitted (least squares) to
+/// the neighbours, None if it cannot be fitted.
+fn quadric(t: &startin::Triangulation, vi: usize, nbs: &[usize]) -> Option<[f64; 5]> {
+    if nbs.len() < 5 {
+        return None;
+    }
+    let o = t.get_point(vi).unwrap();
+    let local: Vec<[f64; 3]> = nbs
+        .iter()
+        .map(|n| {
+            let p = t.get_point(*n).unwrap();
+            [p[0] - o[0], p[1] - o[1], p[2] - o[2]]
+        })
+        .collect();
+    //-- the coordinates are scaled for the conditioning of the system
+    let s = local
+        .iter()
+        .map(|p| p[0].abs().max(p[1].abs()))
+        .fold(0.0, f64::max);
+    if s == 0.0 {
+        return None;
+    }
+    let mut a: Vec<Vec<f64>> = vec![vec![0.0; 5]; 5];
+    let mut b: Vec<f64> = vec![0.0; 5];
+    for p in &local {
+        let (u, v) = (p[0] / s, p[1] / s);
+        let r = [u * u, v * v, u * v, u, v];
+        for i in 0..5 {
+            for j in 0..5 {
+                a[i][j] += r[i] * r[j];
+            }
+            b[i] += r[i] * p[2];
+        }
+    }
+    let x = kriging::gauss(a, b)?;
+    let s2 = s * s;
+    Some([
+        x[3] / s,
+        x[4] / s,
+        2.0 * x[0] / s2,
+        2.0 * x[1] / s2,
+        x[2] / s2,
+    ])
+}
+
+/// The roughness at each vertex: the root mean square of the elevation differences with
+/// the vertices of its k-ring (NaN for the infinite vertex and the removed ones).
+pub fn roughness(t: &startin::Triangulation, rings: usize) -> Vec<f64> {
+    (0..t.all_vertices().len())
+        .map(|vi| {
+            if !is_alive(t, vi) {
+                return f64::NAN;
+            }
+            let z = t.get_point(vi).unwrap()[2];
+            let nbs = k_ring(t, vi, rings);
+            let sum: f64 = nbs
+                .iter()
+                .map(|n| (t.get_point(*n).unwrap()[2] - z).powi(2))
+                .sum();
+            (sum / nbs.len() as f64).sqrt()
+        })
+        .collect()
+}
+
+/// The TPI at each vertex: its elevation minus the mean elevation of the vertices at a
+/// distance <= radius (NaN for the infinite vertex, the removed ones, and if there are
+/// no other vertices in the radius).
+pub fn tpi(t: &mut startin::Triangulation, radius: f64) -> Vec<f64> {
+    (0..t.all_vertices().len())
+        .map(|vi| {
+            if !is_alive(t, vi) {
+                return f64::NAN;
+            }
+            let p = t.get_point(vi).unwrap();
+            let zs: Vec<f64> = search::in_radius(t, [p[0], p[1]], radius)
+                .into_iter()
+                .filter(|n| *n != vi)
+                .map(|n| t.get_point(n).unwrap()[2])
+                .collect();
+            p[2] - zs.iter().sum::<f64>() / zs.len() as f64
+        })
+        .collect()
+}
+
+/// A raster (the rows from north to south) and its geotransform (as in GDAL:
+/// [minx, cellsize, 0, maxy, 0, -cellsize]).
+pub type Raster = (Vec<Vec<u8>>, [f64; 6]);
+
+/// The hillshade raster covering the bbox of the DT: a cell is shaded with the normal of
+/// the triangle containing its centre, the angles are in degrees and the azimuth is
+/// clockwise from the north.
+/// The value of a cell is 1 + 254 * the illumination (the cosine of the angle between the
+/// normal and the direction of the light, 0 if facing away), and 0 (no data) if the
+/// centre is outside the convex hull.
+/// If `multidirectional`, the illuminations from the azimuths 225, 270, 315, and 360 are
+/// combined, weighted with the aspect of the triangle (as in GDAL).
+pub fn hillshade(
+    t: &mut startin::Triangulation,
+    cellsize: f64,
+    azimuth: f64,
+    altitude: f64,
+    z_factor: f64,
+    multidirectional: bool,
+) -> Raster {
+    let bbox = t.get_bbox();
+    let ncols = (((bbox[2] - bbox[0]) / cellsize).ceil() as usize).max(1);
+    let nrows = (((bbox[3] - bbox[1]) / cellsize).ceil() as usize).max(1);
+    let mut raster: Vec<Vec<u8>> = vec![vec![0; ncols]; nrows];
+    for (i, row) in raster.iter_mut().enumerate() {
+        let y = bbox[3] - (i as f64 + 0.5) * cellsize;
+        for (j, cell) in row.iter_mut().enumerate() {
+            let x = bbox[0] + (j as f64 + 0.5) * cellsize;
+            let n = match t.locate(x, y).and_then(|tr| t.normal_triangle(&tr)) {
+                Ok(n) => n,
+                Err(_) => continue,
+            };
+            //-- the normal of the surface with the z-values multiplied by z_factor
+            let (nx, ny, nz) = (n[0] * z_factor, n[1] * z_factor, n[2]);
+            let norm = (nx * nx + ny * ny + nz * nz).sqrt();
+            let n = [nx / norm, ny / norm, nz / norm];
+            let v = if multidirectional {
+                //-- the azimuth of the downslope direction
+                let aspect = n[0].atan2(n[1]);
+                let flat = n[0] == 0.0 && n[1] == 0.0;
+                [225.0, 270.0, 315.0, 360.0_f64]
+                    .iter()
+                    .map(|az| {
+                        let w = if flat {
+                            0.5
+                        } else {
+                            (aspect - az.to_radians()).sin().powi(2)
+                        };
+                        w * illumination(n, *az, altitude)
+                    })
+                    .sum::<f64>()
+                    / 2.0
+            } else {
+                illumination(n, azimuth, altitude)
+            };
+            if v.is_finite() {
+                *cell = (1.0 + 254.0 * v.clamp(0.0, 1.0)).round() as u8;
+            }
+        }
+    }
+    let geotransform = [bbox[0], cellsize, 0.0, bbox[3], 0.0, -cellsize];
+    (raster, geotransform)
+}
+
+/// The cosine of the angle between the normal n and the direction of the light (0 if the
+/// surface faces away from it).
+fn illumination(n: [f64; 3], azimuth: f64, altitude: f64) -> f64 {
+    let (az, alt) = (azimuth.to_radians(), altitude.to_radians());
+    let light = [az.sin() * alt.cos(), az.cos() * alt.cos(), alt.sin()];
+    (n[0] * light[0] + n[1] * light[1] + n[2] * light[2]).max(0.0)
+}
@@ -0,0 +1,121 @@
+//! # validity
+//!
+//! The verification of the data structure of the DT (the stars of the vertices) and of
+//! its geometry, with the robust predicates.
+//!
+//! The empty-circumcircle property is verified locally: for each edge, the vertex opposite
+//! in the adjacent triangle must not be inside the circumcircle of the triangle. If the
+//! stars are consistent and the triangles CCW, this is equivalent to verifying every
+//! triangle against every vertex.
+
+use std::collections::HashSet;
+
+use startin::geom;
+
+/// The offending vertices and triangles, everything is empty if the DT is valid.
+#[derive(Default)]
+pub struct Report {
+    /// The vertices whose star is not consistent with those of the adjacent vertices.
+    pub stars: Vec<usize>,
+    /// The finite triangles that are not CCW (CW or flat).
+    pub orientation: Vec<[usize; 3]>,
+    /// The vertices where the convex hull is not closed or not convex.
+    pub convex_hull: Vec<usize>,
+    /// The triangles with a vertex inside their circumcircle (and that vertex).
+    pub delaunay: Vec<([usize; 3], usize)>,
+}
+
+impl Report {
+    pub fn is_valid(&self) -> bool {
+        self.stars.is_empty()
+            && self.orientation.is_empty()
+            && self.convex_hull.is_empty()
+            && self.delaunay.is_empty()
+    }
+}
+
+pub fn check(t: &startin::Triangulation, check_delaunay: bool) -> Report {
+    let mut re = Report::default();
+    if t.number_of_triangles() == 0 {
+        return re;
+    }
+    let n = t.all_vertices().len();
+    let alive = |vi: usize| t.is_vertex_removed(vi) == Ok(false);
+    //-- the stars: the triangle (v, a, b) must be in the star of a as (a, b, v)
+    for v in (0..n).filter(|v| alive(*v)) {
+        let link = t.adjacent_vertices_to_vertex(v).unwrap();
+        let unique: HashSet<usize> = link.iter().cloned().collect();
+        let consistent = link.len() >= 3
+            && unique.len() == link.len()
+            && !unique.contains(&v)
+            && link.iter().all(|w| alive(*w))
+            && (0..link.len()).all(|i| {
+                let (a, b) = (link[i], link[(i + 1) % link.len()]);
+                next_in_link(t, a, b) == Some(v)
+            });
+        if !consistent {
+            re.stars.push(v);
+        }
+    }
+    //-- the triangles are CCW
+    for tr in t.all_finite_triangles() {
+        if orient(t, tr.v[0], tr.v[1], tr.v[2]) != 1 {
+            re.orientation.push(tr.v);
+        }
+    }
+    //-- the convex hull: each vertex once, convex, and only its vertices are adjacent to
+    //-- the infinite vertex
+    let hull = t.convex_hull();
+    let h = hull.len();
+    let mut seen: HashSet<usize> = HashSet::new();
+    for i in 0..h {
+        let v = hull[(i + 1) % h];
+        if !seen.insert(v) || orient(t, hull[i], v, hull[(i + 2) % h]) == -1 {
+            re.convex_hull.push(v);
+        }
+    }
+    for v in (1..n).filter(|v| alive(*v)) {
+        let zeros = t
+            .adjacent_vertices_to_vertex(v)
+            .unwrap()
+            .iter()
+            .filter(|w| **w == 0)
+            .count();
+        if zeros != usize::from(seen.contains(&v)) && !re.convex_hull.contains(&v) {
+            re.convex_hull.push(v);
+        }
+    }
+    //-- the empty circumcircles
+    if check_delaunay && re.stars.is_empty() {
+        for tr in t.all_finite_triangles() {
+            for i in 0..3 {
+                let (a, b) = (tr.v[i], tr.v[(i + 1) % 3]);
+                //-- the triangle adjacent to ab is (b, a, d): d is after a in the star of b
+                let d = match next_in_link(t, b, a) {
+                    Some(d) if d != 0 => d,
+                    _ => continue,
+                };
+                let p: Vec<Vec<f64>> = tr.v.iter().map(|vi| t.get_point(*vi).unwrap()).collect();
+                if geom::incircle(&p[0], &p[1], &p[2], &t.get_point(d).unwrap(), true) > 0 {
+                    re.delaunay.push((tr.v, d));
+                }
+            }
+        }
+    }
+    re
+}
+
+/// The vertex after b in the star of a (None if b is not in it).
+fn next_in_link(t: &startin::Triangulation, a: usize, b: usize) -> Option<usize> {
+    let link = t.adjacent_vertices_to_vertex(a).ok()?;
+    let i = link.iter().position(|w| *w == b)?;
+    Some(link[(i + 1) % link.len()])
+}
+
+/// The orientation of abc, 0 if one of the vertices is removed.
+fn orient(t: &startin::Triangulation, a: usize, b: usize, c: usize) -> i8 {
+    match (t.get_point(a), t.get_point(b), t.get_point(c)) {
+        (Ok(pa), Ok(pb), Ok(pc)) => geom::orient2d(&pa, &pb, &pc, true),
+        _ => 0,
+    }
+}
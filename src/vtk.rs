@@ -0,0 +1,283 @@
+//! # vtk
+//!
+//! Writers for the [VTK](https://docs.vtk.org/en/latest/design_documents/VTKFileFormats.html)
+//! unstructured grids, either as XML (.vtu) or as the legacy format (.vtk).
+//! The triangles are VTK_TRIANGLE cells, and the attributes of the vertices and of the
+//! triangles are stored as PointData and CellData.
+
+use base64::Engine;
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+const VTK_TRIANGLE: u8 = 5;
+
+/// The values of one attribute.
+pub enum Values {
+    F64(Vec<f64>),
+    I64(Vec<i64>),
+    U64(Vec<u64>),
+    U8(Vec<u8>),
+}
+
+/// An attribute of the points or of the cells, `ncomp` values per point/cell.
+pub struct DataArray {
+    pub name: String,
+    pub ncomp: usize,
+    pub values: Values,
+}
+
+/// An unstructured grid of triangles.
+pub struct Grid {
+    pub pts: Vec<[f64; 3]>,
+    pub trs: Vec<[usize; 3]>,
+    pub point_data: Vec<DataArray>,
+    pub cell_data: Vec<DataArray>,
+}
+
+impl Values {
+    fn xml_type(&self) -> &str {
+        match self {
+            Values::F64(_) => "Float64",
+            Values::I64(_) => "Int64",
+            Values::U64(_) => "UInt64",
+            Values::U8(_) => "UInt8",
+        }
+    }
+
+    fn legacy_type(&self) -> &str {
+        match self {
+            Values::F64(_) => "double",
+            Values::I64(_) => "vtktypeint64",
+            Values::U64(_) => "vtktypeuint64",
+            Values::U8(_) => "unsigned_char",
+        }
+    }
+
+    fn to_bytes(&self, big_endian: bool) -> Vec<u8> {
+        let mut b: Vec<u8> = Vec::new();
+        match self {
+            Values::F64(v) => v.iter().for_each(|x| match big_endian {
+                true => b.extend_from_slice(&x.to_be_bytes()),
+                false => b.extend_from_slice(&x.to_le_bytes()),
+            }),
+            Values::I64(v) => v.iter().for_each(|x| match big_endian {
+                true => b.extend_from_slice(&x.to_be_bytes()),
+                false => b.extend_from_slice(&x.to_le_bytes()),
+            }),
+            Values::U64(v) => v.iter().for_each(|x| match big_endian {
+                true => b.extend_from_slice(&x.to_be_bytes()),
+                false => b.extend_from_slice(&x.to_le_bytes()),
+            }),
+            Values::U8(v) => b.extend_from_slice(v),
+        }
+        b
+    }
+
+    fn to_ascii(&self) -> String {
+        let s: Vec<String> = match self {
+            Values::F64(v) => v.iter().map(|x| x.to_string()).collect(),
+            Values::I64(v) => v.iter().map(|x| x.to_string()).collect(),
+            Values::U64(v) => v.iter().map(|x| x.to_string()).collect(),
+            Values::U8(v) => v.iter().map(|x| x.to_string()).collect(),
+        };
+        s.join(" ")
+    }
+}
+
+/// The area (3D), the slope (in degrees) and the normal of each triangle.
+pub fn triangle_data(pts: &[[f64; 3]], trs: &[[usize; 3]]) -> Vec<DataArray> {
+    let mut areas: Vec<f64> = Vec::with_capacity(trs.len());
+    let mut slopes: Vec<f64> = Vec::with_capacity(trs.len());
+    let mut normals: Vec<f64> = Vec::with_capacity(trs.len() * 3);
+    for tr in trs {
+        let (a, b, c) = (pts[tr[0]], pts[tr[1]], pts[tr[2]]);
+        let u = [b[0] - a[0], b[1] - a[1], b[2] - a[2]];
+        let v = [c[0] - a[0], c[1] - a[1], c[2] - a[2]];
+        let n = [
+            u[1] * v[2] - u[2] * v[1],
+            u[2] * v[0] - u[0] * v[2],
+            u[0] * v[1] - u[1] * v[0],
+        ];
+        let l = (n[0] * n[0] + n[1] * n[1] + n[2] * n[2]).sqrt();
+        areas.push(l / 2.0);
+        if l > 0.0 {
+            slopes.push((n[2] / l).clamp(-1.0, 1.0).acos().to_degrees());
+            normals.extend_from_slice(&[n[0] / l, n[1] / l, n[2] / l]);
+        } else {
+            slopes.push(f64::NAN);
+            normals.extend_from_slice(&[0.0, 0.0, 0.0]);
+        }
+    }
+    vec![
+        DataArray {
+            name: "area".to_string(),
+            ncomp: 1,
+            values: Values::F64(areas),
+        },
+        DataArray {
+            name: "slope".to_string(),
+            ncomp: 1,
+            values: Values::F64(slopes),
+        },
+        DataArray {
+            name: "normal".to_string(),
+            ncomp: 3,
+            values: Values::F64(normals),
+        },
+    ]
+}
+
+fn xml_data_array(
+    fo: &mut impl Write,
+    name: &str,
+    ncomp: usize,
+    values: &Values,
+    binary: bool,
+) -> std::io::Result<()> {
+    write!(
+        fo,
+        r#"        <DataArray type="{}" Name="{}" NumberOfComponents="{}" format="{}">"#,
+        values.xml_type(),
+        name.replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('"', "&quot;"),
+        ncomp,
+        if binary { "binary" } else { "ascii" }
+    )?;
+    if binary {
+        //-- uncompressed: the header (number of bytes) and the data are encoded together
+        let data = values.to_bytes(false);
+        let mut b = (data.len() as u64).to_le_bytes().to_vec();
+        b.extend_from_slice(&data);
+        write!(
+            fo,
+            "{}",
+            base64::engine::general_purpose::STANDARD.encode(&b)
+        )?;
+    } else {
+        write!(fo, "{}", values.to_ascii())?;
+    }
+    writeln!(fo, "</DataArray>")
+}
+
+/// Write the grid as a VTK XML UnstructuredGrid (.vtu), with the data arrays
+/// either inline binary (base64) or ascii.
+pub fn write_vtu(path: &str, grid: &Grid, binary: bool) -> std::io::Result<()> {
+    let mut fo = BufWriter::new(File::create(path)?);
+    writeln!(fo, r#"<?xml version="1.0"?>"#)?;
+    writeln!(
+        fo,
+        r#"<VTKFile type="UnstructuredGrid" version="1.0" byte_order="LittleEndian" header_type="UInt64">"#
+    )?;
+    writeln!(fo, "  <UnstructuredGrid>")?;
+    writeln!(
+        fo,
+        r#"    <Piece NumberOfPoints="{}" NumberOfCells="{}">"#,
+        grid.pts.len(),
+        grid.trs.len()
+    )?;
+    writeln!(fo, "      <PointData>")?;
+    for a in &grid.point_data {
+        xml_data_array(&mut fo, &a.name, a.ncomp, &a.values, binary)?;
+    }
+    writeln!(fo, "      </PointData>")?;
+    writeln!(fo, "      <CellData>")?;
+    for a in &grid.cell_data {
+        xml_data_array(&mut fo, &a.name, a.ncomp, &a.values, binary)?;
+    }
+    writeln!(fo, "      </CellData>")?;
+    writeln!(fo, "      <Points>")?;
+    let coords = Values::F64(grid.pts.iter().flatten().copied().collect());
+    xml_data_array(&mut fo, "Points", 3, &coords, binary)?;
+    writeln!(fo, "      </Points>")?;
+    writeln!(fo, "      <Cells>")?;
+    let conn = Values::I64(grid.trs.iter().flatten().map(|i| *i as i64).collect());
+    xml_data_array(&mut fo, "connectivity", 1, &conn, binary)?;
+    let offsets = Values::I64((1..=grid.trs.len()).map(|i| 3 * i as i64).collect());
+    xml_data_array(&mut fo, "offsets", 1, &offsets, binary)?;
+    let types = Values::U8(vec![VTK_TRIANGLE; grid.trs.len()]);
+    xml_data_array(&mut fo, "types", 1, &types, binary)?;
+    writeln!(fo, "      </Cells>")?;
+    writeln!(fo, "    </Piece>")?;
+    writeln!(fo, "  </UnstructuredGrid>")?;
+    writeln!(fo, "</VTKFile>")?;
+    fo.flush()
+}
+
+fn legacy_values(fo: &mut impl Write, values: &Values, binary: bool) -> std::io::Result<()> {
+    if binary {
+        fo.write_all(&values.to_bytes(true))?;
+        writeln!(fo)
+    } else {
+        writeln!(fo, "{}", values.to_ascii())
+    }
+}
+
+fn legacy_data_array(fo: &mut impl Write, a: &DataArray, binary: bool) -> std::io::Result<()> {
+    //-- names cannot contain spaces in the legacy format
+    let name = a.name.replace(char::is_whitespace, "_");
+    if a.ncomp == 3 && a.name == "normal" {
+        writeln!(fo, "NORMALS {} {}", name, a.values.legacy_type())?;
+    } else {
+        writeln!(
+            fo,
+            "SCALARS {} {} {}",
+            name,
+            a.values.legacy_type(),
+            a.ncomp
+        )?;
+        writeln!(fo, "LOOKUP_TABLE default")?;
+    }
+    legacy_values(fo, &a.values, binary)
+}
+
+/// Write the grid as a legacy VTK file (.vtk), binary (big endian) or ascii.
+pub fn write_vtk(path: &str, grid: &Grid, binary: bool) -> std::io::Result<()> {
+    let mut fo = BufWriter::new(File::create(path)?);
+    writeln!(fo, "# vtk DataFile Version 3.0")?;
+    writeln!(fo, "startinpy TIN")?;
+    writeln!(fo, "{}", if binary { "BINARY" } else { "ASCII" })?;
+    writeln!(fo, "DATASET UNSTRUCTURED_GRID")?;
+    writeln!(fo, "POINTS {} double", grid.pts.len())?;
+    let coords = Values::F64(grid.pts.iter().flatten().copied().collect());
+    legacy_values(&mut fo, &coords, binary)?;
+    writeln!(fo, "CELLS {} {}", grid.trs.len(), grid.trs.len() * 4)?;
+    if binary {
+        for tr in &grid.trs {
+            fo.write_all(&3_i32.to_be_bytes())?;
+            for i in tr {
+                fo.write_all(&(*i as i32).to_be_bytes())?;
+            }
+        }
+        writeln!(fo)?;
+    } else {
+        for tr in &grid.trs {
+            writeln!(fo, "3 {} {} {}", tr[0], tr[1], tr[2])?;
+        }
+    }
+    writeln!(fo, "CELL_TYPES {}", grid.trs.len())?;
+    if binary {
+        for _ in &grid.trs {
+            fo.write_all(&(VTK_TRIANGLE as i32).to_be_bytes())?;
+        }
+        writeln!(fo)?;
+    } else {
+        for _ in &grid.trs {
+            writeln!(fo, "{}", VTK_TRIANGLE)?;
+        }
+    }
+    if !grid.point_data.is_empty() {
+        writeln!(fo, "POINT_DATA {}", grid.pts.len())?;
+        for a in &grid.point_data {
+            legacy_data_array(&mut fo, a, binary)?;
+        }
+    }
+    if !grid.cell_data.is_empty() {
+        writeln!(fo, "CELL_DATA {}", grid.trs.len())?;
+        for a in &grid.cell_data {
+            legacy_data_array(&mut fo, a, binary)?;
+        }
+    }
+    fo.flush()
+}
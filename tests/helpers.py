@@ -0,0 +1,3 @@
+def trs_xy(vertices, triangles):
+    """The triangles as sets of (x, y), to compare DTs with different vertex ids."""
+    return set(frozenset(tuple(vertices[v][:2]) for v in tr) for tr in triangles)
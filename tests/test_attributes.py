@@ -70,3 +70,37 @@ def test_no_attribute():
         a = dt.get_vertex_attributes(2)
     with pytest.raises(Exception):
         a = dt.get_vertex_attributes(12)
+
+
+def test_insert_las():
+    las = laspy.read("data/small.laz")
+    dt = startinpy.DT()
+    n = dt.insert_las("data/small.laz")
+    assert n == (las.classification == 2).sum()
+    assert dt.number_of_vertices() <= n
+    dt = startinpy.DT()
+    n = dt.insert_las("data/small.laz", classes=None, returns=[-1], thin=10)
+    last = (las.return_number == las.number_of_returns).sum()
+    assert n == (last + 9) // 10
+    with pytest.raises(ValueError):
+        dt.insert_las("data/small.laz", thin=0)
+    with pytest.raises(FileNotFoundError):
+        dt.insert_las("data/nothere.laz")
+
+
+def test_insert_las_attributes():
+    las = laspy.read("data/small.laz")
+    dt = startinpy.DT(np.dtype([("intensity", np.float64), ("c", np.uint64)]))
+    dt.insert_las(
+        "data/small.laz",
+        classes=[6],
+        attributes={"intensity": "intensity", "classification": "c"},
+    )
+    i = las.intensity[las.classification == 6]
+    a = dt.get_vertex_attributes(1)
+    assert a["intensity"] == pytest.approx(i[0])
+    assert a["c"] == 6
+    with pytest.raises(ValueError):
+        dt.insert_las("data/small.laz", attributes={"intensity": "reflectance"})
+    with pytest.raises(ValueError):
+        dt.insert_las("data/small.laz", attributes={"colour": "intensity"})
@@ -1,6 +1,7 @@
 import numpy as np
 import pytest
 import startinpy
+from helpers import trs_xy
 
 
 def test_init():
@@ -73,3 +74,33 @@ def test_collinear():
     dt.remove(4)
     assert dt.number_of_vertices() == 3
     assert dt.number_of_triangles() == 0
+
+
+def test_insert_strategies():
+    rng = np.random.default_rng(11)
+    pts = rng.random((3000, 3)) * 100
+    pts = np.vstack((pts, pts[:5]))
+
+    dt1 = startinpy.DT()
+    assert dt1.insertion_statistics() == {}
+    dt1.insert(pts)
+    for s in ["Hilbert", "BRIO"]:
+        dt2 = startinpy.DT()
+        dt2.insert(pts, insertionstrategy=s)
+        assert dt1.number_of_vertices() == dt2.number_of_vertices()
+        trs1 = trs_xy(dt1.points, dt1.triangles)
+        assert trs1 == trs_xy(dt2.points, dt2.triangles)
+        stats = dt2.insertion_statistics()
+        assert stats["strategy"] == s
+        assert stats["points"] == 3005
+        assert stats["inserted"] == 3000
+        assert stats["duplicates"] == 5
+        assert stats["sort_time"] >= 0.0
+        assert stats["insert_time"] >= 0.0
+        if s == "BRIO":
+            assert stats["rounds"] > 1
+    dt3 = startinpy.DT()
+    dt3.insert(np.empty((0, 3)), insertionstrategy="BRIO")
+    assert dt3.insertion_statistics()["rounds"] == 1
+    with pytest.raises(AttributeError):
+        dt1.insert(pts, insertionstrategy="Random")
@@ -120,6 +120,271 @@ def test_idw():
     dt = startinpy.DT()
     dt.insert(pts)
     locs = [[5.0, 5.0], [9.0, 9.0]]
-    re = dt.interpolate({"method": "IDW", "radius": 3.0, "power": 2.0}, locs)
+    i = {"method": "IDW", "radius": 3.0, "power": 2.0, "min_points": 0}
+    re = dt.interpolate(i, locs)
     assert np.isnan(re[0])
     assert re[1] == pytest.approx(3.0)
+    # -- the radius grows until a vertex is found
+    re = dt.interpolate({"method": "IDW", "radius": 3.0, "power": 2.0}, locs)
+    assert re[0] == pytest.approx(2.5)
+    assert re[1] == pytest.approx(3.0)
+
+
+def test_idw_variants():
+    pts = random(500)
+    dt = startinpy.DT()
+    dt.insert(pts)
+    loc = [40.0, 60.0]
+    d = np.hypot(dt.points[1:, 0] - loc[0], dt.points[1:, 1] - loc[1])
+    vis, ws = dt.interpolation_weights({"method": "IDW", "power": 2.0, "k": 7}, loc)
+    assert np.sort(vis).tolist() == np.sort(np.argsort(d)[:7] + 1).tolist()
+    i = {"method": "IDW", "power": 2.0, "radius": 20.0, "max_points": 5}
+    vis, ws = dt.interpolation_weights(i, loc)
+    assert np.sort(vis).tolist() == np.sort(np.argsort(d)[:5] + 1).tolist()
+    i = {"method": "IDW", "power": 2.0, "k": 8, "sectors": 4}
+    vis, ws = dt.interpolation_weights(i, loc)
+    a = np.arctan2(dt.points[vis, 1] - loc[1], dt.points[vis, 0] - loc[0])
+    quadrants = np.floor(np.mod(a, 2 * np.pi) / (np.pi / 2))
+    assert len(vis) == 8
+    assert np.bincount(quadrants.astype(int), minlength=4).tolist() == [2, 2, 2, 2]
+    # -- ellipse along the x-axis
+    i = {
+        "method": "IDW",
+        "power": 2.0,
+        "radius": 30.0,
+        "minor_radius": 5.0,
+        "angle": 0.0,
+        "min_points": 0,
+    }
+    vis, ws = dt.interpolation_weights(i, loc)
+    dx = dt.points[vis, 0] - loc[0]
+    dy = dt.points[vis, 1] - loc[1]
+    assert ((dx / 30.0) ** 2 + (dy / 5.0) ** 2 <= 1.0 + 1e-9).all()
+    assert ws.sum() == pytest.approx(1.0)
+    for i in [
+        {"method": "IDW", "power": 2.0},
+        {"method": "IDW", "power": 2.0, "radius": 10.0, "sectors": 4},
+        {"method": "IDW", "power": 2.0, "k": 5, "sectors": 3},
+        {"method": "IDW", "power": 2.0, "radius": 10.0, "minor_radius": 20.0},
+        {"method": "IDW", "power": 2.0, "k": 3, "min_points": 10},
+    ]:
+        with pytest.raises(ValueError):
+            dt.interpolate(i, [loc])
+
+
+def dt_attributes(n=200, missing=True):
+    pts = random(n)
+    dt = startinpy.DT(np.dtype([("intensity", float), ("classification", np.uint64)]))
+    for p in pts:
+        dt.insert_one_pt(p, intensity=2.0 * p[2] + 1.0, classification=3)
+    # -- a vertex without attributes
+    if missing:
+        dt.insert_one_pt([50.0, 150.0, 7.0])
+    return dt
+
+
+def test_interpolate_attribute():
+    # -- z is interpolated by startin, the attributes by startinpy: same results
+    dt = dt_attributes(missing=False)
+    locs = random(30)[:, :2] * 0.6 + 20.0
+    interpolants = [
+        {"method": "NN"},
+        {"method": "TIN"},
+        {"method": "Laplace"},
+        {"method": "NNI"},
+        {"method": "NNI", "precompute": True},
+        {"method": "IDW", "radius": 30.0, "power": 2.0},
+        {"method": "IDW", "radius": 0.5, "power": 2.0},
+    ]
+    for i in interpolants:
+        z = dt.interpolate(i, locs)
+        a = dt.interpolate(i, locs, attribute="intensity")
+        assert a == pytest.approx(2.0 * z + 1.0)
+        c = dt.interpolate(i, locs, attribute="classification")
+        assert c == pytest.approx(np.full(30, 3.0))
+    with pytest.raises(ValueError):
+        dt.interpolate({"method": "TIN"}, locs, attribute="colour")
+
+
+def test_interpolate_attribute_missing():
+    dt = dt_attributes()
+    # -- the vertex without attributes is used for locations close to it
+    re = dt.interpolate(
+        {"method": "TIN"}, [[50.0, 149.0], [50.0, 50.0]], attribute="intensity"
+    )
+    assert np.isnan(re[0])
+    assert not np.isnan(re[1])
+    re = dt.interpolate({"method": "NN"}, [[-50.0, 50.0]], attribute="intensity")
+    assert np.isnan(re[0])
+    with pytest.raises(Exception):
+        dt.interpolate(
+            {"method": "NN"}, [[-50.0, 50.0]], strict=True, attribute="intensity"
+        )
+
+
+def test_interpolate_attributes():
+    dt = dt_attributes(missing=False)
+    locs = random(10)[:, :2] * 0.6 + 20.0
+    re = dt.interpolate_attributes({"method": "Laplace"}, locs)
+    assert re.dtype.names == ("intensity", "classification")
+    assert re["intensity"] == pytest.approx(
+        dt.interpolate({"method": "Laplace"}, locs, attribute="intensity")
+    )
+    assert re["classification"] == pytest.approx(np.full(10, 3.0))
+    with pytest.raises(ValueError):
+        startinpy.DT().interpolate_attributes({"method": "TIN"}, locs)
+
+
+def test_interpolation_weights():
+    dt = startinpy.DT()
+    dt.insert(random(300))
+    loc = [40.0, 60.0]
+    for i in [
+        {"method": "NN"},
+        {"method": "TIN"},
+        {"method": "Laplace"},
+        {"method": "NNI"},
+        {"method": "IDW", "radius": 20.0, "power": 2.0},
+    ]:
+        vis, ws = dt.interpolation_weights(i, loc)
+        assert vis.shape == ws.shape
+        assert ws.sum() == pytest.approx(1.0)
+        z = np.sum(dt.points[vis, 2] * ws)
+        assert z == pytest.approx(dt.interpolate(i, [loc])[0])
+    vis, ws = dt.interpolation_weights({"method": "TIN"}, loc)
+    assert (np.sort(vis) == np.sort(dt.locate(loc))).all()
+    with pytest.raises(Exception):
+        dt.interpolation_weights({"method": "TIN"}, [-10.0, 60.0])
+
+
+def test_interpolation_weights_many():
+    dt = startinpy.DT()
+    dt.insert(random(300))
+    locs = random(50)[:, :2] * 0.5 + 25.0
+    locs[0] = [-10.0, 60.0]
+    z = np.nan_to_num(dt.points[:, 2], posinf=0.0)
+    for i in [{"method": "TIN"}, {"method": "NNI", "precompute": True}]:
+        indptr, indices, data = dt.interpolation_weights_many(i, locs)
+        assert indptr.shape == (51,)
+        assert indptr[0] == 0 and indptr[1] == 0
+        assert indices.shape == data.shape == (indptr[-1],)
+        # -- sparse matrix-vector product
+        re = np.zeros(50)
+        for r in range(50):
+            row = slice(indptr[r], indptr[r + 1])
+            re[r] = np.sum(data[row] * z[indices[row]])
+        expected = dt.interpolate(i, locs)
+        assert np.isnan(expected[0])
+        assert re[1:] == pytest.approx(expected[1:])
+    with pytest.raises(Exception):
+        dt.interpolation_weights_many({"method": "TIN"}, locs, strict=True)
+
+
+def test_c1_linear():
+    pts = random(300)
+    pts[:, 2] = 0.3 * pts[:, 0] - 0.2 * pts[:, 1] + 5.0
+    dt = startinpy.DT()
+    dt.insert(pts)
+    locs = random(30)[:, :2] * 0.6 + 20.0
+    expected = 0.3 * locs[:, 0] - 0.2 * locs[:, 1] + 5.0
+    for i in [{"method": "SibsonC1"}, {"method": "CloughTocher"}]:
+        re = dt.interpolate(i, locs)
+        assert re == pytest.approx(expected)
+
+
+def test_c1_smooth():
+    pts = random(1000)
+    pts[:, 2] = np.sin(pts[:, 0] / 15.0) * np.cos(pts[:, 1] / 15.0) * 10.0
+    dt = startinpy.DT()
+    dt.insert(pts)
+    locs = random(200)[:, :2] * 0.6 + 20.0
+    expected = np.sin(locs[:, 0] / 15.0) * np.cos(locs[:, 1] / 15.0) * 10.0
+    e_tin = np.abs(dt.interpolate({"method": "TIN"}, locs) - expected).mean()
+    for i in [{"method": "SibsonC1"}, {"method": "CloughTocher"}]:
+        e = np.abs(dt.interpolate(i, locs) - expected).mean()
+        assert e < e_tin
+
+
+def test_c1_outside_and_vertices():
+    pts = four_points()
+    dt = startinpy.DT()
+    dt.insert(pts)
+    dt.insert_one_pt([5.0, 5.0, 11.1])
+    for i in [{"method": "SibsonC1"}, {"method": "CloughTocher"}]:
+        re = dt.interpolate(i, [[5.0, 5.0], [5.0, -0.1]])
+        assert re[0] == pytest.approx(11.1)
+        assert np.isnan(re[1])
+        with pytest.raises(Exception):
+            dt.interpolate(i, [[5.0, -0.1]], strict=True)
+        with pytest.raises(ValueError):
+            dt.interpolation_weights(i, [5.0, 5.0])
+
+
+def test_c1_attributes():
+    dt = dt_attributes(missing=False)
+    locs = random(30)[:, :2] * 0.5 + 25.0
+    for i in [{"method": "SibsonC1"}, {"method": "CloughTocher"}]:
+        z = dt.interpolate(i, locs)
+        re = dt.interpolate(i, locs, attribute="intensity")
+        assert re == pytest.approx(2.0 * z + 1.0)
+        re = dt.interpolate_attributes(i, locs)
+        assert re["intensity"] == pytest.approx(2.0 * z + 1.0)
+
+
+def test_variogram():
+    pts = random(500)
+    pts[:, 2] = np.sin(pts[:, 0] / 15.0) * 10.0
+    dt = startinpy.DT()
+    dt.insert(pts)
+    lags, gammas, counts = dt.experimental_variogram(bins=10, max_distance=40.0)
+    assert lags.shape == gammas.shape == counts.shape == (10,)
+    assert (lags > 0.0).all() and (lags < 40.0).all()
+    assert (counts > 0).all()
+    assert gammas[0] < gammas[-1]
+    for m in ["spherical", "exponential", "gaussian"]:
+        v = dt.fit_variogram(m, bins=10, max_distance=40.0)
+        assert v["model"] == m
+        assert v["range"] > 0.0
+        assert 0.0 <= v["nugget"] <= v["sill"]
+    with pytest.raises(ValueError):
+        dt.fit_variogram("cubic")
+    with pytest.raises(ValueError):
+        dt.experimental_variogram(bins=0)
+    # -- by default 10 times the mean length of the edges
+    edges = {tuple(sorted(e)) for tr in dt.triangles for e in [tr[:2], tr[1:], tr[::2]]}
+    ls = [np.hypot(*(dt.points[a, :2] - dt.points[b, :2])) for a, b in edges]
+    lags, gammas, counts = dt.experimental_variogram(bins=10)
+    assert lags.max() < 10.0 * np.mean(ls)
+    with pytest.raises(Exception):
+        startinpy.DT().fit_variogram()
+
+
+def test_kriging():
+    pts = random(300)
+    pts[:, 2] = np.sin(pts[:, 0] / 15.0) * 10.0
+    dt = startinpy.DT()
+    dt.insert(pts)
+    v = {"model": "exponential", "sill": 50.0, "range": 60.0, "nugget": 0.1}
+    locs = random(30)[:, :2] * 0.6 + 20.0
+    locs[0] = dt.points[7, :2]
+    for nb in ["natural", 0, 2]:
+        i = {"method": "Kriging", "variogram": v, "neighbours": nb}
+        re, var = dt.interpolate(i, locs, return_variance=True)
+        assert re == pytest.approx(dt.interpolate(i, locs))
+        assert re[0] == pytest.approx(dt.points[7, 2])
+        assert var[0] == pytest.approx(0.0)
+        assert (var >= 0.0).all()
+        vis, ws = dt.interpolation_weights(i, locs[1])
+        assert ws.sum() == pytest.approx(1.0)
+        assert np.sum(dt.points[vis, 2] * ws) == pytest.approx(re[1])
+    re, var = dt.interpolate(i, [[-10.0, 50.0]], return_variance=True)
+    assert np.isnan(re[0]) and np.isnan(var[0])
+    with pytest.raises(Exception):
+        dt.interpolate(i, [[-10.0, 50.0]], strict=True)
+    with pytest.raises(ValueError):
+        dt.interpolate({"method": "TIN"}, locs, return_variance=True)
+    with pytest.raises(ValueError):
+        dt.interpolate({"method": "Kriging"}, locs)
+    with pytest.raises(ValueError):
+        v = {"model": "spherical", "sill": 1.0, "range": 60.0, "nugget": 2.0}
+        dt.interpolate({"method": "Kriging", "variogram": v}, locs)
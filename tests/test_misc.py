@@ -57,3 +57,291 @@ def test_update_vertex_z_value():
     assert dt.points[3][2] == pytest.approx(5.55)
     re = dt.update_vertex_z_value(9, 5.55)
     assert re == False
+
+
+def test_move_vertex():
+    dt = dt_5_points()
+    assert dt.move_vertex(5, [5.0, 5.0, 9.0]) == 5
+    assert dt.points[5][2] == pytest.approx(9.0)
+    assert dt.move_vertex(5, [2.0, 7.0, 8.0]) == 5
+    assert (dt.points[5] == np.array([2.0, 7.0, 8.0])).all()
+    assert dt.number_of_vertices() == 5
+    # -- outside the convex hull
+    assert dt.move_vertex(3, [20.0, 20.0, 3.0]) == 3
+    assert sorted(dt.convex_hull()) == [1, 2, 3, 4]
+    # -- same triangles as a DT built from scratch
+    dt2 = startinpy.DT()
+    dt2.insert(dt.points[1:])
+    assert dt.number_of_triangles() == dt2.number_of_triangles()
+    for tr in dt.triangles:
+        assert dt2.is_triangle(tr)
+    with pytest.raises(IndexError):
+        dt.move_vertex(0, [1.0, 1.0, 1.0])
+    with pytest.raises(IndexError):
+        dt.move_vertex(9, [1.0, 1.0, 1.0])
+    # -- without the vertex there would be no triangles
+    dt = startinpy.DT()
+    for p in [[0.0, 0.0, 1.0], [10.0, 0.0, 2.0], [0.0, 10.0, 3.0], [5.0, 0.0, 4.0]]:
+        dt.insert_one_pt(p)
+    with pytest.raises(Exception):
+        dt.move_vertex(3, [8.0, 8.0, 1.0])
+    assert (dt.points[3] == np.array([0.0, 10.0, 3.0])).all()
+    assert dt.move_vertex(4, [4.0, 1.0, 4.0]) == 4
+
+
+def test_move_vertex_attributes_duplicates():
+    dt = startinpy.DT(np.dtype([("intensity", float)]))
+    dt.insert_one_pt([0.0, 0.0, 1.0], intensity=1.0)
+    dt.insert_one_pt([10.0, 0.0, 2.0], intensity=2.0)
+    dt.insert_one_pt([10.0, 10.0, 3.0], intensity=3.0)
+    dt.insert_one_pt([0.0, 10.0, 4.0], intensity=4.0)
+    dt.insert_one_pt([5.0, 5.0, 5.0], intensity=5.0)
+    dt.user_ids = [-1, 10, 20, 30, 40, 50]
+    assert dt.move_vertex(5, [3.0, 6.0, 5.0]) == 5
+    assert dt.get_vertex_attributes(5)["intensity"] == pytest.approx(5.0)
+    assert dt.user_ids[5] == 50
+    # -- moved on another vertex: the duplicates handling is used
+    dt.duplicates_handling = "Highest"
+    assert dt.move_vertex(5, [0.0, 10.0, 5.0]) == 4
+    assert dt.is_vertex_removed(5)
+    assert dt.number_of_vertices() == 4
+    assert dt.points[4][2] == pytest.approx(5.0)
+    assert dt.get_vertex_attributes(4)["intensity"] == pytest.approx(5.0)
+    assert dt.user_ids[4] == 40
+
+
+def test_threads():
+    from concurrent.futures import ThreadPoolExecutor
+
+    def build(seed):
+        rng = np.random.default_rng(seed)
+        dt = startinpy.DT()
+        dt.insert(rng.random((1000, 3)) * 100)
+        return dt
+
+    with ThreadPoolExecutor(max_workers=4) as ex:
+        dts = list(ex.map(build, range(8)))
+    assert all(dt.number_of_vertices() == 1000 for dt in dts)
+    # -- a DT created in a thread can be used in another one
+    with ThreadPoolExecutor(max_workers=2) as ex:
+        tin = {"method": "TIN"}
+        zs = list(ex.map(lambda dt: dt.interpolate(tin, [[50.0, 50.0]]), dts))
+    assert all(len(z) == 1 for z in zs)
+    # -- the same DT shared by several threads, the calls wait for each other
+    dt = dts[0]
+    rng = np.random.default_rng(42)
+    locs = rng.random((500, 2)) * 100
+
+    def work(i):
+        if i % 2 == 0:
+            dt.insert(np.random.default_rng(i).random((100, 3)) * 100)
+        return dt.interpolate({"method": "Laplace"}, locs)
+
+    with ThreadPoolExecutor(max_workers=4) as ex:
+        zs = list(ex.map(work, range(8)))
+    assert dt.number_of_vertices() == 1400
+    assert all(len(z) == 500 for z in zs)
+    assert dt.is_valid()["valid"]
+
+
+def test_threads_reentry():
+    # -- Python code run while the arguments are read cannot use the same DT
+    dt = dt_5_points()
+
+    class Power:
+        def __float__(self):
+            dt.number_of_vertices()
+            return 2.0
+
+    with pytest.raises(RuntimeError):
+        dt.interpolate({"method": "IDW", "power": Power(), "radius": 5.0}, [[5.0, 5.0]])
+    assert dt.number_of_vertices() == 5
+
+
+def test_triangle_quality():
+    dt = dt_5_points()
+    q = dt.triangle_quality()
+    assert q.shape == (4,)
+    # -- the 4 triangles are right isosceles triangles
+    assert q["min_angle"] == pytest.approx(45.0)
+    assert q["max_angle"] == pytest.approx(90.0)
+    assert q["area_2d"] == pytest.approx(25.0)
+    assert q["area_2d"].sum() == pytest.approx(100.0)
+    assert (q["area_3d"] >= q["area_2d"]).all()
+    assert q["aspect_ratio"] == pytest.approx(np.sqrt(3.0))
+    assert q["radius_edge_ratio"] == pytest.approx(5.0 / np.hypot(5.0, 5.0))
+    dt = startinpy.DT()
+    dt.insert([[0.0, 0.0, 0.0], [2.0, 0.0, 0.0], [1.0, np.sqrt(3.0), 0.0]])
+    q = dt.triangle_quality()
+    assert q["min_angle"][0] == pytest.approx(60.0)
+    assert q["aspect_ratio"][0] == pytest.approx(1.0)
+    assert q["radius_edge_ratio"][0] == pytest.approx(1.0 / np.sqrt(3.0))
+
+
+def test_statistics():
+    dt = dt_5_points()
+    s = dt.statistics()
+    assert s["number_vertices"] == 5
+    assert s["number_triangles"] == 4
+    assert s["number_removed_vertices"] == 0
+    assert s["area_2d"] == pytest.approx(100.0)
+    assert s["area_3d"] > 100.0
+    assert s["z_min"] == 1.0 and s["z_max"] == 5.0
+    assert s["mean_edge_length"] == pytest.approx((40.0 + 4 * np.hypot(5, 5)) / 8)
+    assert s["point_density"] == pytest.approx(0.05)
+    assert s["degree_histogram"] == {3: 4, 4: 1}
+    dt.remove(5)
+    s = dt.statistics()
+    assert s["number_vertices"] == 4
+    assert s["number_removed_vertices"] == 1
+    s = startinpy.DT().statistics()
+    assert s["number_vertices"] == 0 and np.isnan(s["z_min"])
+
+
+def test_refine():
+    rng = np.random.default_rng(3)
+    pts = rng.random((300, 3)) * [98.0, 98.0, 10.0] + [1.0, 1.0, 0.0]
+    corners = [
+        [0.0, 0.0, 0.0],
+        [100.0, 0.0, 0.0],
+        [100.0, 100.0, 0.0],
+        [0.0, 100.0, 0.0],
+    ]
+    dt = startinpy.DT(np.dtype([("intensity", np.float64)]))
+    for p in corners:
+        dt.insert_one_pt(p, intensity=1.0)
+    dt.insert(pts)
+    new = dt.refine(min_angle=28.0, max_area=20.0)
+    assert len(new) > 0
+    assert dt.number_of_vertices() == 304 + len(new)
+    q = dt.triangle_quality()
+    assert q["min_angle"].min() >= 28.0
+    assert q["area_2d"].max() <= 20.0
+    assert q["area_2d"].sum() == pytest.approx(10000.0)
+    steiner = dt.attributes["steiner"]
+    assert steiner[new].all()
+    assert steiner.sum() == len(new)
+    assert dt.get_vertex_attributes(1) == {"intensity": 1.0}
+    z = dt.points[new, 2]
+    assert (z >= 0.0).all() and (z <= 10.0).all()
+    # -- already refined
+    assert len(dt.refine(min_angle=28.0, max_area=20.0)) == 0
+
+
+@pytest.mark.parametrize("angle", [17.0, 30.0, 45.0])
+def test_refine_rotated(angle):
+    a = np.radians(angle)
+    rot = np.array(
+        [[np.cos(a), np.sin(a), 0.0], [-np.sin(a), np.cos(a), 0.0], [0.0, 0.0, 1.0]]
+    )
+    rng = np.random.default_rng(3)
+    pts = rng.random((300, 3)) * [98.0, 98.0, 10.0] + [1.0, 1.0, 0.0]
+    square = [
+        [0.0, 0.0, 0.0],
+        [100.0, 0.0, 0.0],
+        [100.0, 100.0, 0.0],
+        [0.0, 100.0, 0.0],
+    ]
+    kite = [[0.0, 0.0, 0.0], [100.0, 20.0, 0.0], [130.0, 100.0, 0.0], [20.0, 60.0, 0.0]]
+    for corners, interior, area in [(square, pts, 10000.0), (kite, [], 6600.0)]:
+        dt = startinpy.DT()
+        dt.insert(np.vstack([np.array(corners), np.reshape(interior, (-1, 3))]) @ rot)
+        new = dt.refine(min_angle=28.0, max_area=20.0)
+        assert len(new) > 0
+        q = dt.triangle_quality()
+        assert q["area_2d"].min() > 1e-3
+        assert q["min_angle"].min() >= 28.0
+        assert q["area_2d"].max() <= 20.0
+        assert q["area_2d"].sum() == pytest.approx(area)
+
+
+def test_refine_errors():
+    dt = dt_5_points()
+    with pytest.raises(ValueError):
+        dt.refine(min_angle=40.0)
+    with pytest.raises(ValueError):
+        dt.refine(max_area=-1.0)
+    dt = startinpy.DT(np.dtype([("steiner", np.int64)]))
+    dt.insert([[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]])
+    with pytest.raises(ValueError):
+        dt.refine()
+
+
+def dt_surface(f):
+    x, y = np.meshgrid(np.linspace(-2.0, 2.0, 17), np.linspace(-2.0, 2.0, 17))
+    x, y = x.ravel(), y.ravel()
+    dt = startinpy.DT()
+    dt.insert(np.column_stack((x, y, f(x, y))))
+    return dt
+
+
+def test_curvature():
+    dt = dt_surface(lambda x, y: x * x + y * y)
+    o = dt.closest_point([0.0, 0.0])
+    a = dt.closest_point([1.0, 0.0])
+    c = dt.curvature("mean")
+    assert c.shape == (dt.points.shape[0],)
+    assert np.isnan(c[0])
+    assert c[o] == pytest.approx(2.0)
+    assert c[a] == pytest.approx(12.0 / (2.0 * 5.0**1.5))
+    assert dt.curvature("profile")[a] == pytest.approx(8.0 / (4.0 * 5.0**1.5))
+    assert dt.curvature("plan", rings=2)[a] == pytest.approx(8.0 / (4.0 * 5.0**0.5))
+    # -- a ridge is convex
+    dt = dt_surface(lambda x, y: -(x * x))
+    assert dt.curvature("mean")[dt.closest_point([0.0, 0.0])] < 0.0
+    dt = dt_surface(lambda x, y: 2.0 * x + 3.0 * y)
+    c = dt.curvature("profile")
+    assert np.allclose(c[1:], 0.0)
+    with pytest.raises(ValueError):
+        dt.curvature("gaussian")
+    with pytest.raises(ValueError):
+        dt.curvature("mean", rings=0)
+
+
+def test_roughness_tpi():
+    dt = dt_surface(lambda x, y: 2.0 * x + 3.0 * y)
+    assert np.all(dt.roughness()[1:] > 0.0)
+    dt = dt_surface(lambda x, y: np.zeros_like(x))
+    r = dt.roughness(rings=2)
+    assert np.isnan(r[0])
+    assert np.allclose(r[1:], 0.0)
+    dt.update_vertex_z_value(dt.closest_point([0.0, 0.0]), 10.0)
+    tpi = dt.tpi(0.6)
+    assert tpi[dt.closest_point([0.0, 0.0])] == pytest.approx(10.0)
+    assert tpi[dt.closest_point([0.25, 0.0])] < 0.0
+    assert tpi[dt.closest_point([2.0, 2.0])] == pytest.approx(0.0)
+    dt.remove(1)
+    assert np.isnan(dt.tpi(0.6)[1])
+    with pytest.raises(ValueError):
+        dt.tpi(0.0)
+
+
+def test_hillshade():
+    dt = dt_surface(lambda x, y: np.zeros_like(x))
+    raster, gt = dt.hillshade(0.5)
+    assert raster.dtype == np.uint8
+    assert raster.shape == (8, 8)
+    assert gt == (-2.0, 0.5, 0.0, 2.0, 0.0, -0.5)
+    # -- a horizontal plane: cos(45°)
+    assert (raster == round(1 + 254 * np.sqrt(0.5))).all()
+    raster, _ = dt.hillshade(0.5, multidirectional=True)
+    assert (raster == round(1 + 254 * np.sqrt(0.5))).all()
+    # -- a slope facing the south-east
+    dt = dt_surface(lambda x, y: y - x)
+    raster, _ = dt.hillshade(0.5)
+    assert (raster == 1).all()
+    raster, _ = dt.hillshade(0.5, azimuth=135.0)
+    assert (raster == round(1 + 254 * (1.0 + np.sqrt(0.5)) / np.sqrt(3.0))).all()
+    # -- a ridge along x=0: the breakline is between 2 columns
+    dt = dt_surface(lambda x, y: -np.abs(x))
+    raster, _ = dt.hillshade(0.5, azimuth=270.0, z_factor=2.0)
+    assert (raster[:, :4] > raster[:, 4:]).all()
+    # -- the cells outside the convex hull have no data
+    dt = startinpy.DT()
+    dt.insert([[0.0, 0.0, 0.0], [10.0, 0.0, 0.0], [0.0, 10.0, 0.0]])
+    raster, _ = dt.hillshade(1.0)
+    assert raster[0, 9] == 0 and raster[9, 0] > 0
+    with pytest.raises(ValueError):
+        dt.hillshade(0.0)
+    with pytest.raises(Exception):
+        startinpy.DT().hillshade(1.0)
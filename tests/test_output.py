@@ -1,6 +1,8 @@
 import json
+import struct
 
 import numpy as np
+import pytest
 import startinpy
 
 
@@ -87,3 +89,260 @@ def test_geojson(tmp_path):
                 nof += 1
         assert nov == 5
         assert nof == 4
+
+
+def test_geojson_user_ids(tmp_path):
+    dt = dt_5_points()
+    dt.user_ids = [-1, 10, 20, 30, 40, 50]
+    dt.remove(2)
+    ofile = tmp_path / "out.geojson"
+    dt.write_geojson(str(ofile))
+    with open(ofile) as f:
+        j = json.load(f)
+    uids = [
+        f["properties"]["user_id"]
+        for f in j["features"]
+        if f["geometry"]["type"] == "Point"
+    ]
+    assert uids == [10, 30, 40, 50]
+
+
+def test_gltf(tmp_path):
+    dt = dt_5_points()
+    d = tmp_path
+    ofile = d / "out.gltf"
+    dt.write_gltf(str(ofile), binary=False, vertex_colors_from="z")
+    with open(ofile) as f:
+        j = json.load(f)
+        assert j["asset"]["version"] == "2.0"
+        prim = j["meshes"][0]["primitives"][0]
+        assert j["accessors"][prim["attributes"]["POSITION"]]["count"] == 5
+        assert "NORMAL" in prim["attributes"]
+        assert "COLOR_0" in prim["attributes"]
+        assert j["accessors"][prim["indices"]]["count"] == 4 * 3
+        assert j["nodes"][1]["translation"] == [0.0, 0.0, 0.0]
+
+
+def test_glb(tmp_path):
+    dt = dt_5_points()
+    dt.remove(5)
+    d = tmp_path
+    ofile = d / "out.glb"
+    dt.write_gltf(str(ofile), normals=False)
+    with open(ofile, "rb") as f:
+        b = f.read()
+        assert b[:4] == b"glTF"
+        jlength = int.from_bytes(b[12:16], "little")
+        j = json.loads(b[20 : 20 + jlength])
+        prim = j["meshes"][0]["primitives"][0]
+        assert j["accessors"][prim["attributes"]["POSITION"]]["count"] == 4
+        assert "NORMAL" not in prim["attributes"]
+        assert j["accessors"][prim["indices"]]["count"] == 2 * 3
+
+
+def dt_lonlat():
+    dt = startinpy.DT()
+    pts = np.array(
+        [
+            [4.0, 52.0, 1.0],
+            [4.2, 52.0, 2.0],
+            [4.2, 52.2, 3.0],
+            [4.0, 52.2, 4.0],
+            [4.1, 52.1, 5.0],
+        ]
+    )
+    dt.insert(pts)
+    return dt
+
+
+def test_quantized_mesh(tmp_path):
+    dt = dt_lonlat()
+    ofile = tmp_path / "0.terrain"
+    dt.write_quantized_mesh(str(ofile), [4.0, 52.0, 4.2, 52.2])
+    with open(ofile, "rb") as f:
+        b = f.read()
+        minh, maxh = struct.unpack_from("<2f", b, 24)
+        assert minh == pytest.approx(1.0)
+        assert maxh == pytest.approx(5.0)
+        (nov,) = struct.unpack_from("<I", b, 88)
+        assert nov == 5
+        (nof,) = struct.unpack_from("<I", b, 92 + 6 * nov)
+        assert nof == 4
+    # -- cut in half, 2 new vertices on the boundary
+    dt.write_quantized_mesh(str(ofile), [4.0, 52.0, 4.1, 52.2])
+    with open(ofile, "rb") as f:
+        (nov,) = struct.unpack_from("<I", f.read(), 88)
+        assert nov == 5
+    with pytest.raises(ValueError):
+        dt.write_quantized_mesh(str(ofile), [5.0, 52.0, 5.2, 52.2])
+
+
+def test_quantized_mesh_tiles(tmp_path):
+    dt = dt_lonlat()
+    tiles = dt.write_quantized_mesh_tiles(str(tmp_path), 0)
+    assert len(tiles) == 1
+    assert list(tiles[0]) == [0, 1, 0]
+    assert (tmp_path / "0" / "1" / "0.terrain").exists()
+    tiles = dt.write_quantized_mesh_tiles(str(tmp_path), 10, normals=True)
+    assert len(tiles) > 1
+    with pytest.raises(ValueError):
+        dt.write_quantized_mesh_tiles(str(tmp_path), 31)
+    # -- 2 vertices in the same quantized (u, v) are not merged
+    dt.insert_one_pt([4.0015, 52.0015, 6.0])
+    dt.write_quantized_mesh_tiles(str(tmp_path), 0)
+    with open(tmp_path / "0" / "1" / "0.terrain", "rb") as f:
+        (nov,) = struct.unpack_from("<I", f.read(), 88)
+        assert nov == 6
+
+
+def test_quantized_mesh_tiles_thin(tmp_path):
+    # -- a long and thin triangle is only in the tiles it intersects
+    dt = startinpy.DT()
+    dt.insert_one_pt([4.0, 52.0, 1.0])
+    dt.insert_one_pt([5.0, 53.0, 2.0])
+    dt.insert_one_pt([5.001, 53.0, 3.0])
+    tiles = dt.write_quantized_mesh_tiles(str(tmp_path), 14)
+    rows = {t[2] for t in tiles}
+    assert len(rows) > 80
+    assert len(tiles) <= 3 * len(rows)
+
+
+def test_landxml(tmp_path):
+    dt = dt_5_points()
+    dt.remove(2)
+    ofile = tmp_path / "out.xml"
+    dt.write_landxml(str(ofile), name="myterrain")
+    with open(ofile) as f:
+        s = f.read()
+        assert s.count("<P id=") == 4
+        assert s.count("<F>") == 2
+        assert '<Surface name="myterrain">' in s
+    dt2 = startinpy.DT()
+    assert dt2.read_landxml(str(ofile)) == 4
+    assert dt2.number_of_vertices() == 4
+    assert dt2.number_of_triangles() == 2
+    assert dt2.points[1] == pytest.approx(dt.points[1])
+    dt3 = startinpy.DT()
+    assert dt3.read_landxml(str(ofile), insertionstrategy="BRIO") == 4
+    assert dt3.insertion_statistics()["strategy"] == "BRIO"
+    assert dt3.number_of_triangles() == 2
+    with pytest.raises(ValueError):
+        dt2.read_landxml(str(ofile), name="other")
+    with pytest.raises(AttributeError):
+        dt2.read_landxml(str(ofile), insertionstrategy="Random")
+    with pytest.raises(ValueError):
+        dt.write_landxml(str(ofile), units="parsec")
+    dt.user_ids = [-1, 10, -1, 30, 40, 50]
+    dt.write_landxml(str(ofile))
+    with open(ofile) as f:
+        s = f.read()
+        assert s.count('" name="') == 4
+        assert '<P id="2" name="30">' in s
+
+
+def test_stl(tmp_path):
+    dt = dt_5_points()
+    ofile = tmp_path / "out.stl"
+    dt.write_stl(str(ofile))
+    with open(ofile, "rb") as f:
+        b = f.read()
+        assert struct.unpack("<I", b[80:84])[0] == 4
+        assert len(b) == 84 + 4 * 50
+    dt.write_stl(str(ofile), binary=False)
+    with open(ofile) as f:
+        s = f.read()
+        assert s.startswith("solid")
+        assert s.count("facet normal") == 4
+
+
+def test_stl_solid(tmp_path):
+    dt = dt_5_points()
+    ofile = tmp_path / "out.stl"
+    dt.write_stl(str(ofile), solid=True, base_z=0.0)
+    with open(ofile, "rb") as f:
+        b = f.read()
+        # -- 4 top + 2*4 walls + 2 bottom
+        assert struct.unpack("<I", b[80:84])[0] == 14
+    with pytest.raises(ValueError):
+        dt.write_stl(str(ofile), solid=True, base_z=1.5)
+
+
+def test_off(tmp_path):
+    dt = dt_5_points()
+    dt.remove(4)
+    ofile = tmp_path / "out.off"
+    dt.write_off(str(ofile))
+    with open(ofile) as f:
+        lines = f.readlines()
+        assert lines[0].strip() == "OFF"
+        assert lines[1].split() == ["4", "2", "0"]
+    dt.write_off(str(ofile), solid=True)
+    with open(ofile) as f:
+        lines = f.readlines()
+        assert lines[1].split() == ["8", "12", "0"]
+        # -- closed: every edge is shared by exactly 2 faces
+        edges = {}
+        for l in lines[2 + 8 :]:
+            f = [int(i) for i in l.split()[1:]]
+            for i in range(3):
+                e = (min(f[i], f[(i + 1) % 3]), max(f[i], f[(i + 1) % 3]))
+                edges[e] = edges.get(e, 0) + 1
+        assert all(v == 2 for v in edges.values())
+
+
+def test_off_solid_snap_tolerance(tmp_path):
+    # -- hull vertices closer than the default snap tolerance are all in the base
+    dt = startinpy.DT()
+    dt.snap_tolerance = 0.0001
+    for p in [[0.0, 0.0, 1.0], [0.0005, 0.0, 1.0], [0.0005, 0.0005, 1.0]]:
+        dt.insert_one_pt(p)
+    ofile = tmp_path / "out.off"
+    dt.write_off(str(ofile), solid=True, base_z=0.0)
+    with open(ofile) as f:
+        lines = f.readlines()
+        # -- 1 top + 2*3 walls + 1 bottom
+        assert lines[1].split() == ["6", "8", "0"]
+
+
+def test_vtu(tmp_path):
+    import base64
+    import xml.etree.ElementTree as ET
+
+    dt = startinpy.DT()
+    dt.set_attributes_schema(np.dtype([("classification", np.uint64), ("name", "<U8")]))
+    dt.insert_one_pt([0.0, 0.0, 1.0], classification=2)
+    dt.insert_one_pt([10.0, 0.0, 2.0], classification=2)
+    dt.insert_one_pt([10.0, 10.0, 3.0], classification=6)
+    dt.insert_one_pt([0.0, 10.0, 4.0], classification=6)
+    dt.insert_one_pt([5.0, 5.0, 5.0], classification=9)
+    dt.remove(5)
+    ofile = tmp_path / "out.vtu"
+    dt.write_vtu(str(ofile))
+    root = ET.parse(ofile).getroot()
+    piece = root.find("UnstructuredGrid/Piece")
+    assert piece.get("NumberOfPoints") == "4"
+    assert piece.get("NumberOfCells") == "2"
+    pd = [a.get("Name") for a in piece.find("PointData")]
+    assert pd == ["classification"]
+    cd = [a.get("Name") for a in piece.find("CellData")]
+    assert cd == ["area", "slope", "normal"]
+    for a in piece.find("PointData"):
+        b = base64.b64decode(a.text)
+        n = struct.unpack("<Q", b[:8])[0]
+        assert list(struct.unpack("<%dQ" % (n // 8), b[8:])) == [2, 2, 6, 6]
+    dt.write_vtu(str(ofile), binary=False)
+    root = ET.parse(ofile).getroot()
+    conn = root.find(".//Cells/DataArray[@Name='connectivity']").text.split()
+    assert max(int(i) for i in conn) == 3
+
+
+def test_vtk(tmp_path):
+    dt = dt_5_points()
+    ofile = tmp_path / "out.vtk"
+    dt.write_vtk(str(ofile), binary=False)
+    with open(ofile) as f:
+        s = f.read()
+        assert "DATASET UNSTRUCTURED_GRID" in s
+        assert "POINTS 5 double" in s
+        assert "CELLS 4 16" in s
+        assert "NORMALS normal double" in s
@@ -104,3 +104,98 @@ def test_get_point():
     dt.remove(77)
     with pytest.raises(IndexError):
         p = dt.get_point(77)
+
+
+def test_remove_many():
+    dt = startinpy.DT()
+    dt.insert(random(100))
+    assert dt.remove_many([3, 7, 7, 11]) == 3
+    assert dt.number_of_vertices() == 97
+    with pytest.raises(IndexError):
+        dt.remove_many([12, 7])
+    assert dt.is_vertex_removed(12) == False
+    with pytest.raises(IndexError):
+        dt.remove_many([0])
+    assert dt.remove_many([12, 7, 0, 500], ignore_invalid=True) == 1
+    assert dt.remove_many([1, 2], collect_garbage=True) == 2
+    assert dt.has_garbage() == False
+    assert dt.number_of_vertices() == 94
+    assert dt.points.shape[0] == 95
+
+
+def test_remove_in_polygon():
+    dt = startinpy.DT()
+    dt.insert(random(500))
+    poly = [[20.0, 20.0], [80.0, 20.0], [50.0, 80.0]]
+    inside = dt.vertices_in_polygon(poly)
+    assert dt.remove_in_polygon(poly) == inside.shape[0]
+    assert dt.number_of_vertices() == 500 - inside.shape[0]
+    assert dt.vertices_in_polygon(poly).shape[0] == 0
+
+
+def test_remove_where():
+    dt = startinpy.DT()
+    dt.insert(random(200))
+    mask = dt.points[:, 2] > 50.0
+    n = np.count_nonzero(mask[1:])
+    assert dt.remove_where(mask, collect_garbage=True) == n
+    assert dt.number_of_vertices() == 200 - n
+    assert (dt.points[1:, 2] <= 50.0).all()
+    with pytest.raises(ValueError):
+        dt.remove_where(mask[:10])
+
+
+def test_garbagecollection_mapping():
+    pts = random(100)
+    dt = startinpy.DT()
+    dt.insert(pts)
+    dt.remove(31)
+    dt.remove(61)
+    old = dt.points
+    mapping = dt.collect_garbage()
+    assert mapping.shape == (101,)
+    assert mapping[0] == 0
+    assert mapping[31] == -1 and mapping[61] == -1
+    assert mapping[30] == 30 and mapping[32] == 31 and mapping[100] == 98
+    for i, m in enumerate(mapping[1:], start=1):
+        if m >= 0:
+            assert (dt.points[m] == old[i]).all()
+    assert (dt.collect_garbage() == np.arange(99)).all()
+
+
+def test_user_ids():
+    pts = random(50)
+    dt = startinpy.DT()
+    assert (dt.user_ids == np.array([-1])).all()
+    dt.insert(pts)
+    assert (dt.user_ids == -1).all()
+    with pytest.raises(ValueError):
+        dt.user_ids = np.arange(10)
+    dt.user_ids = np.arange(1000, 1051)
+    assert dt.user_ids[0] == -1
+    assert dt.user_ids[7] == 1007
+    dt.remove_many([3, 4, 5])
+    assert dt.user_ids[4] == -1
+    dt.collect_garbage()
+    assert dt.user_ids.shape == (48,)
+    assert dt.user_ids[3] == 1006
+    assert dt.user_ids[47] == 1050
+    vi = dt.insert_one_pt([150.0, 150.0, 1.0])[0]
+    assert dt.user_ids[vi] == -1
+    dt.remove(vi)
+    vi = dt.insert_one_pt([-50.0, 150.0, 1.0])[0]
+    assert dt.user_ids[vi] == -1
+
+
+def test_user_ids_reset():
+    # -- with 2 vertices left startin renumbers them at the next insertion
+    dt = startinpy.DT()
+    for p in [[0.0, 0.0, 1.0], [10.0, 0.0, 2.0], [0.0, 10.0, 3.0]]:
+        dt.insert_one_pt(p)
+    dt.user_ids = [-1, 10, 20, 30]
+    dt.remove(1)
+    vi = dt.insert_one_pt([8.0, 8.0, 4.0])[0]
+    assert dt.user_ids[vi] == -1
+    for i in range(1, 3):
+        uid = 20 if dt.points[i][0] == 10.0 else 30
+        assert dt.user_ids[i] == uid
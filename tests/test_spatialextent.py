@@ -89,3 +89,64 @@ def test_closest_point():
     assert dt.closest_point([7.1, 2.1]) == 5
     with pytest.raises(Exception):
         dt.locate(-1.0, 9.0)
+
+
+def test_locate_many():
+    dt = dt_5_points()
+    trs = dt.locate_many(np.array([[7.1, 2.1], [-1.0, 9.0], [2.0, 5.0]]))
+    assert trs.shape == (3, 3)
+    assert (trs[0] == dt.locate([7.1, 2.1])).all()
+    assert (trs[1] == np.array([0, 0, 0])).all()
+    assert (trs[2] == dt.locate([2.0, 5.0])).all()
+    assert dt.locate_many(np.zeros((0, 2))).shape == (0, 3)
+
+
+def test_closest_point_many():
+    dt = dt_5_points()
+    re = dt.closest_point_many([[7.1, 2.1], [-1.0, 9.0], [0.1, 9.9]])
+    assert (re == np.array([5, 0, 4])).all()
+
+
+def test_is_inside_convex_hull_many():
+    dt = dt_5_points()
+    locs = random(100)[:, :2] * 0.2 - 5.0
+    re = dt.is_inside_convex_hull_many(locs)
+    assert re.dtype == bool
+    for l, b in zip(locs, re):
+        assert dt.is_inside_convex_hull(l) == b
+
+
+def test_nearest_k():
+    dt = startinpy.DT()
+    pts = random(500)
+    dt.insert(pts)
+    for q in [[50.0, 50.0], [-30.0, 120.0], [250.0, 10.0]]:
+        d = np.hypot(dt.points[1:, 0] - q[0], dt.points[1:, 1] - q[1])
+        re = dt.nearest_k(q, 7)
+        assert (re == np.argsort(d)[:7] + 1).all()
+    assert dt.nearest_k([50.0, 50.0], 1000).shape == (500,)
+    assert dt.nearest_k([50.0, 50.0], 0).shape == (0,)
+
+
+def test_vertices_in_radius():
+    dt = startinpy.DT()
+    dt.insert(random(500))
+    for q in [[50.0, 50.0], [-5.0, 20.0]]:
+        d = np.hypot(dt.points[1:, 0] - q[0], dt.points[1:, 1] - q[1])
+        re = dt.vertices_in_radius(q, 12.0)
+        assert set(re) == set(np.nonzero(d <= 12.0)[0] + 1)
+    assert dt.vertices_in_radius([-500.0, 0.0], 1.0).shape == (0,)
+    with pytest.raises(ValueError):
+        dt.vertices_in_radius([50.0, 50.0], -1.0)
+
+
+def test_vertices_in_bbox_polygon():
+    dt = dt_5_points()
+    assert (dt.vertices_in_bbox([-1.0, -1.0, 6.0, 6.0]) == np.array([1, 5])).all()
+    assert dt.vertices_in_bbox([20.0, 20.0, 30.0, 30.0]).shape == (0,)
+    with pytest.raises(ValueError):
+        dt.vertices_in_bbox([6.0, 6.0, -1.0, -1.0])
+    poly = [[-1.0, -1.0], [12.0, -1.0], [-1.0, 12.0]]
+    assert (dt.vertices_in_polygon(poly) == np.array([1, 2, 4, 5])).all()
+    with pytest.raises(ValueError):
+        dt.vertices_in_polygon(poly[:2])
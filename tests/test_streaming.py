@@ -0,0 +1,63 @@
+import numpy as np
+import pytest
+import startinpy
+from helpers import trs_xy
+
+
+def random_points(n, seed=1):
+    rng = np.random.default_rng(seed)
+    pts = rng.random((n, 3)) * [1000.0, 1000.0, 10.0]
+    # -- sorted per row of cells, like a tiled dataset
+    order = np.lexsort((pts[:, 0] // 100, pts[:, 1] // 100))
+    return pts[order]
+
+
+def test_streaming_count():
+    pts = random_points(5000)
+    vs = []
+    trs = []
+
+    def f(v, t):
+        vs.extend(v.tolist())
+        trs.extend(t.tolist())
+
+    s = startinpy.StreamingDT(100.0, callback=f)
+    for i in range(0, len(pts), 1000):
+        s.count(pts[i : i + 1000])
+    assert s.grid_size() == (10, 10)
+    maxv = 0
+    for i in range(0, len(pts), 250):
+        s.insert(pts[i : i + 250])
+        maxv = max(maxv, s.number_of_vertices())
+    s.finalise()
+    assert maxv < len(pts)
+    assert s.number_of_emitted_vertices() == len(pts)
+    dt = startinpy.DT()
+    dt.insert(pts)
+    assert s.number_of_emitted_triangles() == dt.number_of_triangles()
+    assert trs_xy(vs, trs) == trs_xy(dt.points, dt.triangles)
+
+
+def test_streaming_errors(tmp_path):
+    ofile = tmp_path / "out.obj"
+    s = startinpy.StreamingDT(100.0, path=str(ofile))
+    with pytest.raises(ValueError):
+        s.insert([[10.0, 10.0, 1.0]])
+    s.count([[10.0, 10.0, 1.0], [90.0, 20.0, 2.0], [50.0, 80.0, 3.0]])
+    s.count([[150.0, 50.0, 1.0]])
+    assert s.grid_size() == (2, 1)
+    s.insert([[10.0, 10.0, 1.0], [90.0, 20.0, 2.0], [50.0, 80.0, 3.0]])
+    # -- the cell (0, 0) has all its points
+    with pytest.raises(ValueError):
+        s.insert([[20.0, 20.0, 1.0]])
+    with pytest.raises(ValueError):
+        s.insert([[250.0, 20.0, 1.0]])
+    with pytest.raises(ValueError):
+        s.count([[150.0, 50.0, 1.0]])
+    s.insert([[150.0, 50.0, 1.0]])
+    s.finalise()
+    with open(ofile) as f:
+        lines = f.readlines()
+        assert sum(1 for l in lines if l[0] == "v") == 4
+        assert sum(1 for l in lines if l[0] == "f") == 2
+        assert s.number_of_emitted_triangles() == 2
@@ -56,3 +56,21 @@ def test_adjacent_vertices_to_vertex():
     # -- includes the infinity vertex
     vs = dt.adjacent_vertices_to_vertex(1)
     assert len(vs) == 4
+
+
+def test_is_valid():
+    dt = dt_5_points()
+    re = dt.is_valid()
+    assert re["valid"] == True
+    assert re["stars"] == [] and re["orientation"] == []
+    assert re["convex_hull"] == [] and re["delaunay"] == []
+    rng = np.random.default_rng(5)
+    dt = startinpy.DT()
+    dt.insert(rng.random((1000, 3)) * 100.0)
+    dt.remove_many(np.arange(1, 600, 3))
+    for p in rng.random((200, 3)) * 100.0:
+        dt.insert_one_pt(p)
+    dt.move_vertex(2, [50.0, 50.0, 1.0])
+    assert dt.is_valid()["valid"] == True
+    assert dt.is_valid(check_delaunay=False)["valid"] == True
+    assert startinpy.DT().is_valid()["valid"] == True